@@ -0,0 +1,5 @@
+CREATE TABLE IF NOT EXISTS antifraud_verdicts (
+    user_email TEXT PRIMARY KEY,
+    ok BOOLEAN NOT NULL,
+    cache_until TIMESTAMPTZ NOT NULL
+);
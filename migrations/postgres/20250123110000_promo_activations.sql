@@ -0,0 +1,9 @@
+CREATE TABLE IF NOT EXISTS promo_activations (
+    id BIGSERIAL PRIMARY KEY,
+    promo_id TEXT NOT NULL,
+    user_id TEXT NOT NULL,
+    activated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS promo_activations_user_id_activated_at_idx
+    ON promo_activations (user_id, activated_at DESC);
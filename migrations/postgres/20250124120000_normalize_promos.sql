@@ -0,0 +1,135 @@
+ALTER TABLE users ADD PRIMARY KEY (id);
+ALTER TABLE companies ADD PRIMARY KEY (id);
+ALTER TABLE promos ADD PRIMARY KEY (promo_id);
+
+CREATE TABLE IF NOT EXISTS promo_likes (
+    promo_id TEXT NOT NULL REFERENCES promos (promo_id) ON DELETE CASCADE,
+    user_id TEXT NOT NULL REFERENCES users (id) ON DELETE CASCADE,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (promo_id, user_id)
+);
+
+CREATE INDEX IF NOT EXISTS promo_likes_user_id_idx ON promo_likes (user_id);
+
+CREATE TABLE IF NOT EXISTS promo_comments (
+    id TEXT PRIMARY KEY,
+    promo_id TEXT NOT NULL REFERENCES promos (promo_id) ON DELETE CASCADE,
+    user_id TEXT NOT NULL REFERENCES users (id) ON DELETE CASCADE,
+    text TEXT NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS promo_comments_promo_id_created_at_idx
+    ON promo_comments (promo_id, created_at DESC);
+
+CREATE TABLE IF NOT EXISTS promo_unique_codes (
+    id BIGSERIAL PRIMARY KEY,
+    promo_id TEXT NOT NULL REFERENCES promos (promo_id) ON DELETE CASCADE,
+    code TEXT NOT NULL,
+    position INTEGER NOT NULL,
+    activated_by TEXT REFERENCES users (id) ON DELETE SET NULL,
+    activated_at TIMESTAMPTZ
+);
+
+CREATE INDEX IF NOT EXISTS promo_unique_codes_promo_id_position_idx
+    ON promo_unique_codes (promo_id, position);
+
+CREATE INDEX IF NOT EXISTS promo_unique_codes_available_idx
+    ON promo_unique_codes (promo_id, position)
+    WHERE activated_at IS NULL;
+
+CREATE TABLE IF NOT EXISTS promo_countries (
+    promo_id TEXT NOT NULL REFERENCES promos (promo_id) ON DELETE CASCADE,
+    country TEXT NOT NULL,
+    activate_count INTEGER NOT NULL DEFAULT 0,
+    PRIMARY KEY (promo_id, country)
+);
+
+DELETE FROM promo_activations
+WHERE promo_id NOT IN (SELECT promo_id FROM promos)
+    OR user_id NOT IN (SELECT id FROM users);
+
+ALTER TABLE promo_activations
+    ADD FOREIGN KEY (promo_id) REFERENCES promos (promo_id) ON DELETE CASCADE,
+    ADD FOREIGN KEY (user_id) REFERENCES users (id) ON DELETE CASCADE;
+
+CREATE INDEX IF NOT EXISTS promo_activations_promo_id_idx ON promo_activations (promo_id);
+
+-- Move the data out of the JSON columns. Likes, comment authors and
+-- activations were keyed by email, so they are resolved to user ids here;
+-- entries whose user no longer exists are dropped.
+
+INSERT INTO promo_likes (promo_id, user_id)
+SELECT p.promo_id, u.id
+FROM promos p
+CROSS JOIN LATERAL json_array_elements_text(
+    CASE WHEN json_typeof(p.likes) = 'array' THEN p.likes ELSE '[]'::json END
+) AS l(email)
+JOIN users u ON u.email = l.email
+ON CONFLICT DO NOTHING;
+
+INSERT INTO promo_comments (id, promo_id, user_id, text, created_at)
+SELECT c->>'id', p.promo_id, u.id, c->>'text', (c->>'date')::timestamptz
+FROM promos p
+CROSS JOIN LATERAL json_array_elements(
+    CASE WHEN json_typeof(p.comments) = 'array' THEN p.comments ELSE '[]'::json END
+) AS c
+JOIN users u ON u.email = c->'author'->>'email'
+ON CONFLICT DO NOTHING;
+
+INSERT INTO promo_activations (promo_id, user_id)
+SELECT p.promo_id, u.id
+FROM promos p
+CROSS JOIN LATERAL json_array_elements_text(
+    CASE WHEN json_typeof(p.activated_users) = 'array' THEN p.activated_users ELSE '[]'::json END
+) AS a(email)
+JOIN users u ON u.email = a.email
+WHERE NOT EXISTS (
+    SELECT 1 FROM promo_activations pa
+    WHERE pa.promo_id = p.promo_id AND pa.user_id = u.id
+);
+
+INSERT INTO promo_unique_codes (promo_id, code, position)
+SELECT p.promo_id, c.code, c.position
+FROM promos p
+CROSS JOIN LATERAL json_array_elements_text(
+    CASE WHEN json_typeof(p.promo_unique) = 'array' THEN p.promo_unique ELSE '[]'::json END
+) WITH ORDINALITY AS c(code, position);
+
+INSERT INTO promo_countries (promo_id, country, activate_count)
+SELECT p.promo_id, lower(c->>'name'), SUM((c->>'activate_count')::INTEGER)
+FROM promos p
+CROSS JOIN LATERAL json_array_elements(
+    CASE WHEN json_typeof(p.countries) = 'array' THEN p.countries ELSE '[]'::json END
+) AS c
+GROUP BY p.promo_id, lower(c->>'name');
+
+UPDATE promos SET used_count = (
+    SELECT COUNT(*) FROM promo_activations a WHERE a.promo_id = promos.promo_id
+);
+
+UPDATE promos SET active = FALSE WHERE active IS NULL;
+
+-- Dates used to be stored as JSON-encoded strings in TEXT columns.
+
+ALTER TABLE promos
+    DROP COLUMN likes,
+    DROP COLUMN comments,
+    DROP COLUMN countries,
+    DROP COLUMN activated_users,
+    DROP COLUMN promo_unique,
+    ALTER COLUMN target TYPE JSONB USING target::jsonb,
+    ALTER COLUMN create_date TYPE TIMESTAMPTZ
+        USING trim(both '"' from create_date)::timestamptz,
+    ALTER COLUMN active_from TYPE DATE
+        USING NULLIF(trim(both '"' from active_from), 'null')::date,
+    ALTER COLUMN active_until TYPE DATE
+        USING NULLIF(trim(both '"' from active_until), 'null')::date,
+    ALTER COLUMN create_date SET DEFAULT NOW(),
+    ALTER COLUMN used_count SET DEFAULT 0,
+    ALTER COLUMN used_count SET NOT NULL,
+    ALTER COLUMN active SET DEFAULT FALSE,
+    ALTER COLUMN active SET NOT NULL;
+
+CREATE INDEX IF NOT EXISTS promos_company_id_create_date_idx
+    ON promos (company_id, create_date DESC);
@@ -0,0 +1,31 @@
+CREATE TABLE IF NOT EXISTS company_members (
+    id TEXT PRIMARY KEY NOT NULL,
+    company_id TEXT NOT NULL REFERENCES companies (id) ON DELETE CASCADE,
+    name TEXT NOT NULL,
+    email TEXT NOT NULL UNIQUE,
+    password_hash TEXT NOT NULL,
+    role TEXT NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS company_members_company_id_idx ON company_members (company_id);
+
+-- Existing company logins become owner accounts. They keep the company id as
+-- their member id so tokens issued before the migration stay valid.
+INSERT INTO company_members (id, company_id, name, email, password_hash, role)
+SELECT id, id, name, email, password_hash, 'owner' FROM companies;
+
+ALTER TABLE companies DROP COLUMN email;
+ALTER TABLE companies DROP COLUMN password_hash;
+
+CREATE TABLE IF NOT EXISTS company_invitations (
+    id TEXT PRIMARY KEY NOT NULL,
+    company_id TEXT NOT NULL REFERENCES companies (id) ON DELETE CASCADE,
+    email TEXT NOT NULL,
+    role TEXT NOT NULL,
+    token_hash TEXT NOT NULL UNIQUE,
+    created_at TIMESTAMPTZ NOT NULL,
+    expires_at TIMESTAMPTZ NOT NULL,
+    accepted_at TIMESTAMPTZ
+);
+
+CREATE INDEX IF NOT EXISTS company_invitations_company_id_idx ON company_invitations (company_id);
@@ -0,0 +1,17 @@
+-- One row per signed-in device. The id doubles as the refresh token family
+-- id, `access_token_id` is the `jti` of the only access token the session
+-- currently accepts.
+CREATE TABLE IF NOT EXISTS user_sessions (
+    id TEXT PRIMARY KEY NOT NULL,
+    user_id TEXT NOT NULL,
+    access_token_id TEXT NOT NULL,
+    ip_address TEXT NOT NULL,
+    user_agent TEXT,
+    device TEXT,
+    created_at TIMESTAMPTZ NOT NULL,
+    last_seen_at TIMESTAMPTZ NOT NULL,
+    expires_at TIMESTAMPTZ NOT NULL,
+    revoked_at TIMESTAMPTZ
+);
+
+CREATE INDEX IF NOT EXISTS user_sessions_user_id_idx ON user_sessions (user_id);
@@ -0,0 +1,3 @@
+-- Deleted accounts are kept as anonymous rows, so that their likes and
+-- activations still count towards promo statistics. See `user::account`.
+ALTER TABLE users ADD COLUMN deleted_at TIMESTAMPTZ;
@@ -0,0 +1,8 @@
+-- The `russian` configuration stems Cyrillic words as Russian and Latin ones
+-- as English, which covers the languages promos are written in.
+ALTER TABLE promos ADD COLUMN search_vector TSVECTOR GENERATED ALWAYS AS (
+    setweight(to_tsvector('russian', description), 'A')
+    || setweight(to_tsvector('russian', company_name), 'B')
+) STORED;
+
+CREATE INDEX IF NOT EXISTS promos_search_vector_idx ON promos USING GIN (search_vector);
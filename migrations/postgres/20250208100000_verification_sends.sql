@@ -0,0 +1,14 @@
+-- When a verification link last went out to each account. Resends claim the
+-- row with a single conditional upsert, so concurrent requests can't both get
+-- past the throttle.
+CREATE TABLE IF NOT EXISTS email_verification_sends (
+    audience TEXT NOT NULL,
+    subject_id TEXT NOT NULL,
+    sent_at TIMESTAMPTZ NOT NULL,
+    PRIMARY KEY (audience, subject_id)
+);
+
+INSERT INTO email_verification_sends (audience, subject_id, sent_at)
+SELECT audience, subject_id, MAX(created_at) FROM email_verification_tokens
+GROUP BY audience, subject_id
+ON CONFLICT DO NOTHING;
@@ -0,0 +1,93 @@
+CREATE TABLE IF NOT EXISTS companies (
+    id TEXT PRIMARY KEY NOT NULL,
+    name TEXT NOT NULL,
+    email TEXT NOT NULL,
+    password_hash TEXT NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS users (
+    id TEXT PRIMARY KEY NOT NULL,
+    name TEXT NOT NULL,
+    surname TEXT NOT NULL,
+    email TEXT NOT NULL,
+    avatar_url TEXT,
+    other TEXT NOT NULL,
+    password_hash TEXT NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS promos (
+    promo_id TEXT PRIMARY KEY NOT NULL,
+    description TEXT NOT NULL,
+    image_url TEXT,
+    target TEXT NOT NULL,
+    max_count INTEGER NOT NULL,
+    create_date TEXT NOT NULL,
+    active_from TEXT,
+    active_until TEXT,
+    mode TEXT NOT NULL,
+    promo_common TEXT,
+    company_id TEXT NOT NULL,
+    company_name TEXT NOT NULL,
+    used_count INTEGER NOT NULL DEFAULT 0,
+    active BOOLEAN NOT NULL DEFAULT FALSE
+);
+
+CREATE INDEX IF NOT EXISTS promos_company_id_create_date_idx
+    ON promos (company_id, create_date DESC);
+
+CREATE TABLE IF NOT EXISTS promo_likes (
+    promo_id TEXT NOT NULL REFERENCES promos (promo_id) ON DELETE CASCADE,
+    user_id TEXT NOT NULL REFERENCES users (id) ON DELETE CASCADE,
+    created_at TEXT NOT NULL,
+    PRIMARY KEY (promo_id, user_id)
+);
+
+CREATE INDEX IF NOT EXISTS promo_likes_user_id_idx ON promo_likes (user_id);
+
+CREATE TABLE IF NOT EXISTS promo_comments (
+    id TEXT PRIMARY KEY NOT NULL,
+    promo_id TEXT NOT NULL REFERENCES promos (promo_id) ON DELETE CASCADE,
+    user_id TEXT NOT NULL REFERENCES users (id) ON DELETE CASCADE,
+    text TEXT NOT NULL,
+    created_at TEXT NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS promo_comments_promo_id_created_at_idx
+    ON promo_comments (promo_id, created_at DESC);
+
+CREATE TABLE IF NOT EXISTS promo_unique_codes (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    promo_id TEXT NOT NULL REFERENCES promos (promo_id) ON DELETE CASCADE,
+    code TEXT NOT NULL,
+    position INTEGER NOT NULL,
+    activated_by TEXT REFERENCES users (id) ON DELETE SET NULL,
+    activated_at TEXT
+);
+
+CREATE INDEX IF NOT EXISTS promo_unique_codes_promo_id_position_idx
+    ON promo_unique_codes (promo_id, position);
+
+CREATE TABLE IF NOT EXISTS promo_countries (
+    promo_id TEXT NOT NULL REFERENCES promos (promo_id) ON DELETE CASCADE,
+    country TEXT NOT NULL,
+    activate_count INTEGER NOT NULL DEFAULT 0,
+    PRIMARY KEY (promo_id, country)
+);
+
+CREATE TABLE IF NOT EXISTS promo_activations (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    promo_id TEXT NOT NULL REFERENCES promos (promo_id) ON DELETE CASCADE,
+    user_id TEXT NOT NULL REFERENCES users (id) ON DELETE CASCADE,
+    activated_at TEXT NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS promo_activations_user_id_activated_at_idx
+    ON promo_activations (user_id, activated_at DESC);
+
+CREATE INDEX IF NOT EXISTS promo_activations_promo_id_idx ON promo_activations (promo_id);
+
+CREATE TABLE IF NOT EXISTS antifraud_verdicts (
+    user_email TEXT PRIMARY KEY NOT NULL,
+    ok BOOLEAN NOT NULL,
+    cache_until TEXT NOT NULL
+);
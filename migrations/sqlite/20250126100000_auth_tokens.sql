@@ -0,0 +1,12 @@
+CREATE TABLE IF NOT EXISTS issued_tokens (
+    jti TEXT PRIMARY KEY NOT NULL,
+    subject_id TEXT NOT NULL,
+    expires_at TEXT NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS issued_tokens_subject_id_idx ON issued_tokens (subject_id);
+
+CREATE TABLE IF NOT EXISTS revoked_tokens (
+    jti TEXT PRIMARY KEY NOT NULL,
+    expires_at TEXT NOT NULL
+);
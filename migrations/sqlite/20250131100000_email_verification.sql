@@ -0,0 +1,19 @@
+ALTER TABLE users ADD COLUMN email_verified_at TEXT;
+ALTER TABLE company_members ADD COLUMN email_verified_at TEXT;
+
+-- Accounts created before verification existed are trusted as they are.
+UPDATE users SET email_verified_at = strftime('%Y-%m-%dT%H:%M:%S+00:00', 'now');
+UPDATE company_members SET email_verified_at = strftime('%Y-%m-%dT%H:%M:%S+00:00', 'now');
+
+CREATE TABLE IF NOT EXISTS email_verification_tokens (
+    id TEXT PRIMARY KEY NOT NULL,
+    audience TEXT NOT NULL,
+    subject_id TEXT NOT NULL,
+    token_hash TEXT NOT NULL UNIQUE,
+    created_at TEXT NOT NULL,
+    expires_at TEXT NOT NULL,
+    used_at TEXT
+);
+
+CREATE INDEX IF NOT EXISTS email_verification_tokens_audience_subject_id_idx
+    ON email_verification_tokens (audience, subject_id, created_at DESC);
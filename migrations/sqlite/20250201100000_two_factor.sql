@@ -0,0 +1,29 @@
+-- A row with enabled_at NULL is an enrollment that has not been confirmed
+-- with a code yet.
+CREATE TABLE IF NOT EXISTS member_totp (
+    member_id TEXT PRIMARY KEY NOT NULL REFERENCES company_members (id) ON DELETE CASCADE,
+    secret TEXT NOT NULL,
+    created_at TEXT NOT NULL,
+    enabled_at TEXT,
+    last_used_step INTEGER
+);
+
+CREATE TABLE IF NOT EXISTS member_recovery_codes (
+    id TEXT PRIMARY KEY NOT NULL,
+    member_id TEXT NOT NULL REFERENCES company_members (id) ON DELETE CASCADE,
+    code_hash TEXT NOT NULL,
+    used_at TEXT
+);
+
+CREATE INDEX IF NOT EXISTS member_recovery_codes_member_id_idx
+    ON member_recovery_codes (member_id);
+
+CREATE TABLE IF NOT EXISTS two_factor_challenges (
+    id TEXT PRIMARY KEY NOT NULL,
+    member_id TEXT NOT NULL REFERENCES company_members (id) ON DELETE CASCADE,
+    token_hash TEXT NOT NULL UNIQUE,
+    created_at TEXT NOT NULL,
+    expires_at TEXT NOT NULL,
+    attempts INTEGER NOT NULL DEFAULT 0,
+    used_at TEXT
+);
@@ -0,0 +1,9 @@
+-- Keyed by audience and email or by client address, see `sign_in_attempts`.
+CREATE TABLE IF NOT EXISTS sign_in_failures (
+    attempt_key TEXT PRIMARY KEY NOT NULL,
+    failures INTEGER NOT NULL,
+    last_failed_at TEXT NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS sign_in_failures_last_failed_at_idx
+    ON sign_in_failures (last_failed_at);
@@ -0,0 +1,17 @@
+-- One row per signed-in device. The id doubles as the refresh token family
+-- id, `access_token_id` is the `jti` of the only access token the session
+-- currently accepts.
+CREATE TABLE IF NOT EXISTS user_sessions (
+    id TEXT PRIMARY KEY NOT NULL,
+    user_id TEXT NOT NULL,
+    access_token_id TEXT NOT NULL,
+    ip_address TEXT NOT NULL,
+    user_agent TEXT,
+    device TEXT,
+    created_at TEXT NOT NULL,
+    last_seen_at TEXT NOT NULL,
+    expires_at TEXT NOT NULL,
+    revoked_at TEXT
+);
+
+CREATE INDEX IF NOT EXISTS user_sessions_user_id_idx ON user_sessions (user_id);
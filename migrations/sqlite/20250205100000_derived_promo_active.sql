@@ -0,0 +1,3 @@
+-- `active` is derived from the dates and remaining codes whenever a promo is
+-- read, so the stored flag, which was never updated, goes away.
+ALTER TABLE promos DROP COLUMN active;
@@ -0,0 +1,26 @@
+-- Full-text index over promos, kept in step with the table by the triggers
+-- below. The porter stemmer only knows English.
+CREATE VIRTUAL TABLE IF NOT EXISTS promo_search USING fts5 (
+    promo_id UNINDEXED,
+    description,
+    company_name,
+    tokenize = 'porter unicode61 remove_diacritics 2'
+);
+
+INSERT INTO promo_search (promo_id, description, company_name)
+SELECT promo_id, description, company_name FROM promos;
+
+CREATE TRIGGER IF NOT EXISTS promo_search_insert AFTER INSERT ON promos BEGIN
+    INSERT INTO promo_search (promo_id, description, company_name)
+    VALUES (new.promo_id, new.description, new.company_name);
+END;
+
+CREATE TRIGGER IF NOT EXISTS promo_search_update AFTER UPDATE OF description, company_name ON promos BEGIN
+    UPDATE promo_search
+    SET description = new.description, company_name = new.company_name
+    WHERE promo_id = new.promo_id;
+END;
+
+CREATE TRIGGER IF NOT EXISTS promo_search_delete AFTER DELETE ON promos BEGIN
+    DELETE FROM promo_search WHERE promo_id = old.promo_id;
+END;
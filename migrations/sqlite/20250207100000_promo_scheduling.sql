@@ -0,0 +1,37 @@
+-- A promo goes live at `publish_at` (right away when it is NULL), and
+-- `published_at` records when it did. Promos created before scheduling was
+-- introduced count as published on creation.
+ALTER TABLE promos ADD COLUMN publish_at TEXT;
+ALTER TABLE promos ADD COLUMN published_at TEXT;
+UPDATE promos SET published_at = create_date;
+
+-- Background jobs, see `scheduler`. A job is due once `run_at` has passed,
+-- `locked_until` keeps other runs off it while it executes, and
+-- `completed_at` is set when it has run or was given up on.
+CREATE TABLE IF NOT EXISTS scheduled_jobs (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    kind TEXT NOT NULL,
+    promo_id TEXT NOT NULL REFERENCES promos (promo_id) ON DELETE CASCADE,
+    run_at TEXT NOT NULL,
+    attempts INTEGER NOT NULL DEFAULT 0,
+    locked_until TEXT,
+    last_error TEXT,
+    created_at TEXT NOT NULL,
+    completed_at TEXT
+);
+
+CREATE INDEX IF NOT EXISTS scheduled_jobs_due_idx
+    ON scheduled_jobs (run_at)
+    WHERE completed_at IS NULL;
+
+CREATE INDEX IF NOT EXISTS scheduled_jobs_promo_id_idx ON scheduled_jobs (promo_id, kind);
+
+-- Lifecycle transitions of promos: `published`, `expired` and `exhausted`.
+CREATE TABLE IF NOT EXISTS promo_events (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    promo_id TEXT NOT NULL REFERENCES promos (promo_id) ON DELETE CASCADE,
+    kind TEXT NOT NULL,
+    occurred_at TEXT NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS promo_events_promo_id_idx ON promo_events (promo_id, id);
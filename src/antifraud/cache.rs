@@ -0,0 +1,70 @@
+use super::Verdict;
+use crate::repository::{Repository, VerdictRepository};
+use chrono::{DateTime, Utc};
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+};
+
+// Verdicts are kept in memory for the hot path and mirrored to the database, so
+// `cache_until` is still honored after the server restarts.
+#[derive(Clone)]
+pub struct VerdictCache {
+    repository: Repository,
+    verdicts: Arc<Mutex<HashMap<String, Verdict>>>,
+}
+
+impl VerdictCache {
+    pub fn new(repository: Repository) -> Self {
+        VerdictCache {
+            repository,
+            verdicts: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    pub async fn get(
+        &self,
+        user_email: &str,
+        now: DateTime<Utc>,
+    ) -> Result<Option<Verdict>, sqlx::Error> {
+        if let Some(verdict) = self.verdicts.lock().unwrap().get(user_email) {
+            if verdict
+                .cache_until
+                .is_some_and(|cache_until| cache_until > now)
+            {
+                return Ok(Some(*verdict));
+            }
+        }
+
+        let cached = self.repository.retrieve_verdict(user_email, now).await?;
+
+        let mut verdicts = self.verdicts.lock().unwrap();
+        match cached {
+            Some(verdict) => {
+                verdicts.insert(user_email.to_string(), verdict);
+                Ok(Some(verdict))
+            }
+            None => {
+                verdicts.remove(user_email);
+                Ok(None)
+            }
+        }
+    }
+
+    pub async fn put(&self, user_email: &str, verdict: Verdict) -> Result<(), sqlx::Error> {
+        let Some(cache_until) = verdict.cache_until else {
+            return Ok(());
+        };
+
+        self.repository
+            .store_verdict(user_email, verdict.ok, cache_until)
+            .await?;
+
+        self.verdicts
+            .lock()
+            .unwrap()
+            .insert(user_email.to_string(), verdict);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,173 @@
+use chrono::{DateTime, NaiveDateTime, Utc};
+use reqwest::StatusCode;
+use serde::{Deserialize, Serialize};
+use std::{fmt, time::Duration};
+
+pub mod cache;
+#[cfg(test)]
+mod tests;
+
+use crate::repository::Repository;
+use cache::VerdictCache;
+
+// A request that failed in transit or got a 5xx response is retried once.
+// Any other response, including one we cannot read, is the service's answer
+// and trying again would only repeat it.
+const MAX_ATTEMPTS: usize = 2;
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(5);
+
+#[derive(Serialize)]
+struct ValidateRequest<'a> {
+    user_email: &'a str,
+    promo_id: &'a str,
+}
+
+#[derive(Deserialize)]
+struct ValidateResponse {
+    ok: bool,
+    cache_until: Option<String>,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct Verdict {
+    pub ok: bool,
+    pub cache_until: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug)]
+pub enum AntifraudError {
+    Request(reqwest::Error),
+    Status(StatusCode),
+    Decode(serde_json::Error),
+}
+
+impl AntifraudError {
+    fn is_retryable(&self) -> bool {
+        match self {
+            AntifraudError::Request(_) => true,
+            AntifraudError::Status(status) => status.is_server_error(),
+            AntifraudError::Decode(_) => false,
+        }
+    }
+}
+
+impl fmt::Display for AntifraudError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AntifraudError::Request(err) => write!(f, "antifraud request failed: {}", err),
+            AntifraudError::Status(status) => write!(f, "antifraud responded with {}", status),
+            AntifraudError::Decode(err) => write!(f, "invalid antifraud response: {}", err),
+        }
+    }
+}
+
+impl From<reqwest::Error> for AntifraudError {
+    fn from(err: reqwest::Error) -> Self {
+        AntifraudError::Request(err)
+    }
+}
+
+#[derive(Clone)]
+pub struct AntifraudClient {
+    http: reqwest::Client,
+    validate_url: String,
+    cache: VerdictCache,
+}
+
+impl AntifraudClient {
+    pub fn new(address: &str, repository: Repository) -> Self {
+        let base_url = if address.starts_with("http://") || address.starts_with("https://") {
+            address.trim_end_matches('/').to_string()
+        } else {
+            format!("http://{}", address.trim_end_matches('/'))
+        };
+
+        AntifraudClient {
+            http: reqwest::Client::builder()
+                .timeout(REQUEST_TIMEOUT)
+                .build()
+                .expect("Unable to build antifraud http client"),
+            validate_url: format!("{}/api/validate", base_url),
+            cache: VerdictCache::new(repository),
+        }
+    }
+
+    pub async fn validate(
+        &self,
+        user_email: &str,
+        promo_id: &str,
+    ) -> Result<Verdict, AntifraudError> {
+        let now = Utc::now();
+        match self.cache.get(user_email, now).await {
+            Ok(Some(verdict)) => return Ok(verdict),
+            Ok(None) => (),
+            Err(err) => tracing::warn!("Unable to read cached antifraud verdict: {}", err),
+        }
+
+        let verdict = self.request_verdict(user_email, promo_id).await?;
+
+        if let Some(cache_until) = verdict.cache_until {
+            if cache_until > now {
+                if let Err(err) = self.cache.put(user_email, verdict).await {
+                    tracing::warn!("Unable to cache antifraud verdict: {}", err);
+                }
+            }
+        }
+
+        Ok(verdict)
+    }
+
+    async fn request_verdict(
+        &self,
+        user_email: &str,
+        promo_id: &str,
+    ) -> Result<Verdict, AntifraudError> {
+        let mut attempt = 1;
+        loop {
+            match self.send(user_email, promo_id).await {
+                Err(err) if err.is_retryable() && attempt < MAX_ATTEMPTS => {
+                    tracing::warn!("Retrying antifraud request: {}", err);
+                    attempt += 1;
+                }
+                result => return result,
+            }
+        }
+    }
+
+    async fn send(&self, user_email: &str, promo_id: &str) -> Result<Verdict, AntifraudError> {
+        let response = self
+            .http
+            .post(&self.validate_url)
+            .json(&ValidateRequest {
+                user_email,
+                promo_id,
+            })
+            .send()
+            .await?;
+
+        if response.status() != StatusCode::OK {
+            return Err(AntifraudError::Status(response.status()));
+        }
+
+        let body = response.bytes().await?;
+        let body: ValidateResponse =
+            serde_json::from_slice(&body).map_err(AntifraudError::Decode)?;
+
+        Ok(Verdict {
+            ok: body.ok,
+            cache_until: body.cache_until.as_deref().and_then(parse_cache_until),
+        })
+    }
+}
+
+// `cache_until` comes without an offset ("2025-01-16T00:17:57.567") and is
+// always UTC+0, but accept RFC 3339 as well in case the service adds one.
+fn parse_cache_until(value: &str) -> Option<DateTime<Utc>> {
+    if let Ok(date_time) = DateTime::parse_from_rfc3339(value) {
+        return Some(date_time.with_timezone(&Utc));
+    }
+
+    NaiveDateTime::parse_from_str(value, "%Y-%m-%dT%H:%M:%S%.f")
+        .ok()
+        .map(|date_time| date_time.and_utc())
+}
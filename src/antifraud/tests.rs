@@ -0,0 +1,219 @@
+use super::{AntifraudClient, AntifraudError};
+use crate::{error::ACTIVATION_FORBIDDEN, repository::PromoRepository, testing};
+use axum::{extract::State, http::StatusCode, routing::post, Router};
+use chrono::{Duration, Utc};
+use serde_json::{json, Value};
+use std::{
+    collections::VecDeque,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, Mutex,
+    },
+};
+use tokio::net::TcpListener;
+
+// Answers `/api/validate` with the given responses in order and counts the
+// requests it got.
+struct StandIn {
+    responses: Mutex<VecDeque<(StatusCode, String)>>,
+    calls: AtomicUsize,
+}
+
+impl StandIn {
+    fn calls(&self) -> usize {
+        self.calls.load(Ordering::SeqCst)
+    }
+}
+
+async fn start_stand_in(responses: &[(StatusCode, &str)]) -> (String, Arc<StandIn>) {
+    let stand_in = Arc::new(StandIn {
+        responses: Mutex::new(
+            responses
+                .iter()
+                .map(|(status, body)| (*status, body.to_string()))
+                .collect(),
+        ),
+        calls: AtomicUsize::new(0),
+    });
+
+    let app = Router::new()
+        .route(
+            "/api/validate",
+            post(|State(stand_in): State<Arc<StandIn>>| async move {
+                stand_in.calls.fetch_add(1, Ordering::SeqCst);
+                stand_in.responses.lock().unwrap().pop_front().unwrap_or((
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    "no response left".to_string(),
+                ))
+            }),
+        )
+        .with_state(stand_in.clone());
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let address = listener.local_addr().unwrap();
+    tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+
+    (address.to_string(), stand_in)
+}
+
+fn verdict_body(ok: bool, cache_until: Option<chrono::DateTime<Utc>>) -> String {
+    let mut body = json!({ "ok": ok });
+    if let Some(cache_until) = cache_until {
+        // The service sends the time without an offset.
+        body["cache_until"] = json!(cache_until.format("%Y-%m-%dT%H:%M:%S%.3f").to_string());
+    }
+    body.to_string()
+}
+
+#[cfg_attr(feature = "postgres", ignore = "needs TEST_DATABASE_URL")]
+#[tokio::test]
+async fn server_errors_are_retried_once() {
+    let repository = testing::repository().await;
+    let accepted = verdict_body(true, None);
+    let (address, stand_in) = start_stand_in(&[
+        (StatusCode::SERVICE_UNAVAILABLE, ""),
+        (StatusCode::OK, &accepted),
+    ])
+    .await;
+    let client = AntifraudClient::new(&address, repository.clone());
+
+    let verdict = client.validate("user@example.com", "promo").await.unwrap();
+    assert!(verdict.ok);
+    assert_eq!(stand_in.calls(), 2);
+
+    let (address, stand_in) = start_stand_in(&[
+        (StatusCode::INTERNAL_SERVER_ERROR, ""),
+        (StatusCode::BAD_GATEWAY, ""),
+    ])
+    .await;
+    let client = AntifraudClient::new(&address, repository);
+
+    let result = client.validate("user@example.com", "promo").await;
+    assert!(matches!(
+        result,
+        Err(AntifraudError::Status(StatusCode::BAD_GATEWAY))
+    ));
+    assert_eq!(stand_in.calls(), 2);
+}
+
+#[cfg_attr(feature = "postgres", ignore = "needs TEST_DATABASE_URL")]
+#[tokio::test]
+async fn client_errors_and_malformed_responses_are_not_retried() {
+    let repository = testing::repository().await;
+    let accepted = verdict_body(true, None);
+
+    let (address, stand_in) =
+        start_stand_in(&[(StatusCode::BAD_REQUEST, ""), (StatusCode::OK, &accepted)]).await;
+    let client = AntifraudClient::new(&address, repository.clone());
+    let result = client.validate("user@example.com", "promo").await;
+    assert!(matches!(
+        result,
+        Err(AntifraudError::Status(StatusCode::BAD_REQUEST))
+    ));
+    assert_eq!(stand_in.calls(), 1);
+
+    let (address, stand_in) =
+        start_stand_in(&[(StatusCode::OK, "not json"), (StatusCode::OK, &accepted)]).await;
+    let client = AntifraudClient::new(&address, repository);
+    let result = client.validate("user@example.com", "promo").await;
+    assert!(matches!(result, Err(AntifraudError::Decode(_))));
+    assert_eq!(stand_in.calls(), 1);
+}
+
+#[cfg_attr(feature = "postgres", ignore = "needs TEST_DATABASE_URL")]
+#[tokio::test]
+async fn unreachable_service_is_an_error() {
+    let repository = testing::repository().await;
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let address = listener.local_addr().unwrap().to_string();
+    drop(listener);
+
+    let client = AntifraudClient::new(&address, repository);
+    let result = client.validate("user@example.com", "promo").await;
+    assert!(matches!(result, Err(AntifraudError::Request(_))));
+}
+
+#[cfg_attr(feature = "postgres", ignore = "needs TEST_DATABASE_URL")]
+#[tokio::test]
+async fn verdicts_are_cached_until_cache_until() {
+    let repository = testing::repository().await;
+    let rejected = verdict_body(false, Some(Utc::now() + Duration::minutes(10)));
+    let (address, stand_in) = start_stand_in(&[(StatusCode::OK, &rejected)]).await;
+
+    let client = AntifraudClient::new(&address, repository.clone());
+    for _ in 0..2 {
+        let verdict = client
+            .validate("cached@example.com", "promo")
+            .await
+            .unwrap();
+        assert!(!verdict.ok);
+    }
+    assert_eq!(stand_in.calls(), 1);
+
+    // The verdict is kept in the database as well, so it outlives a restart.
+    let restarted = AntifraudClient::new(&address, repository.clone());
+    let verdict = restarted
+        .validate("cached@example.com", "promo")
+        .await
+        .unwrap();
+    assert!(!verdict.ok);
+    assert_eq!(stand_in.calls(), 1);
+
+    // Verdicts without a `cache_until` in the future are asked for every time.
+    let expired = verdict_body(true, Some(Utc::now() - Duration::minutes(10)));
+    let uncached = verdict_body(true, None);
+    let (address, stand_in) = start_stand_in(&[
+        (StatusCode::OK, &expired),
+        (StatusCode::OK, &uncached),
+        (StatusCode::OK, &uncached),
+    ])
+    .await;
+    let client = AntifraudClient::new(&address, repository);
+    for _ in 0..3 {
+        assert!(
+            client
+                .validate("fresh@example.com", "promo")
+                .await
+                .unwrap()
+                .ok
+        );
+    }
+    assert_eq!(stand_in.calls(), 3);
+}
+
+#[cfg_attr(feature = "postgres", ignore = "needs TEST_DATABASE_URL")]
+#[tokio::test]
+async fn activation_rejected_by_antifraud_is_forbidden() {
+    let repository = testing::repository().await;
+    let rejected = verdict_body(false, None);
+    let (address, stand_in) = start_stand_in(&[(StatusCode::OK, &rejected)]).await;
+    let state = testing::app_state(repository.clone(), &address).await;
+    let user = testing::user(&repository, 25, "ru").await;
+    let token = testing::user_token(&state, &user).await;
+    let (company, _) = testing::company(&repository).await;
+    let promo = testing::promo(&repository, &company, 10, None, Utc::now()).await;
+    let base_url = testing::serve(state).await;
+
+    let response = reqwest::Client::new()
+        .post(format!(
+            "{}/api/user/promo/{}/activate",
+            base_url, promo.promo_id
+        ))
+        .bearer_auth(token)
+        .json(&Value::Null)
+        .send()
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    let body: Value = response.json().await.unwrap();
+    assert_eq!(body["status"], "error");
+    assert_eq!(body["message"], ACTIVATION_FORBIDDEN);
+    assert_eq!(stand_in.calls(), 1);
+
+    let promo = repository
+        .retrieve_promo(&promo.promo_id, Utc::now().date_naive())
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(promo.used_count, 0);
+}
@@ -0,0 +1,7 @@
+use crate::AppState;
+use axum::{extract::State, Json};
+use jsonwebtoken::jwk::JwkSet;
+
+pub async fn jwks(State(app_state): State<AppState>) -> Json<JwkSet> {
+    Json(app_state.jwt_keys.jwks())
+}
@@ -0,0 +1,395 @@
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use jsonwebtoken::{
+    jwk::{
+        AlgorithmParameters, CommonParameters, EllipticCurve, Jwk, JwkSet, KeyAlgorithm,
+        OctetKeyPairParameters, OctetKeyPairType, PublicKeyUse, RSAKeyParameters, RSAKeyType,
+    },
+    Algorithm, DecodingKey, EncodingKey,
+};
+use ring::{
+    rsa::PublicKeyComponents,
+    signature::{Ed25519KeyPair, KeyPair, RsaKeyPair},
+};
+use sha2::{Digest, Sha256};
+use simple_asn1::{oid, ASN1Block};
+use std::{collections::HashMap, env, fs, path::Path, sync::Arc};
+
+// The key id of the key derived from `RANDOM_SECRET`.
+const SECRET_KEY_ID: &str = "random-secret";
+// DER prefix of an Ed25519 PKCS#8 private key, followed by the 32-byte seed.
+const ED25519_PKCS8_PREFIX: [u8; 16] = [
+    0x30, 0x2e, 0x02, 0x01, 0x00, 0x30, 0x05, 0x06, 0x03, 0x2b, 0x65, 0x70, 0x04, 0x22, 0x04, 0x20,
+];
+
+struct VerificationKey {
+    algorithm: Algorithm,
+    decoding_key: DecodingKey,
+    jwk: Jwk,
+}
+
+// Every `<kid>.pem` in the keys directory is an Ed25519 or RSA key, either a
+// PKCS#8 private key or a public key. The private key named by the signing key
+// id signs new tokens, all of them are accepted for verification and published
+// as the JWKS. To rotate, add the new key, switch the signing key id and keep
+// only the public half of the old one until the access tokens it signed have
+// expired.
+#[derive(Clone)]
+pub struct JwtKeys {
+    signing_kid: String,
+    signing_algorithm: Algorithm,
+    encoding_key: Arc<EncodingKey>,
+    verification_keys: Arc<HashMap<String, VerificationKey>>,
+}
+
+impl JwtKeys {
+    // `JWT_KEYS_DIR` and `JWT_SIGNING_KEY_ID` name the keys directory and the
+    // signing key. Without them a single Ed25519 key is derived from
+    // `RANDOM_SECRET`, so tokens stay valid across restarts as long as the
+    // secret does.
+    pub fn from_env() -> Result<Self, String> {
+        match (env::var("JWT_KEYS_DIR"), env::var("JWT_SIGNING_KEY_ID")) {
+            (Ok(dir), Ok(signing_kid)) => JwtKeys::load(&dir, &signing_kid),
+            (Ok(_), Err(_)) | (Err(_), Ok(_)) => {
+                Err("JWT_KEYS_DIR and JWT_SIGNING_KEY_ID must be set together".to_string())
+            }
+            (Err(_), Err(_)) => match env::var("RANDOM_SECRET") {
+                Ok(secret) if !secret.is_empty() => JwtKeys::from_secret(&secret),
+                _ => Err("set JWT_KEYS_DIR and JWT_SIGNING_KEY_ID, or RANDOM_SECRET".to_string()),
+            },
+        }
+    }
+
+    pub fn from_secret(secret: &str) -> Result<Self, String> {
+        let mut pkcs8 = ED25519_PKCS8_PREFIX.to_vec();
+        pkcs8.extend_from_slice(&Sha256::digest(secret.as_bytes()));
+        let (encoding_key, verification_key) =
+            ed25519_private_key(SECRET_KEY_ID, &pkcs8).ok_or("invalid derived key")?;
+
+        Ok(JwtKeys {
+            signing_kid: SECRET_KEY_ID.to_string(),
+            signing_algorithm: verification_key.algorithm,
+            encoding_key: Arc::new(encoding_key),
+            verification_keys: Arc::new(HashMap::from([(
+                SECRET_KEY_ID.to_string(),
+                verification_key,
+            )])),
+        })
+    }
+
+    pub fn load(dir: &str, signing_kid: &str) -> Result<Self, String> {
+        let mut encoding_keys = HashMap::new();
+        let mut verification_keys = HashMap::new();
+
+        let entries = fs::read_dir(dir).map_err(|err| format!("{}: {}", dir, err))?;
+        for entry in entries {
+            let path = entry.map_err(|err| err.to_string())?.path();
+            if path.extension().is_none_or(|extension| extension != "pem") {
+                continue;
+            }
+
+            let (kid, encoding_key, verification_key) = load_key(&path)?;
+            if let Some(encoding_key) = encoding_key {
+                encoding_keys.insert(kid.clone(), encoding_key);
+            }
+            verification_keys.insert(kid, verification_key);
+        }
+
+        let encoding_key = match encoding_keys.remove(signing_kid) {
+            Some(encoding_key) => encoding_key,
+            None if verification_keys.contains_key(signing_kid) => {
+                return Err(format!(
+                    "signing key {} in {} is a public key",
+                    signing_kid, dir
+                ))
+            }
+            None => return Err(format!("signing key {} not found in {}", signing_kid, dir)),
+        };
+
+        Ok(JwtKeys {
+            signing_kid: signing_kid.to_string(),
+            signing_algorithm: verification_keys[signing_kid].algorithm,
+            encoding_key: Arc::new(encoding_key),
+            verification_keys: Arc::new(verification_keys),
+        })
+    }
+
+    pub fn signing_key(&self) -> (&str, Algorithm, &EncodingKey) {
+        (
+            &self.signing_kid,
+            self.signing_algorithm,
+            &self.encoding_key,
+        )
+    }
+
+    pub fn verification_key(&self, kid: &str) -> Option<(Algorithm, &DecodingKey)> {
+        self.verification_keys
+            .get(kid)
+            .map(|key| (key.algorithm, &key.decoding_key))
+    }
+
+    pub fn jwks(&self) -> JwkSet {
+        let mut keys: Vec<Jwk> = self
+            .verification_keys
+            .values()
+            .map(|key| key.jwk.clone())
+            .collect();
+        keys.sort_by(|a, b| a.common.key_id.cmp(&b.common.key_id));
+
+        JwkSet { keys }
+    }
+}
+
+// Public keys come without an encoding key and can only verify tokens.
+fn load_key(path: &Path) -> Result<(String, Option<EncodingKey>, VerificationKey), String> {
+    let error = |err: String| format!("{}: {}", path.display(), err);
+
+    let kid = path
+        .file_stem()
+        .and_then(|stem| stem.to_str())
+        .ok_or_else(|| error("invalid file name".to_string()))?
+        .to_string();
+    let contents = fs::read(path).map_err(|err| error(err.to_string()))?;
+    let der = pem::parse(&contents).map_err(|err| error(err.to_string()))?;
+
+    match der.tag() {
+        "PRIVATE KEY" => {
+            if let Some((encoding_key, verification_key)) =
+                ed25519_private_key(&kid, der.contents())
+            {
+                Ok((kid, Some(encoding_key), verification_key))
+            } else if let Ok(key_pair) = RsaKeyPair::from_pkcs8(der.contents()) {
+                let components = PublicKeyComponents::<Vec<u8>>::from(key_pair.public());
+                let encoding_key =
+                    EncodingKey::from_rsa_pem(&contents).map_err(|err| error(err.to_string()))?;
+                let verification_key = verification_key(
+                    &kid,
+                    Algorithm::RS256,
+                    rsa_parameters(&components.n, &components.e),
+                )
+                .ok_or_else(|| error("invalid RSA key".to_string()))?;
+                Ok((kid, Some(encoding_key), verification_key))
+            } else {
+                Err(error("unsupported key type".to_string()))
+            }
+        }
+        "PUBLIC KEY" => {
+            let (algorithm, parameters) = parse_public_key(der.contents())
+                .ok_or_else(|| error("unsupported public key".to_string()))?;
+            let verification_key = verification_key(&kid, algorithm, parameters)
+                .ok_or_else(|| error("invalid public key".to_string()))?;
+            Ok((kid, None, verification_key))
+        }
+        _ => Err(error(
+            "expected a PKCS#8 private key or a public key".to_string(),
+        )),
+    }
+}
+
+fn ed25519_private_key(kid: &str, pkcs8: &[u8]) -> Option<(EncodingKey, VerificationKey)> {
+    let key_pair = Ed25519KeyPair::from_pkcs8_maybe_unchecked(pkcs8).ok()?;
+    let verification_key = verification_key(
+        kid,
+        Algorithm::EdDSA,
+        ed25519_parameters(key_pair.public_key().as_ref()),
+    )?;
+    Some((EncodingKey::from_ed_der(pkcs8), verification_key))
+}
+
+// Reads a SubjectPublicKeyInfo holding an Ed25519 or RSA key.
+fn parse_public_key(der: &[u8]) -> Option<(Algorithm, AlgorithmParameters)> {
+    let blocks = simple_asn1::from_der(der).ok()?;
+    let [ASN1Block::Sequence(_, info)] = blocks.as_slice() else {
+        return None;
+    };
+    let [ASN1Block::Sequence(_, identifier), ASN1Block::BitString(_, _, key)] = info.as_slice()
+    else {
+        return None;
+    };
+    let Some(ASN1Block::ObjectIdentifier(_, algorithm)) = identifier.first() else {
+        return None;
+    };
+
+    if *algorithm == oid!(1, 3, 101, 112) {
+        if key.len() != 32 {
+            return None;
+        }
+        Some((Algorithm::EdDSA, ed25519_parameters(key)))
+    } else if *algorithm == oid!(1, 2, 840, 113549, 1, 1, 1) {
+        let blocks = simple_asn1::from_der(key).ok()?;
+        let [ASN1Block::Sequence(_, numbers)] = blocks.as_slice() else {
+            return None;
+        };
+        let [ASN1Block::Integer(_, n), ASN1Block::Integer(_, e)] = numbers.as_slice() else {
+            return None;
+        };
+        Some((
+            Algorithm::RS256,
+            rsa_parameters(&n.to_bytes_be().1, &e.to_bytes_be().1),
+        ))
+    } else {
+        None
+    }
+}
+
+fn ed25519_parameters(public_key: &[u8]) -> AlgorithmParameters {
+    AlgorithmParameters::OctetKeyPair(OctetKeyPairParameters {
+        key_type: OctetKeyPairType::OctetKeyPair,
+        curve: EllipticCurve::Ed25519,
+        x: URL_SAFE_NO_PAD.encode(public_key),
+    })
+}
+
+fn rsa_parameters(n: &[u8], e: &[u8]) -> AlgorithmParameters {
+    AlgorithmParameters::RSA(RSAKeyParameters {
+        key_type: RSAKeyType::RSA,
+        n: URL_SAFE_NO_PAD.encode(n),
+        e: URL_SAFE_NO_PAD.encode(e),
+    })
+}
+
+fn verification_key(
+    kid: &str,
+    algorithm: Algorithm,
+    parameters: AlgorithmParameters,
+) -> Option<VerificationKey> {
+    let jwk = Jwk {
+        common: CommonParameters {
+            public_key_use: Some(PublicKeyUse::Signature),
+            key_algorithm: Some(match algorithm {
+                Algorithm::EdDSA => KeyAlgorithm::EdDSA,
+                _ => KeyAlgorithm::RS256,
+            }),
+            key_id: Some(kid.to_string()),
+            ..Default::default()
+        },
+        algorithm: parameters,
+    };
+    let decoding_key = DecodingKey::from_jwk(&jwk).ok()?;
+
+    Some(VerificationKey {
+        algorithm,
+        decoding_key,
+        jwk,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use jsonwebtoken::{decode, decode_header, encode, Header, Validation};
+    use ring::rand::SystemRandom;
+    use serde_json::{json, Value};
+    use std::path::PathBuf;
+
+    const RSA_PUBLIC_KEY: &str = "-----BEGIN PUBLIC KEY-----
+MIIBIjANBgkqhkiG9w0BAQEFAAOCAQ8AMIIBCgKCAQEAvSdrk9DQbOJOs6J1x3Lp
+sqLoTQcGHgdamuFHwLczoWShj4drWjL6VwDs4a1cuXI3OcAs8MVQI90R19If+Rjk
+OvbLpgFYTRnLFrBC4Tff6yAvHRGig9/DBXNVgfk4VyTNxUW71IDPnBxWVC9CNy43
+KOkZM1hgg6DE2N/NKnADZ3xAsYueyjn+f+yt58bGXpQPubkqeUD/9LpVMvpx/tS/
+FBCQ/DtpwqN9fakklCG99+WrZO9XndiFr0egnjmwK756NYvlCDn4eYoWS1UR8VPp
+AwPspDf9ArqswHmbJXfhy19ChT2DKPmSbRdEfEHYb8bzWDHC4bxKCm+rnAVuQ2Z1
+gQIDAQAB
+-----END PUBLIC KEY-----
+";
+
+    // Returns the private and the public key PEM of a new Ed25519 key.
+    fn ed25519_pems() -> (String, String) {
+        let pkcs8 = Ed25519KeyPair::generate_pkcs8(&SystemRandom::new()).unwrap();
+        let key_pair = Ed25519KeyPair::from_pkcs8(pkcs8.as_ref()).unwrap();
+        let mut spki = vec![
+            0x30, 0x2a, 0x30, 0x05, 0x06, 0x03, 0x2b, 0x65, 0x70, 0x03, 0x21, 0x00,
+        ];
+        spki.extend_from_slice(key_pair.public_key().as_ref());
+
+        (
+            pem::encode(&pem::Pem::new("PRIVATE KEY", pkcs8.as_ref())),
+            pem::encode(&pem::Pem::new("PUBLIC KEY", spki)),
+        )
+    }
+
+    fn keys_dir(files: &[(&str, &str)]) -> PathBuf {
+        let dir = env::temp_dir().join(format!("prod_project-keys-{}", uuid::Uuid::new_v4()));
+        fs::create_dir(&dir).unwrap();
+        for (name, contents) in files {
+            fs::write(dir.join(name), contents).unwrap();
+        }
+        dir
+    }
+
+    fn sign(keys: &JwtKeys) -> String {
+        let (kid, algorithm, encoding_key) = keys.signing_key();
+        let mut header = Header::new(algorithm);
+        header.kid = Some(kid.to_string());
+        encode(&header, &json!({ "sub": "someone" }), encoding_key).unwrap()
+    }
+
+    fn verifies(keys: &JwtKeys, token: &str) -> bool {
+        let kid = decode_header(token).unwrap().kid.unwrap();
+        let Some((algorithm, decoding_key)) = keys.verification_key(&kid) else {
+            return false;
+        };
+        let mut validation = Validation::new(algorithm);
+        validation.required_spec_claims.clear();
+        validation.validate_exp = false;
+        decode::<Value>(token, decoding_key, &validation).is_ok()
+    }
+
+    #[test]
+    fn the_secret_key_survives_restarts() {
+        let keys = JwtKeys::from_secret("a secret").unwrap();
+        let token = sign(&keys);
+
+        assert!(verifies(&JwtKeys::from_secret("a secret").unwrap(), &token));
+        assert!(!verifies(
+            &JwtKeys::from_secret("another secret").unwrap(),
+            &token
+        ));
+        assert_eq!(keys.jwks().keys.len(), 1);
+    }
+
+    #[test]
+    fn public_keys_only_verify() {
+        let (current, _) = ed25519_pems();
+        let (previous, previous_public) = ed25519_pems();
+        let dir = keys_dir(&[("old.pem", &previous)]);
+        let token = sign(&JwtKeys::load(&dir.to_string_lossy(), "old").unwrap());
+        fs::remove_dir_all(dir).unwrap();
+
+        let dir = keys_dir(&[
+            ("new.pem", &current),
+            ("old.pem", &previous_public),
+            ("rsa.pem", RSA_PUBLIC_KEY),
+        ]);
+        let keys = JwtKeys::load(&dir.to_string_lossy(), "new").unwrap();
+        assert!(verifies(&keys, &token));
+        assert!(verifies(&keys, &sign(&keys)));
+        assert_eq!(keys.jwks().keys.len(), 3);
+
+        let error = JwtKeys::load(&dir.to_string_lossy(), "old").err().unwrap();
+        assert!(error.contains("public key"), "{}", error);
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn rsa_public_keys_are_published() {
+        let (ed, _) = ed25519_pems();
+        let dir = keys_dir(&[("ed.pem", &ed), ("rsa.pem", RSA_PUBLIC_KEY)]);
+        let keys = JwtKeys::load(&dir.to_string_lossy(), "ed").unwrap();
+        fs::remove_dir_all(dir).unwrap();
+
+        let jwk = keys
+            .jwks()
+            .keys
+            .into_iter()
+            .find(|jwk| jwk.common.key_id.as_deref() == Some("rsa"))
+            .unwrap();
+        let AlgorithmParameters::RSA(parameters) = jwk.algorithm else {
+            panic!("expected an RSA key");
+        };
+        assert_eq!(parameters.e, "AQAB");
+        let n = URL_SAFE_NO_PAD.decode(parameters.n).unwrap();
+        assert_eq!(n.len(), 256);
+        assert_eq!(n[0], 0xbd);
+        assert_eq!(keys.verification_key("rsa").unwrap().0, Algorithm::RS256);
+    }
+}
@@ -0,0 +1,214 @@
+use crate::{
+    auth::{decode_jwt, hash_token, Audience, Role},
+    email_verification::VerificationPolicy,
+    error::{AppError, EMAIL_NOT_VERIFIED, INSUFFICIENT_ROLE},
+    repository::{ApiKeyRepository, CompanyRepository, MemberRepository, UserRepository},
+    sessions, AppState,
+};
+use axum::{
+    extract::{Request, State},
+    http,
+    middleware::Next,
+    response::Response,
+};
+use chrono::Utc;
+
+pub const BUSINESS_ROLES: &[Role] = &[Role::Owner, Role::Editor, Role::Analyst, Role::Viewer];
+pub const USER_ROLES: &[Role] = &[Role::User];
+
+const API_KEY_HEADER: &str = "x-api-key";
+
+// State for `authorize`: which audience the guarded routes belong to, which
+// roles may call them, whether API keys are accepted and whether the caller's
+// email has to be verified.
+#[derive(Clone)]
+pub struct AuthConfig {
+    pub app_state: AppState,
+    pub audience: Audience,
+    pub roles: &'static [Role],
+    pub api_keys: bool,
+    pub verified_email: bool,
+}
+
+impl AuthConfig {
+    pub fn new(app_state: AppState, audience: Audience, roles: &'static [Role]) -> Self {
+        AuthConfig {
+            app_state,
+            audience,
+            roles,
+            api_keys: audience == Audience::Business,
+            verified_email: false,
+        }
+    }
+
+    // Only takes effect when the email verification policy is enforced.
+    pub fn require_verified_email(mut self) -> Self {
+        self.verified_email =
+            self.app_state.email_verification.policy == VerificationPolicy::Enforce;
+        self
+    }
+
+    // For routes that act on the signed-in member rather than the company.
+    pub fn without_api_keys(mut self) -> Self {
+        self.api_keys = false;
+        self
+    }
+}
+
+// Rejects tokens issued for another audience, then puts the authenticated
+// `Company` and `CompanyMember`, or the `User` and their `UserSession`, and the
+// token's `Claims` into the request extensions. The member's current role is read from the database,
+// so a role change applies to tokens that are already issued.
+//
+// Business routes also accept a company API key in the `X-Api-Key` header.
+// Such requests only get the `Company` extension.
+pub async fn authorize(
+    State(config): State<AuthConfig>,
+    mut req: Request,
+    next: Next,
+) -> Result<Response, AppError> {
+    if config.api_keys && req.headers().contains_key(API_KEY_HEADER) {
+        return authorize_api_key(config, req, next).await;
+    }
+
+    let auth_header = match req.headers_mut().get(http::header::AUTHORIZATION) {
+        Some(header) => header.to_str().map_err(|_| AppError::Unauthorized)?,
+        None => return Err(AppError::Unauthorized),
+    };
+
+    let [_, token, ..] = *auth_header.split_whitespace().collect::<Vec<&str>>() else {
+        return Err(AppError::Unauthorized);
+    };
+
+    let claims = decode_jwt(&config.app_state.jwt_keys, token, config.audience)?.claims;
+    if claims.aud != config.audience || config.app_state.revoked_tokens.is_revoked(&claims.jti) {
+        return Err(AppError::Unauthorized);
+    }
+
+    let repository = &config.app_state.repository;
+    match config.audience {
+        Audience::Business => {
+            let Some(member) = repository.retrieve_member_by_id(&claims.sub).await? else {
+                return Err(AppError::Unauthorized);
+            };
+            let role = member.role().ok_or(AppError::Unauthorized)?;
+            if !config.roles.contains(&role) {
+                return Err(AppError::Forbidden(INSUFFICIENT_ROLE));
+            }
+            if config.verified_email && member.email_verified_at.is_none() {
+                return Err(AppError::Forbidden(EMAIL_NOT_VERIFIED));
+            }
+            let Some(company) = repository
+                .retrieve_company_by_id(&member.company_id)
+                .await?
+            else {
+                return Err(AppError::Unauthorized);
+            };
+
+            req.extensions_mut().insert(company);
+            req.extensions_mut().insert(member);
+        }
+        Audience::User => {
+            if claims.role != Role::User {
+                return Err(AppError::Unauthorized);
+            }
+            let session = sessions::authenticate(&config.app_state, &claims).await?;
+            let Some(user) = repository.retrieve_user_by_id(&claims.sub).await? else {
+                return Err(AppError::Unauthorized);
+            };
+            if config.verified_email && user.email_verified_at.is_none() {
+                return Err(AppError::Forbidden(EMAIL_NOT_VERIFIED));
+            }
+
+            req.extensions_mut().insert(user);
+            req.extensions_mut().insert(session);
+        }
+    }
+
+    req.extensions_mut().insert(claims);
+    Ok(next.run(req).await)
+}
+
+async fn authorize_api_key(
+    config: AuthConfig,
+    mut req: Request,
+    next: Next,
+) -> Result<Response, AppError> {
+    let key = req
+        .headers()
+        .get(API_KEY_HEADER)
+        .and_then(|header| header.to_str().ok())
+        .ok_or(AppError::Unauthorized)?;
+
+    let repository = &config.app_state.repository;
+    let Some(api_key) = repository.use_api_key(&hash_token(key), Utc::now()).await? else {
+        return Err(AppError::Unauthorized);
+    };
+    let role = api_key.role().ok_or(AppError::Unauthorized)?;
+    if !config.roles.contains(&role) {
+        return Err(AppError::Forbidden(INSUFFICIENT_ROLE));
+    }
+    let Some(company) = repository
+        .retrieve_company_by_id(&api_key.company_id)
+        .await?
+    else {
+        return Err(AppError::Unauthorized);
+    };
+
+    req.extensions_mut().insert(company);
+    Ok(next.run(req).await)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::testing;
+    use axum::http::StatusCode;
+
+    async fn get(base_url: &str, path: &str, token: &str) -> StatusCode {
+        reqwest::Client::new()
+            .get(format!("{}{}", base_url, path))
+            .bearer_auth(token)
+            .send()
+            .await
+            .unwrap()
+            .status()
+    }
+
+    #[cfg_attr(feature = "postgres", ignore = "needs TEST_DATABASE_URL")]
+    #[tokio::test]
+    async fn tokens_only_open_routes_of_their_audience() {
+        let repository = testing::repository().await;
+        let user = testing::user(&repository, 25, "ru").await;
+        let (_, owner) = testing::company(&repository).await;
+        let state = testing::app_state(repository, "127.0.0.1:9").await;
+        let user_token = testing::user_token(&state, &user).await;
+        let member_token = testing::member_token(&state, &owner).await;
+        let base_url = testing::serve(state).await;
+
+        assert_eq!(
+            get(&base_url, "/api/user/profile", &user_token).await,
+            StatusCode::OK
+        );
+        assert_eq!(
+            get(&base_url, "/api/business/promo", &member_token).await,
+            StatusCode::OK
+        );
+
+        for path in ["/api/business/promo", "/api/business/members"] {
+            assert_eq!(
+                get(&base_url, path, &user_token).await,
+                StatusCode::UNAUTHORIZED,
+                "{}",
+                path
+            );
+        }
+        for path in ["/api/user/profile", "/api/user/feed", "/api/user/sessions"] {
+            assert_eq!(
+                get(&base_url, path, &member_token).await,
+                StatusCode::UNAUTHORIZED,
+                "{}",
+                path
+            );
+        }
+    }
+}
@@ -0,0 +1,147 @@
+use crate::error::AppError;
+use chrono::{DateTime, Duration, TimeDelta, Utc};
+use jsonwebtoken::{decode, decode_header, encode, Header, TokenData, Validation};
+use keys::JwtKeys;
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use uuid::Uuid;
+
+pub mod jwks;
+pub mod keys;
+pub mod middleware;
+pub mod password;
+pub mod totp;
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum Audience {
+    Business,
+    User,
+}
+
+impl Audience {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Audience::Business => "business",
+            Audience::User => "user",
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum Role {
+    Owner,
+    Editor,
+    Analyst,
+    Viewer,
+    User,
+}
+
+impl Role {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Role::Owner => "owner",
+            Role::Editor => "editor",
+            Role::Analyst => "analyst",
+            Role::Viewer => "viewer",
+            Role::User => "user",
+        }
+    }
+
+    pub fn parse(role: &str) -> Option<Self> {
+        [
+            Role::Owner,
+            Role::Editor,
+            Role::Analyst,
+            Role::Viewer,
+            Role::User,
+        ]
+        .into_iter()
+        .find(|candidate| candidate.as_str() == role)
+    }
+}
+
+// `sub` is the id of the company or user the token was issued to, `aud` tells
+// which half of the API it is valid for. User tokens also name the session
+// they belong to in `sid`.
+#[derive(Serialize, Deserialize, Clone)]
+pub struct Claims {
+    pub sub: String,
+    pub aud: Audience,
+    pub role: Role,
+    pub exp: usize,
+    pub iat: usize,
+    pub jti: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sid: Option<String>,
+}
+
+impl Claims {
+    pub fn expires_at(&self) -> DateTime<Utc> {
+        DateTime::from_timestamp(self.exp as i64, 0).unwrap_or(DateTime::<Utc>::MAX_UTC)
+    }
+}
+
+pub fn encode_jwt(
+    keys: &JwtKeys,
+    audience: Audience,
+    subject_id: &str,
+    role: Role,
+    session_id: Option<&str>,
+) -> Result<(String, Claims), AppError> {
+    let now = Utc::now();
+    let expire: TimeDelta = Duration::minutes(15);
+    let exp = (now + expire).timestamp() as usize;
+    let iat = now.timestamp() as usize;
+    let claims = Claims {
+        sub: subject_id.to_string(),
+        aud: audience,
+        role,
+        exp,
+        iat,
+        jti: Uuid::new_v4().to_string(),
+        sid: session_id.map(str::to_string),
+    };
+
+    let (kid, algorithm, encoding_key) = keys.signing_key();
+    let mut header = Header::new(algorithm);
+    header.kid = Some(kid.to_string());
+
+    let token = encode(&header, &claims, encoding_key)?;
+    Ok((token, claims))
+}
+
+// Tokens are verified with the key named by their `kid`, and only with the
+// algorithm that key was loaded for.
+pub fn decode_jwt(
+    keys: &JwtKeys,
+    token: &str,
+    audience: Audience,
+) -> Result<TokenData<Claims>, AppError> {
+    let kid = decode_header(token)?.kid.ok_or(AppError::Unauthorized)?;
+    let (algorithm, decoding_key) = keys.verification_key(&kid).ok_or(AppError::Unauthorized)?;
+
+    let mut validation = Validation::new(algorithm);
+    validation.set_audience(&[audience.as_str()]);
+    validation.set_required_spec_claims(&["exp", "aud", "sub"]);
+
+    Ok(decode(token, decoding_key, &validation)?)
+}
+
+// Opaque tokens handed out to clients (refresh tokens, invitations). Only
+// `hash_token` of them is ever stored.
+pub fn random_token() -> String {
+    let mut bytes = [0u8; 32];
+    rand::thread_rng().fill_bytes(&mut bytes);
+    hex(&bytes)
+}
+
+pub fn hash_token(token: &str) -> String {
+    hex(&Sha256::digest(token.as_bytes()))
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
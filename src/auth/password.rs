@@ -0,0 +1,146 @@
+use crate::error::AppError;
+use argon2::{
+    password_hash::{self, PasswordHash, PasswordHasher as _, PasswordVerifier, SaltString},
+    Algorithm, Argon2, Params, Version, ARGON2ID_IDENT,
+};
+use rand::RngCore;
+use std::{env, fmt};
+use tokio::task::{self, JoinError};
+
+#[derive(Debug)]
+pub enum PasswordError {
+    Argon2(password_hash::Error),
+    Bcrypt(bcrypt::BcryptError),
+    Task(JoinError),
+}
+
+impl fmt::Display for PasswordError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PasswordError::Argon2(err) => write!(f, "argon2 error: {}", err),
+            PasswordError::Bcrypt(err) => write!(f, "bcrypt error: {}", err),
+            PasswordError::Task(err) => write!(f, "hashing task failed: {}", err),
+        }
+    }
+}
+
+impl From<password_hash::Error> for PasswordError {
+    fn from(err: password_hash::Error) -> Self {
+        PasswordError::Argon2(err)
+    }
+}
+
+impl From<bcrypt::BcryptError> for PasswordError {
+    fn from(err: bcrypt::BcryptError) -> Self {
+        PasswordError::Bcrypt(err)
+    }
+}
+
+// New passwords are hashed with Argon2id using the parameters from
+// `ARGON2_MEMORY_KIB`, `ARGON2_ITERATIONS` and `ARGON2_PARALLELISM`. Hashes
+// made with bcrypt or with other parameters still verify, and `rehash` tells
+// sign-in to replace them while it has the plain password at hand.
+//
+// Hashing is deliberately slow, so it runs on the blocking thread pool
+// instead of holding up an async worker.
+#[derive(Clone)]
+pub struct PasswordHasher {
+    params: Params,
+}
+
+impl PasswordHasher {
+    pub fn from_env() -> Result<Self, String> {
+        let param = |name: &str, default: u32| match env::var(name) {
+            Ok(value) => value
+                .parse::<u32>()
+                .map_err(|err| format!("{}: {}", name, err)),
+            Err(_) => Ok(default),
+        };
+
+        let params = Params::new(
+            param("ARGON2_MEMORY_KIB", Params::DEFAULT_M_COST)?,
+            param("ARGON2_ITERATIONS", Params::DEFAULT_T_COST)?,
+            param("ARGON2_PARALLELISM", Params::DEFAULT_P_COST)?,
+            None,
+        )
+        .map_err(|err| err.to_string())?;
+
+        Ok(PasswordHasher { params })
+    }
+
+    pub async fn hash(&self, password: &str) -> Result<String, AppError> {
+        let argon2 = Argon2::new(Algorithm::Argon2id, Version::V0x13, self.params.clone());
+        let password = password.to_string();
+
+        run(move || {
+            let mut salt = [0u8; 16];
+            rand::thread_rng().fill_bytes(&mut salt);
+            let salt = SaltString::encode_b64(&salt)?;
+
+            Ok(argon2
+                .hash_password(password.as_bytes(), &salt)?
+                .to_string())
+        })
+        .await
+    }
+
+    pub async fn verify(&self, password: &str, hash: &str) -> Result<bool, AppError> {
+        let password = password.to_string();
+        let hash = hash.to_string();
+
+        run(move || {
+            if is_bcrypt(&hash) {
+                return Ok(bcrypt::verify(&password, &hash)?);
+            }
+
+            // The algorithm, version and parameters are taken from the hash.
+            let hash = PasswordHash::new(&hash)?;
+            match Argon2::default().verify_password(password.as_bytes(), &hash) {
+                Ok(()) => Ok(true),
+                Err(password_hash::Error::Password) => Ok(false),
+                Err(err) => Err(err.into()),
+            }
+        })
+        .await
+    }
+
+    // Returns a replacement for a hash that was verified against `password`
+    // when it was not made with the current scheme and parameters.
+    pub async fn rehash(&self, password: &str, hash: &str) -> Result<Option<String>, AppError> {
+        if self.is_current(hash) {
+            return Ok(None);
+        }
+        Ok(Some(self.hash(password).await?))
+    }
+
+    fn is_current(&self, hash: &str) -> bool {
+        if is_bcrypt(hash) {
+            return false;
+        }
+
+        PasswordHash::new(hash).is_ok_and(|hash| {
+            hash.algorithm == ARGON2ID_IDENT
+                && hash.version == Some(Version::V0x13.into())
+                && Params::try_from(&hash).is_ok_and(|params| {
+                    params.m_cost() == self.params.m_cost()
+                        && params.t_cost() == self.params.t_cost()
+                        && params.p_cost() == self.params.p_cost()
+                })
+        })
+    }
+}
+
+fn is_bcrypt(hash: &str) -> bool {
+    hash.starts_with("$2")
+}
+
+async fn run<T, F>(f: F) -> Result<T, AppError>
+where
+    T: Send + 'static,
+    F: FnOnce() -> Result<T, PasswordError> + Send + 'static,
+{
+    match task::spawn_blocking(f).await {
+        Ok(result) => Ok(result?),
+        Err(err) => Err(PasswordError::Task(err).into()),
+    }
+}
@@ -0,0 +1,42 @@
+use chrono::{DateTime, Utc};
+use rand::RngCore;
+use totp_rs::{Algorithm, Secret, TOTP};
+
+const ISSUER: &str = "PROD";
+const DIGITS: usize = 6;
+const STEP_SECS: i64 = 30;
+// Codes of the neighbouring steps are accepted to tolerate clock drift.
+const SKEW_STEPS: i64 = 1;
+
+// Returns a new 160-bit secret, base32-encoded the way authenticator apps
+// expect it, and the otpauth:// URI to enroll it with.
+pub fn generate(account: &str) -> (String, String) {
+    let mut bytes = [0u8; 20];
+    rand::thread_rng().fill_bytes(&mut bytes);
+
+    let totp = totp(bytes.to_vec(), account).expect("TOTP parameters are valid");
+    (totp.get_secret_base32(), totp.get_url())
+}
+
+// Returns the time step the code belongs to, so that the caller can refuse a
+// code that was already used.
+pub fn verify(secret: &str, code: &str, now: DateTime<Utc>) -> Option<i64> {
+    let secret = Secret::Encoded(secret.to_string()).to_bytes().ok()?;
+    let totp = totp(secret, "").ok()?;
+    let current_step = now.timestamp() / STEP_SECS;
+
+    (current_step - SKEW_STEPS..=current_step + SKEW_STEPS)
+        .find(|step| totp.check(code, (step * STEP_SECS) as u64))
+}
+
+fn totp(secret: Vec<u8>, account: &str) -> Result<TOTP, totp_rs::TotpUrlError> {
+    TOTP::new(
+        Algorithm::SHA1,
+        DIGITS,
+        0,
+        STEP_SECS as u64,
+        secret,
+        Some(ISSUER.to_string()),
+        account.replace(':', ""),
+    )
+}
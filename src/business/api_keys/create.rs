@@ -0,0 +1,64 @@
+use super::ApiKey;
+use crate::{
+    auth::{hash_token, random_token, Role},
+    business::auth::Company,
+    error::AppError,
+    extract::AppJson,
+    repository::ApiKeyRepository,
+    validation::{Validate, Validator},
+    AppState,
+};
+use axum::{extract::State, Extension, Json};
+use chrono::Utc;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use uuid::Uuid;
+
+const API_KEY_PREFIX: &str = "pk_";
+
+#[derive(Deserialize)]
+pub struct CreateApiKey {
+    pub name: String,
+    pub role: String,
+}
+
+impl Validate for CreateApiKey {
+    fn validate(&self, v: &mut Validator) {
+        v.length("name", &self.name, 1..=100);
+        v.check(
+            "role",
+            matches!(
+                Role::parse(&self.role),
+                Some(Role::Editor | Role::Analyst | Role::Viewer)
+            ),
+        );
+    }
+}
+
+// The key itself is only returned here, the database keeps its hash.
+pub async fn create_api_key(
+    State(app_state): State<AppState>,
+    Extension(company): Extension<Company>,
+    AppJson(create_api_key): AppJson<CreateApiKey>,
+) -> Result<Json<Value>, AppError> {
+    app_state.validation_rules.check(&create_api_key)?;
+
+    let key = format!("{}{}", API_KEY_PREFIX, random_token());
+    let api_key = ApiKey {
+        id: Uuid::new_v4().to_string(),
+        company_id: company.id,
+        name: create_api_key.name,
+        role: create_api_key.role,
+        prefix: key.chars().take(API_KEY_PREFIX.len() + 8).collect(),
+        key_hash: hash_token(&key),
+        created_at: Utc::now(),
+        last_used_at: None,
+        revoked_at: None,
+    };
+    app_state.repository.create_api_key(&api_key).await?;
+
+    Ok(Json(json!({
+        "id": api_key.id,
+        "key": key
+    })))
+}
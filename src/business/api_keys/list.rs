@@ -0,0 +1,15 @@
+use super::ApiKey;
+use crate::{business::auth::Company, error::AppError, repository::ApiKeyRepository, AppState};
+use axum::{extract::State, Extension, Json};
+
+pub async fn list_api_keys(
+    State(app_state): State<AppState>,
+    Extension(company): Extension<Company>,
+) -> Result<Json<Vec<ApiKey>>, AppError> {
+    Ok(Json(
+        app_state
+            .repository
+            .retrieve_company_api_keys(&company.id)
+            .await?,
+    ))
+}
@@ -0,0 +1,35 @@
+use crate::auth::Role;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use sqlx::FromRow;
+
+pub mod create;
+pub mod list;
+pub mod revoke;
+#[cfg(test)]
+mod tests;
+
+// A company-scoped key for server-to-server calls, sent in the `X-Api-Key`
+// header. The key grants one of the business roles below owner. `prefix` is the
+// start of the key, kept so it can be recognised in listings.
+#[derive(Serialize, FromRow, Clone, Debug)]
+pub struct ApiKey {
+    pub id: String,
+    #[serde(skip_serializing)]
+    pub company_id: String,
+    pub name: String,
+    pub role: String,
+    pub prefix: String,
+    #[serde(skip_serializing)]
+    pub key_hash: String,
+    pub created_at: DateTime<Utc>,
+    pub last_used_at: Option<DateTime<Utc>>,
+    pub revoked_at: Option<DateTime<Utc>>,
+}
+
+impl ApiKey {
+    pub fn role(&self) -> Option<Role> {
+        Role::parse(&self.role)
+            .filter(|role| matches!(role, Role::Editor | Role::Analyst | Role::Viewer))
+    }
+}
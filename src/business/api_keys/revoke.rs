@@ -0,0 +1,30 @@
+use crate::{
+    business::auth::Company,
+    error::{AppError, API_KEY_NOT_FOUND},
+    repository::ApiKeyRepository,
+    AppState,
+};
+use axum::{
+    extract::{Path, State},
+    Extension, Json,
+};
+use chrono::Utc;
+use serde_json::{json, Value};
+
+pub async fn revoke_api_key(
+    State(app_state): State<AppState>,
+    Extension(company): Extension<Company>,
+    Path(id): Path<String>,
+) -> Result<Json<Value>, AppError> {
+    if !app_state
+        .repository
+        .revoke_api_key(&company.id, &id, Utc::now())
+        .await?
+    {
+        return Err(AppError::NotFound(API_KEY_NOT_FOUND));
+    }
+
+    Ok(Json(json!({
+        "status": "ok"
+    })))
+}
@@ -0,0 +1,124 @@
+use crate::testing;
+use axum::http::StatusCode;
+use chrono::Utc;
+use reqwest::{Client, RequestBuilder};
+use serde_json::{json, Value};
+
+// An editor key of a fresh company, and the owner's access token to manage it.
+struct Keyring {
+    base_url: String,
+    owner_token: String,
+    key_id: String,
+    key: String,
+    promo_id: String,
+}
+
+impl Keyring {
+    fn as_owner(&self, request: RequestBuilder) -> RequestBuilder {
+        request.bearer_auth(&self.owner_token)
+    }
+
+    fn with_key(&self, request: RequestBuilder) -> RequestBuilder {
+        request.header("X-Api-Key", &self.key)
+    }
+
+    fn url(&self, path: &str) -> String {
+        format!("{}{}", self.base_url, path)
+    }
+
+    async fn edit_promo(&self) -> StatusCode {
+        self.with_key(
+            Client::new().patch(self.url(&format!("/api/business/promo/{}", self.promo_id))),
+        )
+        .json(&json!({ "description": "Edited with an API key" }))
+        .send()
+        .await
+        .unwrap()
+        .status()
+    }
+}
+
+async fn keyring() -> Keyring {
+    let repository = testing::repository().await;
+    let (company, owner) = testing::company(&repository).await;
+    let promo = testing::promo(&repository, &company, 10, None, Utc::now()).await;
+    let state = testing::app_state(repository, "127.0.0.1:9").await;
+    let owner_token = testing::member_token(&state, &owner).await;
+    let base_url = testing::serve(state).await;
+
+    let response = Client::new()
+        .post(format!("{}/api/business/api-keys", base_url))
+        .bearer_auth(&owner_token)
+        .json(&json!({ "name": "CI", "role": "editor" }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let created: Value = response.json().await.unwrap();
+
+    Keyring {
+        base_url,
+        owner_token,
+        key_id: created["id"].as_str().unwrap().to_string(),
+        key: created["key"].as_str().unwrap().to_string(),
+        promo_id: promo.promo_id,
+    }
+}
+
+#[cfg_attr(feature = "postgres", ignore = "needs TEST_DATABASE_URL")]
+#[tokio::test]
+async fn keys_open_routes_of_their_role_only() {
+    let keyring = keyring().await;
+    assert_eq!(keyring.edit_promo().await, StatusCode::OK);
+
+    for path in ["/api/business/members", "/api/business/api-keys"] {
+        let response = keyring
+            .with_key(Client::new().get(keyring.url(path)))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::FORBIDDEN, "{}", path);
+    }
+}
+
+#[cfg_attr(feature = "postgres", ignore = "needs TEST_DATABASE_URL")]
+#[tokio::test]
+async fn using_a_key_is_recorded() {
+    let keyring = keyring().await;
+    let list_keys = || async {
+        let response = keyring
+            .as_owner(Client::new().get(keyring.url("/api/business/api-keys")))
+            .send()
+            .await
+            .unwrap();
+        let keys: Value = response.json().await.unwrap();
+        keys[0].clone()
+    };
+
+    let listed = list_keys().await;
+    assert_eq!(listed["id"], keyring.key_id.as_str());
+    assert!(listed["last_used_at"].is_null());
+    assert!(listed.get("key_hash").is_none());
+
+    assert_eq!(keyring.edit_promo().await, StatusCode::OK);
+    assert!(list_keys().await["last_used_at"].is_string());
+}
+
+#[cfg_attr(feature = "postgres", ignore = "needs TEST_DATABASE_URL")]
+#[tokio::test]
+async fn revoked_keys_are_rejected() {
+    let keyring = keyring().await;
+    assert_eq!(keyring.edit_promo().await, StatusCode::OK);
+
+    let response = keyring
+        .as_owner(
+            Client::new()
+                .delete(keyring.url(&format!("/api/business/api-keys/{}", keyring.key_id))),
+        )
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    assert_eq!(keyring.edit_promo().await, StatusCode::UNAUTHORIZED);
+}
@@ -1,76 +1,35 @@
-use axum::http::StatusCode;
-use bcrypt::{hash, verify, DEFAULT_COST};
-use chrono::{Duration, TimeDelta, Utc};
-use jsonwebtoken::{decode, encode, DecodingKey, EncodingKey, Header, TokenData, Validation};
-use serde::{Deserialize, Serialize};
-use sqlx::{FromRow, PgPool};
-use std::env;
+use crate::auth::Role;
+use chrono::{DateTime, Utc};
+use sqlx::FromRow;
 
+pub mod password_reset;
+pub mod refresh;
 pub mod sign_in;
+pub mod sign_out;
 pub mod sign_up;
+pub mod two_factor;
+pub mod verify_email;
 
-#[derive(Serialize, Deserialize)]
-pub struct Claims {
-    pub exp: usize,
-    pub iat: usize,
-    pub email: String,
+#[derive(FromRow, Clone, Debug)]
+pub struct Company {
+    pub id: String,
+    pub name: String,
 }
 
+// A login belonging to a company. `role` is one of the business `Role`s.
 #[derive(FromRow, Clone, Debug)]
-pub struct Company {
+pub struct CompanyMember {
     pub id: String,
+    pub company_id: String,
     pub name: String,
     pub email: String,
     pub password_hash: String,
+    pub role: String,
+    pub email_verified_at: Option<DateTime<Utc>>,
 }
 
-pub async fn retrieve_company_by_email(pool: &PgPool, email: &str) -> Option<Company> {
-    let company = sqlx::query_as(
-        r#"
-        SELECT * FROM companies WHERE email = ?
-        "#,
-    )
-    .bind(email)
-    .fetch_optional(pool)
-    .await
-    .unwrap();
-
-    company
-}
-
-pub fn verify_password(password: &str, hash: &str) -> Result<bool, bcrypt::BcryptError> {
-    verify(password, hash)
-}
-
-pub fn hash_password(password: &str) -> Result<String, bcrypt::BcryptError> {
-    let hash = hash(password, DEFAULT_COST)?;
-    Ok(hash)
-}
-
-pub fn encode_jwt(email: String) -> Result<String, StatusCode> {
-    let secret = env::var("RANDOM_SECRET").map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
-    let now = Utc::now();
-    let expire: TimeDelta = Duration::hours(24);
-    let exp = (now + expire).timestamp() as usize;
-    let iat = now.timestamp() as usize;
-    let claims = Claims { exp, iat, email };
-
-    encode(
-        &Header::default(),
-        &claims,
-        &EncodingKey::from_secret(secret.as_ref()),
-    )
-    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)
-}
-
-pub fn decode_jwt(token: &str) -> Result<TokenData<Claims>, StatusCode> {
-    let secret = env::var("RANDOM_SECRET").map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
-    let result = decode(
-        &token,
-        &DecodingKey::from_secret(secret.as_ref()),
-        &Validation::default(),
-    )
-    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR);
-
-    result
+impl CompanyMember {
+    pub fn role(&self) -> Option<Role> {
+        Role::parse(&self.role).filter(|role| *role != Role::User)
+    }
 }
@@ -0,0 +1,74 @@
+use crate::{
+    auth::Audience,
+    error::AppError,
+    extract::AppJson,
+    password_reset,
+    repository::MemberRepository,
+    validation::{Validate, Validator},
+    AppState,
+};
+use axum::{extract::State, Json};
+use serde::Deserialize;
+use serde_json::{json, Value};
+
+#[derive(Deserialize)]
+pub struct RequestReset {
+    pub email: String,
+}
+
+#[derive(Deserialize)]
+pub struct ConfirmReset {
+    pub token: String,
+    pub password: String,
+}
+
+impl Validate for RequestReset {
+    fn validate(&self, v: &mut Validator) {
+        v.length("email", &self.email, 1..=120);
+    }
+}
+
+impl Validate for ConfirmReset {
+    fn validate(&self, v: &mut Validator) {
+        v.password("password", &self.password);
+    }
+}
+
+pub async fn request_reset(
+    State(app_state): State<AppState>,
+    AppJson(request_reset): AppJson<RequestReset>,
+) -> Result<Json<Value>, AppError> {
+    app_state.validation_rules.check(&request_reset)?;
+
+    password_reset::request_reset(app_state, Audience::Business, request_reset.email);
+
+    Ok(Json(json!({
+        "status": "ok"
+    })))
+}
+
+pub async fn confirm_reset(
+    State(app_state): State<AppState>,
+    AppJson(confirm_reset): AppJson<ConfirmReset>,
+) -> Result<Json<Value>, AppError> {
+    app_state.validation_rules.check(&confirm_reset)?;
+
+    let member_id =
+        password_reset::use_reset_token(&app_state, Audience::Business, &confirm_reset.token)
+            .await?;
+    app_state
+        .repository
+        .update_member_password(
+            &member_id,
+            &app_state
+                .password_hasher
+                .hash(&confirm_reset.password)
+                .await?,
+        )
+        .await?;
+    password_reset::end_sessions(&app_state, Audience::Business, &member_id).await?;
+
+    Ok(Json(json!({
+        "status": "ok"
+    })))
+}
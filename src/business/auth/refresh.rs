@@ -0,0 +1,50 @@
+use crate::{
+    auth::{encode_jwt, Audience},
+    error::AppError,
+    extract::AppJson,
+    refresh,
+    repository::MemberRepository,
+    AppState,
+};
+use axum::{extract::State, Json};
+use serde::Deserialize;
+use serde_json::{json, Value};
+
+#[derive(Deserialize)]
+pub struct RefreshData {
+    pub refresh_token: String,
+}
+
+pub async fn refresh(
+    State(app_state): State<AppState>,
+    AppJson(refresh_data): AppJson<RefreshData>,
+) -> Result<Json<Value>, AppError> {
+    let (successor, refresh_token) =
+        refresh::rotate(&app_state, Audience::Business, &refresh_data.refresh_token).await?;
+
+    let member = match app_state
+        .repository
+        .retrieve_member_by_id(&successor.subject_id)
+        .await?
+    {
+        Some(member) => member,
+        None => return Err(AppError::Unauthorized),
+    };
+
+    let (token, claims) = encode_jwt(
+        &app_state.jwt_keys,
+        Audience::Business,
+        &member.id,
+        member.role().ok_or(AppError::Unauthorized)?,
+        None,
+    )?;
+    app_state
+        .revoked_tokens
+        .start_session(&member.id, &claims.jti, claims.expires_at())
+        .await?;
+
+    Ok(Json(json!({
+        "token": token,
+        "refresh_token": refresh_token
+    })))
+}
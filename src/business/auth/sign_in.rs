@@ -1,8 +1,14 @@
 use crate::{
-    business::auth::{encode_jwt, retrieve_company_by_email, verify_password},
+    auth::{encode_jwt, Audience},
+    business::auth::{two_factor, CompanyMember},
+    error::AppError,
+    extract::{AppJson, ClientInfo},
+    refresh,
+    repository::MemberRepository,
+    validation::{Validate, Validator},
     AppState,
 };
-use axum::{extract::State, http::StatusCode, Json};
+use axum::{extract::State, Json};
 use serde::Deserialize;
 use serde_json::{json, Value};
 
@@ -12,56 +18,88 @@ pub struct SignInData {
     pub password: String,
 }
 
-impl SignInData {
-    pub fn is_valid(&self) -> bool {
-        if self.email.chars().count() < 8 || self.email.chars().count() > 120 {
-            return false;
-        }
-
-        let mut has_whitespace = false;
-        let mut has_upper = false;
-        let mut has_lower = false;
-        let mut has_digit = false;
-        for c in self.password.chars() {
-            has_whitespace |= c.is_whitespace();
-            has_lower |= c.is_lowercase();
-            has_upper |= c.is_uppercase();
-            has_digit |= c.is_digit(10);
-        }
-
-        return !has_whitespace
-            && has_upper
-            && has_lower
-            && has_digit
-            && self.password.chars().count() >= 8
-            && self.password.chars().count() <= 60;
+// Only bounds are checked: the account may predate the current password
+// policy.
+impl Validate for SignInData {
+    fn validate(&self, v: &mut Validator) {
+        v.length("email", &self.email, 8..=120);
+        v.current_password("password", &self.password);
     }
 }
 
 pub async fn sign_in(
     State(app_state): State<AppState>,
-    Json(sign_in_data): Json<SignInData>,
-) -> Result<Json<Value>, StatusCode> {
-    if !sign_in_data.is_valid() {
-        return Err(StatusCode::BAD_REQUEST);
-    }
+    client: ClientInfo,
+    AppJson(sign_in_data): AppJson<SignInData>,
+) -> Result<Json<Value>, AppError> {
+    app_state.validation_rules.check(&sign_in_data)?;
+
+    let attempt = app_state
+        .sign_in_attempts
+        .start(Audience::Business, &sign_in_data.email, client.ip_address)
+        .await?;
 
-    let company = match retrieve_company_by_email(&app_state.pool, &sign_in_data.email).await {
-        Some(company) => company,
-        None => {
-            return Err(StatusCode::UNAUTHORIZED);
+    let member = match app_state
+        .repository
+        .retrieve_member_by_email(&sign_in_data.email)
+        .await?
+    {
+        Some(member)
+            if app_state
+                .password_hasher
+                .verify(&sign_in_data.password, &member.password_hash)
+                .await? =>
+        {
+            member
+        }
+        _ => {
+            attempt.failed().await?;
+            return Err(AppError::InvalidCredentials);
         }
     };
+    attempt.succeeded().await?;
 
-    if !verify_password(&sign_in_data.password, &company.password_hash)
-        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+    if let Some(password_hash) = app_state
+        .password_hasher
+        .rehash(&sign_in_data.password, &member.password_hash)
+        .await?
     {
-        return Err(StatusCode::UNAUTHORIZED);
+        app_state
+            .repository
+            .update_member_password(&member.id, &password_hash)
+            .await?;
+    }
+
+    if let Some(two_factor_token) = two_factor::start_challenge(&app_state, &member).await? {
+        return Ok(Json(json!({
+            "two_factor_required": true,
+            "two_factor_token": two_factor_token
+        })));
     }
 
-    let token = encode_jwt(company.email).map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    issue_tokens(&app_state, &member).await
+}
+
+pub async fn issue_tokens(
+    app_state: &AppState,
+    member: &CompanyMember,
+) -> Result<Json<Value>, AppError> {
+    let (token, claims) = encode_jwt(
+        &app_state.jwt_keys,
+        Audience::Business,
+        &member.id,
+        member.role().ok_or(AppError::Unauthorized)?,
+        None,
+    )?;
+    app_state
+        .revoked_tokens
+        .start_session(&member.id, &claims.jti, claims.expires_at())
+        .await?;
+    let refresh_token =
+        refresh::issue(&app_state.repository, Audience::Business, &member.id).await?;
 
     Ok(Json(json!({
-        "token": token
+        "token": token,
+        "refresh_token": refresh_token
     })))
 }
@@ -0,0 +1,24 @@
+use crate::{
+    auth::{Audience, Claims},
+    business::auth::CompanyMember,
+    error::AppError,
+    refresh, AppState,
+};
+use axum::{extract::State, Extension, Json};
+use serde_json::{json, Value};
+
+pub async fn sign_out(
+    State(app_state): State<AppState>,
+    Extension(member): Extension<CompanyMember>,
+    Extension(claims): Extension<Claims>,
+) -> Result<Json<Value>, AppError> {
+    app_state
+        .revoked_tokens
+        .revoke(&claims.jti, claims.expires_at())
+        .await?;
+    refresh::revoke(&app_state.repository, Audience::Business, &member.id).await?;
+
+    Ok(Json(json!({
+        "status": "ok"
+    })))
+}
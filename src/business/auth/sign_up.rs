@@ -1,13 +1,17 @@
 use crate::{
-    business::auth::{hash_password, Company},
-    user::middlewares::authorize::encode_jwt,
+    auth::{encode_jwt, Audience, Role},
+    business::auth::{Company, CompanyMember},
+    email_verification,
+    error::{AppError, EMAIL_ALREADY_REGISTERED},
+    extract::AppJson,
+    refresh,
+    repository::{CompanyRepository, MemberRepository, Repository},
+    validation::{Validate, Validator},
     AppState,
 };
-use axum::{extract::State, http::StatusCode, Json};
-use regex::Regex;
+use axum::{extract::State, Json};
 use serde::Deserialize;
 use serde_json::json;
-use sqlx::PgPool;
 use uuid::Uuid;
 
 #[derive(Deserialize, Debug)]
@@ -17,81 +21,66 @@ pub struct CreateCompany {
     password: String,
 }
 
+impl Validate for CreateCompany {
+    fn validate(&self, v: &mut Validator) {
+        v.length("name", &self.name, 5..=50);
+        v.email("email", &self.email);
+        v.password("password", &self.password);
+    }
+}
+
 pub async fn sign_up(
     State(app_state): State<AppState>,
-    Json(sign_up_data): Json<CreateCompany>,
-) -> Result<Json<serde_json::Value>, StatusCode> {
-    if !is_valid_sign_up_data(&sign_up_data) {
-        return Err(StatusCode::BAD_REQUEST);
-    }
-    if !is_unique_company(&app_state.pool, &sign_up_data).await {
-        return Err(StatusCode::CONFLICT);
+    AppJson(sign_up_data): AppJson<CreateCompany>,
+) -> Result<Json<serde_json::Value>, AppError> {
+    app_state.validation_rules.check(&sign_up_data)?;
+    if !is_unique_email(&app_state.repository, &sign_up_data.email).await? {
+        return Err(AppError::Conflict(EMAIL_ALREADY_REGISTERED));
     }
-    let id = Uuid::new_v4().to_string();
-    sqlx::query(
-        r#"
-        INSERT INTO companies VALUES (?, ?, ?, ?)
-        "#,
-    )
-    .bind(&id)
-    .bind(sign_up_data.name)
-    .bind(&sign_up_data.email)
-    .bind(hash_password(&sign_up_data.password).unwrap())
-    .execute(&app_state.pool)
-    .await
-    .unwrap();
+    let company = Company {
+        id: Uuid::new_v4().to_string(),
+        name: sign_up_data.name,
+    };
+    let owner = CompanyMember {
+        id: Uuid::new_v4().to_string(),
+        company_id: company.id.clone(),
+        name: company.name.clone(),
+        email: sign_up_data.email,
+        password_hash: app_state
+            .password_hasher
+            .hash(&sign_up_data.password)
+            .await?,
+        role: Role::Owner.as_str().to_string(),
+        email_verified_at: None,
+    };
+    app_state
+        .repository
+        .create_company(&company, &owner)
+        .await?;
+    email_verification::send_verification(&app_state, Audience::Business, &owner.id, &owner.email)
+        .await?;
 
-    let token = encode_jwt(sign_up_data.email).map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    let (token, claims) = encode_jwt(
+        &app_state.jwt_keys,
+        Audience::Business,
+        &owner.id,
+        Role::Owner,
+        None,
+    )?;
+    app_state
+        .revoked_tokens
+        .start_session(&owner.id, &claims.jti, claims.expires_at())
+        .await?;
+    let refresh_token =
+        refresh::issue(&app_state.repository, Audience::Business, &owner.id).await?;
 
     Ok(Json(json!({
-        "company_id": id,
-        "token": token
+        "company_id": company.id,
+        "token": token,
+        "refresh_token": refresh_token
     })))
 }
 
-async fn is_unique_company(pool: &PgPool, sign_up_data: &CreateCompany) -> bool {
-    let row: Option<Company> = sqlx::query_as(
-        r#"
-        SELECT * FROM companies WHERE email = ?
-        "#,
-    )
-    .bind(&sign_up_data.email)
-    .fetch_optional(pool)
-    .await
-    .unwrap();
-
-    row.is_none()
-}
-
-fn is_valid_sign_up_data(sign_up_data: &CreateCompany) -> bool {
-    let email_regex = Regex::new(
-        r"^([a-z0-9_+]([a-z0-9_+.]*[a-z0-9_+])?)@([a-z0-9]+([\-\.]{1}[a-z0-9]+)*\.[a-z]{2,6})",
-    )
-    .unwrap();
-
-    let mut has_whitespace = false;
-    let mut has_upper = false;
-    let mut has_lower = false;
-    let mut has_digit = false;
-
-    if sign_up_data.name.chars().count() < 5 || sign_up_data.name.chars().count() > 50 {
-        return false;
-    }
-
-    for c in sign_up_data.password.chars() {
-        has_whitespace |= c.is_whitespace();
-        has_lower |= c.is_lowercase();
-        has_upper |= c.is_uppercase();
-        has_digit |= c.is_digit(10);
-    }
-
-    if !email_regex.is_match(&sign_up_data.email) {
-        return false;
-    }
-    !has_whitespace
-        && has_upper
-        && has_lower
-        && has_digit
-        && (sign_up_data.password.chars().count() >= 8
-            || sign_up_data.password.chars().count() <= 60)
+pub async fn is_unique_email(repository: &Repository, email: &str) -> Result<bool, AppError> {
+    Ok(repository.retrieve_member_by_email(email).await?.is_none())
 }
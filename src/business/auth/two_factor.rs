@@ -0,0 +1,447 @@
+use crate::{
+    auth::{hash_token, random_token, totp},
+    business::auth::{sign_in::issue_tokens, CompanyMember},
+    error::{
+        AppError, TWO_FACTOR_ALREADY_ENABLED, TWO_FACTOR_ENROLLMENT_NOT_FOUND,
+        TWO_FACTOR_NOT_ENABLED,
+    },
+    extract::{AppJson, ClientInfo},
+    repository::{MemberRepository, TwoFactorRepository},
+    validation::{Validate, Validator},
+    AppState,
+};
+use axum::{extract::State, Extension, Json};
+use chrono::{DateTime, Duration, Utc};
+use rand::seq::SliceRandom;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use sqlx::FromRow;
+use uuid::Uuid;
+
+const CHALLENGE_TTL_MINUTES: i64 = 5;
+const MAX_CHALLENGE_ATTEMPTS: i32 = 5;
+const RECOVERY_CODE_COUNT: usize = 10;
+const RECOVERY_CODE_ALPHABET: &[u8] = b"abcdefghjkmnpqrstuvwxyz23456789";
+// Long enough for a recovery code typed with separators or spaces.
+const MAX_CODE_LENGTH: usize = 32;
+
+// The TOTP secret has to be kept in the clear to check codes. Until a first
+// code confirms the enrollment `enabled_at` is empty and sign-in ignores it.
+#[derive(FromRow, Clone, Debug)]
+pub struct MemberTotp {
+    pub member_id: String,
+    pub secret: String,
+    pub created_at: DateTime<Utc>,
+    pub enabled_at: Option<DateTime<Utc>>,
+}
+
+// Issued by sign-in in place of the tokens when the member has 2FA enabled,
+// and exchanged for them together with a code. Only its hash is stored.
+#[derive(FromRow, Clone, Debug)]
+pub struct TwoFactorChallenge {
+    pub id: String,
+    pub member_id: String,
+    pub token_hash: String,
+    pub created_at: DateTime<Utc>,
+    pub expires_at: DateTime<Utc>,
+    pub attempts: i32,
+}
+
+#[derive(Deserialize)]
+pub struct Code {
+    pub code: String,
+}
+
+#[derive(Deserialize)]
+pub struct DisableTwoFactor {
+    pub password: String,
+    pub code: String,
+}
+
+#[derive(Deserialize)]
+pub struct TwoFactorSignIn {
+    pub two_factor_token: String,
+    pub code: String,
+}
+
+impl Validate for Code {
+    fn validate(&self, v: &mut Validator) {
+        v.length("code", &self.code, 1..=MAX_CODE_LENGTH);
+    }
+}
+
+impl Validate for DisableTwoFactor {
+    fn validate(&self, v: &mut Validator) {
+        v.current_password("password", &self.password);
+        v.length("code", &self.code, 1..=MAX_CODE_LENGTH);
+    }
+}
+
+impl Validate for TwoFactorSignIn {
+    fn validate(&self, v: &mut Validator) {
+        v.length("code", &self.code, 1..=MAX_CODE_LENGTH);
+    }
+}
+
+pub async fn enroll(
+    State(app_state): State<AppState>,
+    Extension(member): Extension<CompanyMember>,
+) -> Result<Json<Value>, AppError> {
+    if enabled_totp(&app_state, &member.id).await?.is_some() {
+        return Err(AppError::Conflict(TWO_FACTOR_ALREADY_ENABLED));
+    }
+
+    let (secret, otpauth_uri) = totp::generate(&member.email);
+    app_state
+        .repository
+        .save_pending_member_totp(&MemberTotp {
+            member_id: member.id,
+            secret: secret.clone(),
+            created_at: Utc::now(),
+            enabled_at: None,
+        })
+        .await?;
+
+    Ok(Json(json!({
+        "secret": secret,
+        "otpauth_uri": otpauth_uri
+    })))
+}
+
+pub async fn confirm(
+    State(app_state): State<AppState>,
+    Extension(member): Extension<CompanyMember>,
+    AppJson(code): AppJson<Code>,
+) -> Result<Json<Value>, AppError> {
+    app_state.validation_rules.check(&code)?;
+
+    let member_totp = match app_state
+        .repository
+        .retrieve_member_totp(&member.id)
+        .await?
+    {
+        Some(member_totp) if member_totp.enabled_at.is_some() => {
+            return Err(AppError::Conflict(TWO_FACTOR_ALREADY_ENABLED))
+        }
+        Some(member_totp) => member_totp,
+        None => return Err(AppError::NotFound(TWO_FACTOR_ENROLLMENT_NOT_FOUND)),
+    };
+
+    let now = Utc::now();
+    let step = totp::verify(&member_totp.secret, code.code.trim(), now)
+        .ok_or(AppError::InvalidTwoFactorCode)?;
+
+    let (recovery_codes, code_hashes) = generate_recovery_codes();
+    if !app_state
+        .repository
+        .enable_member_totp(&member.id, step, &code_hashes, now)
+        .await?
+    {
+        return Err(AppError::Conflict(TWO_FACTOR_ALREADY_ENABLED));
+    }
+
+    Ok(Json(json!({
+        "recovery_codes": recovery_codes
+    })))
+}
+
+pub async fn disable(
+    State(app_state): State<AppState>,
+    Extension(member): Extension<CompanyMember>,
+    AppJson(disable): AppJson<DisableTwoFactor>,
+) -> Result<Json<Value>, AppError> {
+    app_state.validation_rules.check(&disable)?;
+
+    if !app_state
+        .password_hasher
+        .verify(&disable.password, &member.password_hash)
+        .await?
+    {
+        return Err(AppError::InvalidCredentials);
+    }
+
+    let member_totp = enabled_totp(&app_state, &member.id)
+        .await?
+        .ok_or(AppError::NotFound(TWO_FACTOR_NOT_ENABLED))?;
+    if !check_code(&app_state, &member_totp, &disable.code).await? {
+        return Err(AppError::InvalidTwoFactorCode);
+    }
+    app_state.repository.delete_member_totp(&member.id).await?;
+
+    Ok(Json(json!({
+        "status": "ok"
+    })))
+}
+
+// Replaces the remaining recovery codes with a fresh set.
+pub async fn regenerate_recovery_codes(
+    State(app_state): State<AppState>,
+    Extension(member): Extension<CompanyMember>,
+    AppJson(code): AppJson<Code>,
+) -> Result<Json<Value>, AppError> {
+    app_state.validation_rules.check(&code)?;
+
+    let member_totp = enabled_totp(&app_state, &member.id)
+        .await?
+        .ok_or(AppError::NotFound(TWO_FACTOR_NOT_ENABLED))?;
+    if !check_code(&app_state, &member_totp, &code.code).await? {
+        return Err(AppError::InvalidTwoFactorCode);
+    }
+
+    let (recovery_codes, code_hashes) = generate_recovery_codes();
+    app_state
+        .repository
+        .replace_recovery_codes(&member.id, &code_hashes)
+        .await?;
+
+    Ok(Json(json!({
+        "recovery_codes": recovery_codes
+    })))
+}
+
+// Second step of sign-in. Each challenge takes a few guesses, and failed
+// codes also count against the member across challenges, see
+// `SignInAttempts::start_two_factor`.
+pub async fn verify_sign_in(
+    State(app_state): State<AppState>,
+    client: ClientInfo,
+    AppJson(sign_in): AppJson<TwoFactorSignIn>,
+) -> Result<Json<Value>, AppError> {
+    app_state.validation_rules.check(&sign_in)?;
+
+    let now = Utc::now();
+    let challenge = app_state
+        .repository
+        .reserve_two_factor_attempt(
+            &hash_token(&sign_in.two_factor_token),
+            now,
+            MAX_CHALLENGE_ATTEMPTS,
+        )
+        .await?
+        .ok_or(AppError::Unauthorized)?;
+
+    let member = app_state
+        .repository
+        .retrieve_member_by_id(&challenge.member_id)
+        .await?
+        .ok_or(AppError::Unauthorized)?;
+    let member_totp = enabled_totp(&app_state, &member.id)
+        .await?
+        .ok_or(AppError::Unauthorized)?;
+
+    let attempt = app_state
+        .sign_in_attempts
+        .start_two_factor(&member.id, client.ip_address)
+        .await?;
+    if !check_code(&app_state, &member_totp, &sign_in.code).await? {
+        attempt.failed().await?;
+        return Err(AppError::InvalidTwoFactorCode);
+    }
+    attempt.succeeded().await?;
+    if !app_state
+        .repository
+        .use_two_factor_challenge(&challenge.id, now)
+        .await?
+    {
+        return Err(AppError::Unauthorized);
+    }
+
+    issue_tokens(&app_state, &member).await
+}
+
+// Returns the token sign-in hands out instead of the session tokens when the
+// member has 2FA enabled.
+pub async fn start_challenge(
+    app_state: &AppState,
+    member: &CompanyMember,
+) -> Result<Option<String>, AppError> {
+    if enabled_totp(app_state, &member.id).await?.is_none() {
+        return Ok(None);
+    }
+
+    let token = random_token();
+    let now = Utc::now();
+    app_state
+        .repository
+        .create_two_factor_challenge(&TwoFactorChallenge {
+            id: Uuid::new_v4().to_string(),
+            member_id: member.id.clone(),
+            token_hash: hash_token(&token),
+            created_at: now,
+            expires_at: now + Duration::minutes(CHALLENGE_TTL_MINUTES),
+            attempts: 0,
+        })
+        .await?;
+
+    Ok(Some(token))
+}
+
+async fn enabled_totp(
+    app_state: &AppState,
+    member_id: &str,
+) -> Result<Option<MemberTotp>, AppError> {
+    Ok(app_state
+        .repository
+        .retrieve_member_totp(member_id)
+        .await?
+        .filter(|member_totp| member_totp.enabled_at.is_some()))
+}
+
+// Accepts either a TOTP code, each of which works once, or an unused recovery
+// code.
+async fn check_code(
+    app_state: &AppState,
+    member_totp: &MemberTotp,
+    code: &str,
+) -> Result<bool, AppError> {
+    let now = Utc::now();
+    let code = code.trim();
+    if let Some(step) = totp::verify(&member_totp.secret, code, now) {
+        return Ok(app_state
+            .repository
+            .use_totp_step(&member_totp.member_id, step)
+            .await?);
+    }
+
+    Ok(app_state
+        .repository
+        .use_recovery_code(
+            &member_totp.member_id,
+            &hash_token(&normalize_recovery_code(code)),
+            now,
+        )
+        .await?)
+}
+
+// Returns the codes to show the member once, formatted as `xxxxx-xxxxx`, and
+// the hashes to store.
+fn generate_recovery_codes() -> (Vec<String>, Vec<String>) {
+    let mut rng = rand::thread_rng();
+    (0..RECOVERY_CODE_COUNT)
+        .map(|_| {
+            let code: String = (0..10)
+                .map(|_| *RECOVERY_CODE_ALPHABET.choose(&mut rng).unwrap() as char)
+                .collect();
+            let code_hash = hash_token(&code);
+            (format!("{}-{}", &code[..5], &code[5..]), code_hash)
+        })
+        .unzip()
+}
+
+fn normalize_recovery_code(code: &str) -> String {
+    code.chars()
+        .filter(|c| c.is_ascii_alphanumeric())
+        .map(|c| c.to_ascii_lowercase())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{MemberTotp, MAX_CHALLENGE_ATTEMPTS};
+    use crate::{
+        auth::{hash_token, totp},
+        repository::{MemberRepository, TwoFactorRepository},
+        testing,
+    };
+    use axum::http::StatusCode;
+    use chrono::Utc;
+    use reqwest::Client;
+    use serde_json::{json, Value};
+    use tokio::task::JoinSet;
+
+    const PASSWORD: &str = "Member password 1!";
+    const RECOVERY_CODE: &str = "abcde-fghjk";
+
+    // Serves a company whose owner has 2FA enabled. Returns the address and
+    // the owner's email.
+    async fn serve_owner_with_2fa() -> (String, String) {
+        let repository = testing::repository().await;
+        let (_, owner) = testing::company(&repository).await;
+        let state = testing::app_state(repository.clone(), "127.0.0.1:9").await;
+        let password_hash = state.password_hasher.hash(PASSWORD).await.unwrap();
+        repository
+            .update_member_password(&owner.id, &password_hash)
+            .await
+            .unwrap();
+        let now = Utc::now();
+        repository
+            .save_pending_member_totp(&MemberTotp {
+                member_id: owner.id.clone(),
+                secret: totp::generate(&owner.email).0,
+                created_at: now,
+                enabled_at: None,
+            })
+            .await
+            .unwrap();
+        repository
+            .enable_member_totp(&owner.id, 0, &[hash_token("abcdefghjk")], now)
+            .await
+            .unwrap();
+
+        (testing::serve(state).await, owner.email)
+    }
+
+    async fn challenge(base_url: &str, email: &str) -> String {
+        let response = Client::new()
+            .post(format!("{}/api/business/auth/sign-in", base_url))
+            .json(&json!({ "email": email, "password": PASSWORD }))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body: Value = response.json().await.unwrap();
+        body["two_factor_token"].as_str().unwrap().to_string()
+    }
+
+    async fn answer(base_url: &str, two_factor_token: &str, code: &str) -> (StatusCode, Value) {
+        let response = Client::new()
+            .post(format!("{}/api/business/auth/sign-in/2fa", base_url))
+            .json(&json!({ "two_factor_token": two_factor_token, "code": code }))
+            .send()
+            .await
+            .unwrap();
+        (response.status(), response.json().await.unwrap())
+    }
+
+    #[cfg_attr(feature = "postgres", ignore = "needs TEST_DATABASE_URL")]
+    #[tokio::test]
+    async fn concurrent_guesses_share_the_challenge_attempts() {
+        let (base_url, email) = serve_owner_with_2fa().await;
+        let two_factor_token = challenge(&base_url, &email).await;
+
+        let mut guesses = JoinSet::new();
+        for _ in 0..10 {
+            let base_url = base_url.clone();
+            let two_factor_token = two_factor_token.clone();
+            guesses.spawn(async move { answer(&base_url, &two_factor_token, "wrong").await });
+        }
+        let mut checked = 0;
+        while let Some(guess) = guesses.join_next().await {
+            let (status, body) = guess.unwrap();
+            assert_eq!(status, StatusCode::UNAUTHORIZED);
+            if body["message"] == "Неверный код подтверждения." {
+                checked += 1;
+            }
+        }
+        assert_eq!(checked, MAX_CHALLENGE_ATTEMPTS);
+    }
+
+    #[cfg_attr(feature = "postgres", ignore = "needs TEST_DATABASE_URL")]
+    #[tokio::test]
+    async fn failed_codes_lock_the_member_out_across_challenges() {
+        let (base_url, email) = serve_owner_with_2fa().await;
+
+        // Every guess gets a fresh challenge, the password keeps working.
+        let mut statuses = vec![];
+        for _ in 0..7 {
+            let two_factor_token = challenge(&base_url, &email).await;
+            statuses.push(answer(&base_url, &two_factor_token, "wrong").await.0);
+        }
+        assert_eq!(statuses[..6], [StatusCode::UNAUTHORIZED; 6]);
+        assert_eq!(statuses[6], StatusCode::TOO_MANY_REQUESTS);
+
+        let two_factor_token = challenge(&base_url, &email).await;
+        let (status, _) = answer(&base_url, &two_factor_token, RECOVERY_CODE).await;
+        assert_eq!(status, StatusCode::TOO_MANY_REQUESTS);
+    }
+}
@@ -0,0 +1,58 @@
+use crate::{
+    auth::Audience,
+    business::auth::CompanyMember,
+    email_verification,
+    error::{AppError, EMAIL_ALREADY_VERIFIED},
+    extract::AppQuery,
+    repository::MemberRepository,
+    AppState,
+};
+use axum::{extract::State, Extension, Json};
+use chrono::Utc;
+use serde::Deserialize;
+use serde_json::{json, Value};
+
+#[derive(Deserialize)]
+pub struct VerifyEmail {
+    pub token: String,
+}
+
+pub async fn verify_email(
+    State(app_state): State<AppState>,
+    AppQuery(verify_email): AppQuery<VerifyEmail>,
+) -> Result<Json<Value>, AppError> {
+    let member_id = email_verification::use_verification_token(
+        &app_state,
+        Audience::Business,
+        &verify_email.token,
+    )
+    .await?;
+    app_state
+        .repository
+        .mark_member_email_verified(&member_id, Utc::now())
+        .await?;
+
+    Ok(Json(json!({
+        "status": "ok"
+    })))
+}
+
+pub async fn resend_verification(
+    State(app_state): State<AppState>,
+    Extension(member): Extension<CompanyMember>,
+) -> Result<Json<Value>, AppError> {
+    if member.email_verified_at.is_some() {
+        return Err(AppError::Conflict(EMAIL_ALREADY_VERIFIED));
+    }
+    email_verification::resend_verification(
+        &app_state,
+        Audience::Business,
+        &member.id,
+        &member.email,
+    )
+    .await?;
+
+    Ok(Json(json!({
+        "status": "ok"
+    })))
+}
@@ -0,0 +1,93 @@
+use crate::{
+    auth::{encode_jwt, hash_token, Audience},
+    business::auth::{sign_up::is_unique_email, CompanyMember},
+    error::{AppError, EMAIL_ALREADY_REGISTERED, INVITATION_NOT_FOUND},
+    extract::AppJson,
+    refresh,
+    repository::MemberRepository,
+    validation::{Validate, Validator},
+    AppState,
+};
+use axum::{extract::State, Json};
+use chrono::Utc;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use uuid::Uuid;
+
+#[derive(Deserialize)]
+pub struct AcceptInvitation {
+    pub token: String,
+    pub name: String,
+    pub password: String,
+}
+
+impl Validate for AcceptInvitation {
+    fn validate(&self, v: &mut Validator) {
+        v.length("name", &self.name, 1..=100);
+        v.password("password", &self.password);
+    }
+}
+
+pub async fn accept_invitation(
+    State(app_state): State<AppState>,
+    AppJson(accept_invitation): AppJson<AcceptInvitation>,
+) -> Result<Json<Value>, AppError> {
+    app_state.validation_rules.check(&accept_invitation)?;
+
+    let now = Utc::now();
+    let invitation = match app_state
+        .repository
+        .retrieve_invitation(&hash_token(&accept_invitation.token))
+        .await?
+    {
+        Some(invitation) if invitation.accepted_at.is_none() && invitation.expires_at > now => {
+            invitation
+        }
+        _ => return Err(AppError::NotFound(INVITATION_NOT_FOUND)),
+    };
+
+    if !is_unique_email(&app_state.repository, &invitation.email).await? {
+        return Err(AppError::Conflict(EMAIL_ALREADY_REGISTERED));
+    }
+
+    let member = CompanyMember {
+        id: Uuid::new_v4().to_string(),
+        company_id: invitation.company_id,
+        name: accept_invitation.name,
+        email: invitation.email,
+        password_hash: app_state
+            .password_hasher
+            .hash(&accept_invitation.password)
+            .await?,
+        role: invitation.role,
+        // The token was mailed to this address, so using it proves the
+        // invitee reads that mailbox.
+        email_verified_at: Some(now),
+    };
+    if !app_state
+        .repository
+        .accept_invitation(&invitation.id, &member, now)
+        .await?
+    {
+        return Err(AppError::NotFound(INVITATION_NOT_FOUND));
+    }
+    let (token, claims) = encode_jwt(
+        &app_state.jwt_keys,
+        Audience::Business,
+        &member.id,
+        member.role().ok_or(AppError::Unauthorized)?,
+        None,
+    )?;
+    app_state
+        .revoked_tokens
+        .start_session(&member.id, &claims.jti, claims.expires_at())
+        .await?;
+    let refresh_token =
+        refresh::issue(&app_state.repository, Audience::Business, &member.id).await?;
+
+    Ok(Json(json!({
+        "company_id": member.company_id,
+        "token": token,
+        "refresh_token": refresh_token
+    })))
+}
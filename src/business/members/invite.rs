@@ -0,0 +1,143 @@
+use super::Invitation;
+use crate::{
+    auth::{hash_token, random_token, Role},
+    business::auth::{sign_up::is_unique_email, Company},
+    error::{AppError, EMAIL_ALREADY_REGISTERED},
+    extract::AppJson,
+    mailer::Email,
+    repository::MemberRepository,
+    validation::{Validate, Validator},
+    AppState,
+};
+use axum::{extract::State, Extension, Json};
+use chrono::{Duration, Utc};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use uuid::Uuid;
+
+const INVITATION_TTL_DAYS: i64 = 7;
+
+#[derive(Deserialize)]
+pub struct InviteMember {
+    pub email: String,
+    pub role: String,
+}
+
+impl Validate for InviteMember {
+    fn validate(&self, v: &mut Validator) {
+        v.email("email", &self.email);
+        v.check(
+            "role",
+            matches!(
+                Role::parse(&self.role),
+                Some(Role::Editor | Role::Analyst | Role::Viewer)
+            ),
+        );
+    }
+}
+
+// The invitation token is mailed to the invitee and never returned, so that
+// only the owner of the address can join. Only its hash is stored.
+pub async fn invite_member(
+    State(app_state): State<AppState>,
+    Extension(company): Extension<Company>,
+    AppJson(invite_member): AppJson<InviteMember>,
+) -> Result<Json<Value>, AppError> {
+    app_state.validation_rules.check(&invite_member)?;
+    if !is_unique_email(&app_state.repository, &invite_member.email).await? {
+        return Err(AppError::Conflict(EMAIL_ALREADY_REGISTERED));
+    }
+
+    let token = random_token();
+    let now = Utc::now();
+    let invitation = Invitation {
+        id: Uuid::new_v4().to_string(),
+        company_id: company.id.clone(),
+        email: invite_member.email,
+        role: invite_member.role,
+        token_hash: hash_token(&token),
+        created_at: now,
+        expires_at: now + Duration::days(INVITATION_TTL_DAYS),
+        accepted_at: None,
+    };
+    app_state.repository.create_invitation(&invitation).await?;
+
+    app_state.mailer.send_in_background(Email {
+        to: invitation.email,
+        subject: "Приглашение в компанию".to_string(),
+        body: format!(
+            "Вас пригласили в компанию {}.\nЧтобы принять приглашение, отправьте этот код на {}/api/business/auth/accept-invitation:\n{}\nКод действует {} дней.",
+            company.name, app_state.email_verification.public_url, token, INVITATION_TTL_DAYS
+        ),
+    });
+
+    Ok(Json(json!({
+        "invitation_id": invitation.id,
+        "expires_at": invitation.expires_at
+    })))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{repository::MemberRepository, testing};
+    use axum::http::StatusCode;
+    use chrono::Utc;
+    use serde_json::{json, Value};
+
+    #[cfg_attr(feature = "postgres", ignore = "needs TEST_DATABASE_URL")]
+    #[tokio::test]
+    async fn invitation_tokens_are_mailed_to_the_invitee() {
+        let repository = testing::repository().await;
+        let (company, owner) = testing::company(&repository).await;
+        repository
+            .mark_member_email_verified(&owner.id, Utc::now())
+            .await
+            .unwrap();
+        let state = testing::app_state(repository.clone(), "127.0.0.1:9").await;
+        let token = testing::member_token(&state, &owner).await;
+        let mailbox = testing::mailbox(&state);
+        let base_url = testing::serve(state).await;
+        let client = reqwest::Client::new();
+
+        let response = client
+            .post(format!("{}/api/business/members/invite", base_url))
+            .bearer_auth(token)
+            .json(&json!({ "email": "invitee@example.com", "role": "editor" }))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body: Value = response.json().await.unwrap();
+        assert!(body["invitation_id"].is_string());
+        assert!(body.get("token").is_none());
+
+        let sent = mailbox.wait_for(1).await;
+        assert_eq!(sent.len(), 1);
+        assert_eq!(sent[0].to, "invitee@example.com");
+        assert!(sent[0].body.contains(&company.name));
+        let invitation_token = sent[0].body.lines().nth(2).unwrap();
+
+        let response = client
+            .post(format!("{}/api/business/auth/accept-invitation", base_url))
+            .json(&json!({
+                "token": invitation_token,
+                "name": "Invitee",
+                "password": "N3w-Passw0rd!"
+            }))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let member = repository
+            .retrieve_member_by_email("invitee@example.com")
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(member.company_id, company.id);
+        assert_eq!(member.role, "editor");
+        // The invitation already proved the address, no second mail is sent.
+        assert!(member.email_verified_at.is_some());
+        assert_eq!(mailbox.sent().len(), 1);
+    }
+}
@@ -0,0 +1,15 @@
+use super::MemberProfile;
+use crate::{business::auth::Company, error::AppError, repository::MemberRepository, AppState};
+use axum::{extract::State, Extension, Json};
+
+pub async fn list_members(
+    State(app_state): State<AppState>,
+    Extension(company): Extension<Company>,
+) -> Result<Json<Vec<MemberProfile>>, AppError> {
+    let members = app_state
+        .repository
+        .retrieve_company_members(&company.id)
+        .await?;
+
+    Ok(Json(members.into_iter().map(MemberProfile::from).collect()))
+}
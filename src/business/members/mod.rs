@@ -0,0 +1,39 @@
+use crate::business::auth::CompanyMember;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use sqlx::FromRow;
+
+pub mod accept;
+pub mod invite;
+pub mod list;
+
+#[derive(FromRow, Clone, Debug)]
+pub struct Invitation {
+    pub id: String,
+    pub company_id: String,
+    pub email: String,
+    pub role: String,
+    pub token_hash: String,
+    pub created_at: DateTime<Utc>,
+    pub expires_at: DateTime<Utc>,
+    pub accepted_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Serialize)]
+pub struct MemberProfile {
+    id: String,
+    name: String,
+    email: String,
+    role: String,
+}
+
+impl From<CompanyMember> for MemberProfile {
+    fn from(member: CompanyMember) -> Self {
+        MemberProfile {
+            id: member.id,
+            name: member.name,
+            email: member.email,
+            role: member.role,
+        }
+    }
+}
@@ -1,38 +0,0 @@
-use crate::{
-    business::auth::{decode_jwt, retrieve_company_by_email},
-    AppState,
-};
-use axum::{
-    body::Body,
-    extract::{Request, State},
-    http::{self, Response, StatusCode},
-    middleware::Next,
-};
-
-pub async fn authorize_middleware(
-    State(app_state): State<AppState>,
-    mut req: Request,
-    next: Next,
-) -> Result<Response<Body>, StatusCode> {
-    let auth_header = match req.headers_mut().get(http::header::AUTHORIZATION) {
-        Some(header) => header.to_str().map_err(|_| StatusCode::UNAUTHORIZED)?,
-        None => return Err(StatusCode::UNAUTHORIZED)?,
-    };
-
-    let [_, token, ..] = *auth_header.split_whitespace().collect::<Vec<&str>>() else {
-        return Err(StatusCode::INTERNAL_SERVER_ERROR);
-    };
-
-    let token_data = match decode_jwt(token) {
-        Ok(data) => data,
-        Err(_) => return Err(StatusCode::UNAUTHORIZED),
-    };
-
-    let company = match retrieve_company_by_email(&app_state.pool, &token_data.claims.email).await {
-        Some(company) => company,
-        None => return Err(StatusCode::UNAUTHORIZED),
-    };
-
-    req.extensions_mut().insert(company);
-    Ok(next.run(req).await)
-}
@@ -1,9 +1,4 @@
+pub mod api_keys;
 pub mod auth;
-pub mod middlewares;
+pub mod members;
 pub mod promo;
-
-// pub struct BusinessResponse<T> {
-//     data: Option<T>,
-//     message: String,
-//     status_code: StatusCode,
-// }
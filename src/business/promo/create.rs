@@ -1,66 +1,58 @@
-use super::{Comment, Country};
 use crate::{
-    business::{auth::Company, promo::CreatePromo},
-    AppState,
+    business::{
+        auth::Company,
+        promo::{CreatePromo, Promo},
+    },
+    error::AppError,
+    extract::AppJson,
+    repository::PromoRepository,
+    scheduler, AppState,
 };
 use axum::{extract::State, http::StatusCode, Extension, Json};
-use chrono::Utc;
 use serde_json::json;
-use sqlx::prelude::FromRow;
 use uuid::Uuid;
 
-#[derive(FromRow, PartialEq)]
-struct Id {
-    id: String,
-}
-
 pub async fn create_promo(
     State(app_state): State<AppState>,
     Extension(company): Extension<Company>,
-    Json(create_promo): Json<CreatePromo>,
-) -> Result<(StatusCode, Json<serde_json::Value>), StatusCode> {
-    if !create_promo.is_valid() {
-        return Err(StatusCode::BAD_REQUEST);
-    }
-    let id = Uuid::new_v4().to_string();
+    AppJson(create_promo): AppJson<CreatePromo>,
+) -> Result<(StatusCode, Json<serde_json::Value>), AppError> {
+    app_state.validation_rules.check(&create_promo)?;
+
+    let now = app_state.calendar.now();
+    let publish_at = create_promo.publish_at;
+    let promo = Promo {
+        promo_id: Uuid::new_v4().to_string(),
+        company_id: company.id,
+        company_name: company.name,
+        description: create_promo.description.unwrap(),
+        image_url: create_promo.image_url,
+        target: sqlx::types::Json(create_promo.target.unwrap()),
+        max_count: create_promo.max_count.unwrap(),
+        active_from: create_promo.active_from,
+        active_until: create_promo.active_until,
+        mode: create_promo.mode.unwrap(),
+        promo_common: create_promo.promo_common,
+        create_date: now,
+        used_count: 0,
+        publish_at,
+        published_at: match publish_at {
+            Some(publish_at) if publish_at > now => None,
+            _ => Some(now),
+        },
+        active: false,
+    };
 
-    sqlx::query(
-        r#"
-        INSERT INTO promos
-        VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
-        "#,
-    )
-    .bind(create_promo.description)
-    .bind(create_promo.image_url)
-    .bind(sqlx::types::Json(create_promo.target))
-    .bind(if let Some(max_count) = create_promo.max_count {
-        Some(max_count as i32)
-    } else {
-        None
-    })
-    .bind(sqlx::types::Json(Utc::now()))
-    .bind(create_promo.active_from)
-    .bind(create_promo.active_until)
-    .bind(create_promo.mode)
-    .bind(create_promo.promo_common)
-    .bind(create_promo.promo_unique)
-    .bind(&id)
-    .bind(company.id)
-    .bind(company.name)
-    .bind(sqlx::types::Json(Vec::<String>::new()))
-    .bind(0)
-    .bind(false)
-    .bind(sqlx::types::Json(Vec::<Country>::new()))
-    .bind(sqlx::types::Json(Vec::<Comment>::new()))
-    .bind(sqlx::types::Json(Vec::<String>::new()))
-    .execute(&app_state.pool)
-    .await
-    .unwrap();
+    app_state
+        .repository
+        .create_promo(&promo, create_promo.promo_unique.as_deref())
+        .await?;
+    scheduler::schedule_promo(&app_state.repository, &app_state.calendar, &promo).await?;
 
     Ok((
         StatusCode::CREATED,
         Json(json!({
-            "id": id,
+            "id": promo.promo_id,
         })),
     ))
 }
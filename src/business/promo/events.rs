@@ -0,0 +1,44 @@
+use crate::{
+    business::auth::Company,
+    error::{AppError, NO_ACCESS_TO_PROMO, PROMO_NOT_FOUND},
+    repository::PromoRepository,
+    AppState,
+};
+use axum::{
+    extract::{Path, State},
+    Extension, Json,
+};
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use sqlx::FromRow;
+
+// A lifecycle transition of a promo: `published`, `expired` or `exhausted`.
+#[derive(Serialize, FromRow)]
+pub struct PromoEvent {
+    pub id: i64,
+    pub kind: String,
+    pub occurred_at: DateTime<Utc>,
+}
+
+pub async fn get_promo_events(
+    State(app_state): State<AppState>,
+    Extension(company): Extension<Company>,
+    Path(id): Path<String>,
+) -> Result<Json<Vec<PromoEvent>>, AppError> {
+    let promo = match app_state
+        .repository
+        .retrieve_promo(&id, app_state.calendar.today())
+        .await?
+    {
+        Some(promo) => promo,
+        None => return Err(AppError::NotFound(PROMO_NOT_FOUND)),
+    };
+
+    if promo.company_id != company.id {
+        return Err(AppError::Forbidden(NO_ACCESS_TO_PROMO));
+    }
+
+    let events = app_state.repository.retrieve_promo_events(&id).await?;
+
+    Ok(Json(events))
+}
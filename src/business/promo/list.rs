@@ -1,120 +1,349 @@
-use super::{Promo, PromoReadOnly};
-use crate::{business::auth::Company, AppState};
+use super::PromoReadOnly;
+use crate::{
+    business::auth::Company, error::AppError, extract::AppQuery, pagination::default_limit,
+    repository::PromoRepository, AppState,
+};
 use axum::{
-    extract::{Query, State},
+    extract::State,
     http::{HeaderMap, HeaderValue, StatusCode},
     response::{IntoResponse, Response},
     Extension, Json,
 };
-use chrono::NaiveDate;
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use chrono::{DateTime, NaiveDate, SecondsFormat, Utc};
+use sqlx::FromRow;
+use std::str::FromStr;
+
+// Listings are always sorted in descending order, ties broken by promo id.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum PromoSort {
+    CreateDate,
+    // A missing `active_from` sorts as the earliest date.
+    ActiveFrom,
+    // A missing `active_until` sorts as the latest date.
+    ActiveUntil,
+    LikeCount,
+    UsedCount,
+}
+
+impl PromoSort {
+    fn parse(value: &str) -> Option<Self> {
+        match value {
+            "create_date" => Some(PromoSort::CreateDate),
+            "active_from" => Some(PromoSort::ActiveFrom),
+            "active_until" => Some(PromoSort::ActiveUntil),
+            "like_count" => Some(PromoSort::LikeCount),
+            "used_count" => Some(PromoSort::UsedCount),
+            _ => None,
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            PromoSort::CreateDate => "create_date",
+            PromoSort::ActiveFrom => "active_from",
+            PromoSort::ActiveUntil => "active_until",
+            PromoSort::LikeCount => "like_count",
+            PromoSort::UsedCount => "used_count",
+        }
+    }
+}
+
+// Narrows a company's promo listing. Empty or absent fields filter nothing.
+#[derive(Default)]
+pub struct PromoFilter {
+    // Lowercased. Promos without a target country always pass.
+    pub countries: Vec<String>,
+    pub mode: Option<String>,
+    pub active: Option<bool>,
+    // Promos valid on at least one day of `[date_from; date_until]`.
+    pub date_from: Option<NaiveDate>,
+    pub date_until: Option<NaiveDate>,
+    // Matched case-insensitively anywhere in the description.
+    pub text: Option<String>,
+}
+
+// The last promo of a page, which the next page starts right after. Clients
+// get it as an opaque token tied to the sort order it was issued for. The
+// sort key is checked against the type of that order when the token is read,
+// and kept in the form the database lists it in: RFC 3339 for times.
+pub struct ListCursor {
+    pub sort_key: String,
+    pub promo_id: String,
+}
+
+impl ListCursor {
+    fn encode(&self, sort: PromoSort) -> String {
+        URL_SAFE_NO_PAD.encode(format!(
+            "{}:{}:{}",
+            sort.name(),
+            self.promo_id,
+            self.sort_key
+        ))
+    }
+
+    fn decode(token: &str, sort: PromoSort) -> Option<Self> {
+        let token = String::from_utf8(URL_SAFE_NO_PAD.decode(token).ok()?).ok()?;
+        let mut parts = token.splitn(3, ':');
+        if parts.next()? != sort.name() {
+            return None;
+        }
+
+        let promo_id = parts.next()?.to_string();
+        let sort_key = parts.next()?;
+        let sort_key = match sort {
+            PromoSort::CreateDate => DateTime::parse_from_rfc3339(sort_key)
+                .ok()?
+                .with_timezone(&Utc)
+                .to_rfc3339_opts(SecondsFormat::AutoSi, false),
+            PromoSort::ActiveFrom | PromoSort::ActiveUntil => {
+                NaiveDate::from_str(sort_key).ok()?.to_string()
+            }
+            PromoSort::LikeCount => sort_key.parse::<i64>().ok()?.to_string(),
+            PromoSort::UsedCount => sort_key.parse::<i32>().ok()?.to_string(),
+        };
+
+        Some(ListCursor { promo_id, sort_key })
+    }
+}
+
+// Which part of the sorted listing to return. With a cursor, the page starts
+// after it and `offset` is zero.
+pub struct PromoPage {
+    pub sort: PromoSort,
+    pub after: Option<ListCursor>,
+    pub limit: i64,
+    pub offset: i64,
+}
+
+// A listed promo along with its sort key as text, to build the cursor from.
+#[derive(FromRow)]
+pub struct ListedPromo {
+    #[sqlx(flatten)]
+    pub promo: PromoReadOnly,
+    pub sort_key: String,
+}
 
 pub async fn list_promos(
     State(app_state): State<AppState>,
     Extension(company): Extension<Company>,
-    Query(params): Query<Vec<(String, String)>>,
-) -> Response {
-    let promos: Vec<Promo> = sqlx::query_as(
-        r#"
-        SELECT * FROM promos WHERE company_id = ?
-        "#,
-    )
-    .bind(company.id)
-    .fetch_all(&app_state.pool)
-    .await
-    .unwrap();
-
-    let mut countries = vec![];
-
-    for param in params.iter() {
-        if param.0 == "country" {
-            countries.push(param.1.clone().to_lowercase());
+    AppQuery(params): AppQuery<Vec<(String, String)>>,
+) -> Result<Response, AppError> {
+    let mut filter = PromoFilter::default();
+    let mut sort = PromoSort::CreateDate;
+    let mut limit = default_limit();
+    let mut offset = None;
+    let mut cursor = None;
+
+    let mut validator = app_state.validation_rules.validator();
+    for (name, value) in params.iter() {
+        match name.as_str() {
+            "limit" => match value.parse() {
+                Ok(value) => limit = value,
+                Err(_) => validator.fail("limit", "invalid"),
+            },
+            "offset" => match value.parse::<u32>() {
+                Ok(value) => offset = Some(value),
+                Err(_) => validator.fail("offset", "invalid"),
+            },
+            "sort_by" => match PromoSort::parse(value) {
+                Some(value) => sort = value,
+                None => validator.fail("sort_by", "invalid"),
+            },
+            "cursor" => cursor = Some(value),
+            // Both `country=ru&country=fr` and `country=ru,fr` are accepted.
+            "country" => {
+                for country in value.split(',') {
+                    validator.country("country", country);
+                    filter.countries.push(country.to_lowercase());
+                }
+            }
+            "mode" => match value.to_uppercase().as_str() {
+                mode @ ("COMMON" | "UNIQUE") => filter.mode = Some(mode.to_string()),
+                _ => validator.fail("mode", "invalid"),
+            },
+            "active" => match value.parse() {
+                Ok(value) => filter.active = Some(value),
+                Err(_) => validator.fail("active", "invalid"),
+            },
+            "date_from" => match NaiveDate::from_str(value) {
+                Ok(date) => filter.date_from = Some(date),
+                Err(_) => validator.fail("date_from", "invalid"),
+            },
+            "date_until" => match NaiveDate::from_str(value) {
+                Ok(date) => filter.date_until = Some(date),
+                Err(_) => validator.fail("date_until", "invalid"),
+            },
+            "q" => {
+                validator.length("q", value, 1..=100);
+                filter.text = Some(value.clone());
+            }
+            _ => (),
         }
     }
 
-    let mut promos: Vec<Promo> = promos
-        .into_iter()
-        .filter(|promo| {
-            promo.target.0.country.is_none()
-                || countries.contains(&promo.target.0.country.as_ref().unwrap())
-                || countries.is_empty()
-        })
-        .collect();
-    println!("{:?}", params);
-
-    if let Some(offset) = params.iter().rposition(|param| param.0 == "offset") {
-        let mut offset = params[offset].1.parse::<usize>().unwrap();
-        if offset + 1 > promos.len() {
-            offset = promos.len();
+    if let (Some(date_from), Some(date_until)) = (filter.date_from, filter.date_until) {
+        validator.check("date_until", date_from <= date_until);
+    }
+    let cursor = match cursor {
+        Some(token) => {
+            // A cursor already marks where the page starts.
+            validator.check("offset", offset.is_none());
+            let cursor = ListCursor::decode(token, sort);
+            validator.check("cursor", cursor.is_some());
+            cursor
+        }
+        None => None,
+    };
+    validator.finish()?;
+
+    let today = app_state.calendar.today();
+    let total = app_state
+        .repository
+        .count_company_promos(&company.id, &filter, today)
+        .await?;
+
+    // One promo past the page tells whether there is a next one.
+    let page = PromoPage {
+        sort,
+        after: cursor,
+        limit: limit as i64 + 1,
+        offset: offset.unwrap_or(0) as i64,
+    };
+    let mut promos = app_state
+        .repository
+        .list_company_promos(&company.id, &filter, &page, today)
+        .await?;
+
+    let mut headers = HeaderMap::new();
+    headers.insert("X-Total-Count", HeaderValue::from(total));
+    if promos.len() > limit as usize {
+        promos.truncate(limit as usize);
+        if let Some(last) = promos.last() {
+            let next = ListCursor {
+                sort_key: last.sort_key.clone(),
+                promo_id: last.promo.promo_id.clone(),
+            };
+            // URL-safe base64 is always a valid header value.
+            if let Ok(next) = HeaderValue::from_str(&next.encode(sort)) {
+                headers.insert("X-Next-Cursor", next);
+            }
         }
-        promos = promos.split_at(offset).1.to_vec();
     }
 
-    if let Some(limit) = params.iter().rposition(|param| param.0 == "limit") {
-        let mut limit = params[limit].1.parse::<usize>().unwrap();
-        if limit + 1 > promos.len() {
-            limit = promos.len();
+    let promos: Vec<PromoReadOnly> = promos.into_iter().map(|listed| listed.promo).collect();
+    Ok((StatusCode::OK, headers, Json(promos)).into_response())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ListCursor, PromoSort};
+    use crate::testing;
+    use axum::http::StatusCode;
+    use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+    use chrono::Utc;
+    use serde_json::{json, Value};
+
+    fn token(sort: &str, sort_key: &str) -> String {
+        URL_SAFE_NO_PAD.encode(format!("{}:promo-id:{}", sort, sort_key))
+    }
+
+    #[test]
+    fn cursor_keys_are_read_by_the_type_of_the_sort() {
+        for (sort, sort_key) in [
+            (PromoSort::CreateDate, "2025-01-02T10:00:00.123456+00:00"),
+            (PromoSort::ActiveFrom, "2025-01-02"),
+            (PromoSort::ActiveUntil, "9999-12-31"),
+            (PromoSort::LikeCount, "12"),
+            (PromoSort::UsedCount, "0"),
+        ] {
+            let cursor = ListCursor::decode(&token(sort.name(), sort_key), sort).unwrap();
+            assert_eq!(cursor.promo_id, "promo-id");
+            assert_eq!(cursor.sort_key, sort_key);
+            let cursor = ListCursor::decode(&cursor.encode(sort), sort).unwrap();
+            assert_eq!(cursor.sort_key, sort_key);
         }
-        promos = promos.split_at(limit).0.to_vec();
+
+        // Times are kept in UTC, whatever offset they came with.
+        let cursor = ListCursor::decode(
+            &token("create_date", "2025-01-02T13:00:00+03:00"),
+            PromoSort::CreateDate,
+        )
+        .unwrap();
+        assert_eq!(cursor.sort_key, "2025-01-02T10:00:00+00:00");
     }
 
-    if let Some(sort_idx) = params.iter().rposition(|param| param.0 == "sort_by") {
-        if params[sort_idx].1 != "active_from" && params[sort_idx].1 != "active_until" {
-            return StatusCode::BAD_REQUEST.into_response();
-        } else if params[sort_idx].1 == "active_until" {
-            promos.sort_by(|a, b| {
-                a.active_until
-                    .unwrap_or(sqlx::types::Json(
-                        NaiveDate::from_ymd_opt(1, 12, 31).unwrap(),
-                    ))
-                    .0
-                    .cmp(
-                        &b.active_until
-                            .unwrap_or(sqlx::types::Json(
-                                NaiveDate::from_ymd_opt(1, 12, 31).unwrap(),
-                            ))
-                            .0,
-                    )
-            });
-        } else if params[sort_idx].1 == "active_from" {
-            promos.sort_by(|a, b| {
-                a.active_from
-                    .unwrap_or(sqlx::types::Json(
-                        NaiveDate::from_ymd_opt(1, 12, 31).unwrap(),
-                    ))
-                    .0
-                    .cmp(
-                        &b.active_from
-                            .unwrap_or(sqlx::types::Json(
-                                NaiveDate::from_ymd_opt(1, 12, 31).unwrap(),
-                            ))
-                            .0,
-                    )
-            });
+    #[test]
+    fn malformed_cursors_are_rejected() {
+        for (sort, token) in [
+            (PromoSort::CreateDate, token("create_date", "yesterday")),
+            (PromoSort::CreateDate, token("create_date", "2025-01-02")),
+            (PromoSort::ActiveFrom, token("active_from", "2025-13-01")),
+            (PromoSort::LikeCount, token("like_count", "1.5")),
+            (PromoSort::UsedCount, token("used_count", "99999999999")),
+            // Issued for another order.
+            (PromoSort::LikeCount, token("used_count", "1")),
+            (PromoSort::LikeCount, "not base64!".to_string()),
+            (PromoSort::LikeCount, URL_SAFE_NO_PAD.encode("like_count:1")),
+        ] {
+            assert!(ListCursor::decode(&token, sort).is_none());
         }
-    } else {
-        promos.sort_by(|b, a| a.create_date.0.cmp(&b.create_date.0));
     }
-    let read_only_promos: Vec<PromoReadOnly> = promos
-        .into_iter()
-        .map(|promo| PromoReadOnly {
-            description: promo.description,
-            image_url: promo.image_url,
-            target: promo.target,
-            max_count: promo.max_count,
-            active_from: promo.active_from,
-            active_until: promo.active_until,
-            mode: promo.mode,
-            promo_common: promo.promo_common,
-            promo_unique: promo.promo_unique,
-            promo_id: promo.promo_id,
-            company_id: promo.company_id,
-            company_name: promo.company_name,
-            like_count: promo.likes.0.len() as i32,
-            used_count: promo.used_count,
-            active: promo.active,
-        })
-        .collect();
-    let mut headers = HeaderMap::new();
-    headers.insert("X-Total-Count", HeaderValue::from(read_only_promos.len()));
-    (StatusCode::OK, headers, Json(read_only_promos)).into_response()
+
+    #[cfg_attr(feature = "postgres", ignore = "needs TEST_DATABASE_URL")]
+    #[tokio::test]
+    async fn pages_follow_the_cursor() {
+        let repository = testing::repository().await;
+        let (company, owner) = testing::company(&repository).await;
+        let mut promo_ids = vec![];
+        for _ in 0..3 {
+            let promo = testing::promo(&repository, &company, 10, None, Utc::now()).await;
+            promo_ids.push(promo.promo_id);
+        }
+        promo_ids.reverse();
+        let state = testing::app_state(repository, "127.0.0.1:9").await;
+        let access_token = testing::member_token(&state, &owner).await;
+        let base_url = testing::serve(state).await;
+        let client = reqwest::Client::new();
+        let list = |query: Vec<(&'static str, String)>| {
+            client
+                .get(format!("{}/api/business/promo", base_url))
+                .bearer_auth(&access_token)
+                .query(&query)
+                .send()
+        };
+
+        let response = list(vec![("limit", "2".to_string())]).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let cursor = response.headers()["X-Next-Cursor"]
+            .to_str()
+            .unwrap()
+            .to_string();
+        let page: Vec<Value> = response.json().await.unwrap();
+        assert_eq!(page.len(), 2);
+
+        let response = list(vec![("limit", "2".to_string()), ("cursor", cursor)])
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert!(response.headers().get("X-Next-Cursor").is_none());
+        let next_page: Vec<Value> = response.json().await.unwrap();
+
+        let listed: Vec<&Value> = page.iter().chain(&next_page).collect();
+        let listed: Vec<&str> = listed
+            .iter()
+            .map(|promo| promo["promo_id"].as_str().unwrap())
+            .collect();
+        assert_eq!(listed, promo_ids);
+
+        let response = list(vec![("cursor", token("create_date", "soon"))])
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        let body: Value = response.json().await.unwrap();
+        assert_eq!(
+            body["errors"],
+            json!([{ "field": "cursor", "code": "invalid" }])
+        );
+    }
 }
@@ -1,34 +1,34 @@
-use std::{collections::HashSet, str::FromStr};
-
+use crate::validation::{Validate, Validator};
 use chrono::{DateTime, NaiveDate, Utc};
 use serde::{Deserialize, Serialize};
 use sqlx::{prelude::FromRow, types::Json};
+use std::str::FromStr;
 
 pub mod create;
+pub mod events;
 pub mod list;
 pub mod promo_by_id;
 
 #[derive(FromRow, Clone)]
 pub struct Promo {
+    pub promo_id: String,
+    pub company_id: String,
+    pub company_name: String,
     pub description: String,
     pub image_url: Option<String>,
     pub target: Json<Target>,
     pub max_count: i32,
-    pub create_date: Json<DateTime<Utc>>,
-    pub active_from: Option<Json<NaiveDate>>,
-    pub active_until: Option<Json<NaiveDate>>,
+    pub active_from: Option<NaiveDate>,
+    pub active_until: Option<NaiveDate>,
     pub mode: String,
     pub promo_common: Option<String>,
-    pub promo_unique: Option<Json<Vec<String>>>,
-    pub promo_id: String,
-    pub company_id: String,
-    pub company_name: String,
-    pub likes: Json<HashSet<String>>,
+    pub create_date: DateTime<Utc>,
     pub used_count: i32,
+    pub publish_at: Option<DateTime<Utc>>,
+    // Unset until the promo goes live, see `scheduler`.
+    pub published_at: Option<DateTime<Utc>>,
+    // Computed by the repository for the day of the read; never stored.
     pub active: bool,
-    pub countries: Json<Vec<Country>>,
-    pub comments: Json<HashSet<Comment>>,
-    pub activated_users: Json<HashSet<String>>,
 }
 
 #[derive(Serialize, FromRow)]
@@ -39,9 +39,9 @@ pub struct PromoReadOnly {
     target: Json<Target>,
     max_count: i32,
     #[serde(skip_serializing_if = "Option::is_none")]
-    active_from: Option<Json<NaiveDate>>,
+    active_from: Option<NaiveDate>,
     #[serde(skip_serializing_if = "Option::is_none")]
-    active_until: Option<Json<NaiveDate>>,
+    active_until: Option<NaiveDate>,
     mode: String,
     #[serde(skip_serializing_if = "Option::is_none")]
     promo_common: Option<String>,
@@ -53,9 +53,13 @@ pub struct PromoReadOnly {
     like_count: i32,
     used_count: i32,
     active: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    publish_at: Option<DateTime<Utc>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    published_at: Option<DateTime<Utc>>,
 }
 
-#[derive(Serialize)]
+#[derive(Serialize, FromRow)]
 pub struct PromoForUser {
     pub promo_id: String,
     pub company_id: String,
@@ -77,60 +81,71 @@ pub struct PatchPromo {
     max_count: Option<i32>,
     active_from: Option<Json<String>>,
     active_until: Option<Json<String>>,
+    publish_at: Option<DateTime<Utc>>,
+}
+
+// The dates of a `PatchPromo`, parsed once while validating it.
+pub struct PatchDates {
+    pub active_from: Option<NaiveDate>,
+    pub active_until: Option<NaiveDate>,
 }
 
 impl PatchPromo {
-    pub fn is_valid(&self, promo: &Promo) -> bool {
+    // Partly depends on the promo being edited, so it is run through
+    // `ValidationRules::validator` rather than the `Validate` trait. The
+    // returned dates are only complete once the validator has finished cleanly.
+    pub fn validate(&self, v: &mut Validator, promo: &Promo) -> PatchDates {
         if let Some(ref description) = self.description {
-            if description.len() < 10 || description.len() > 300 {
-                return false;
-            }
+            v.length("description", description, 10..=300);
         }
         if let Some(ref image_url) = self.image_url {
-            if image_url.len() > 350 {
-                return false;
-            }
+            v.url("image_url", image_url);
         }
         if let Some(max_count) = self.max_count {
-            if max_count as i32 > promo.max_count {
-                return false;
-            } else if promo.mode == "UNIQUE" && max_count != 1 {
-                return false;
-            }
+            v.check(
+                "max_count",
+                max_count <= promo.max_count && (promo.mode != "UNIQUE" || max_count == 1),
+            );
         }
         if let Some(ref target) = self.target {
-            return target.is_valid();
+            v.nested("target", &target.0);
         }
 
-        if let Some(ref active_from) = self.active_from {
-            match NaiveDate::from_str(&active_from) {
-                Ok(_) => (),
-                Err(_) => return false,
-            };
+        let active_from = self
+            .active_from
+            .as_ref()
+            .map(|date| NaiveDate::from_str(date));
+        let active_until = self
+            .active_until
+            .as_ref()
+            .map(|date| NaiveDate::from_str(date));
+        if let Some(ref active_from) = active_from {
+            v.check("active_from", active_from.is_ok());
         }
-
-        if let Some(ref active_until) = self.active_until {
-            match NaiveDate::from_str(&active_until) {
-                Ok(_) => (),
-                Err(_) => return false,
-            };
+        if let Some(ref active_until) = active_until {
+            v.check("active_until", active_until.is_ok());
+        }
+        let active_from = active_from.and_then(Result::ok);
+        let active_until = active_until.and_then(Result::ok);
+        if let (Some(active_from), Some(active_until)) = (active_from, active_until) {
+            v.check("active_until", active_from <= active_until);
+        }
+        // A promo that is already live can't be published again.
+        if self.publish_at.is_some() {
+            v.check("publish_at", promo.published_at.is_none());
         }
 
-        if self.active_from.is_some() && self.active_until.is_some() {
-            if NaiveDate::from_str(self.active_from.as_ref().unwrap()).unwrap()
-                > NaiveDate::from_str(self.active_until.as_ref().unwrap()).unwrap()
-            {
-                return false;
-            }
+        PatchDates {
+            active_from,
+            active_until,
         }
-        true
     }
 }
 
 #[derive(Serialize)]
 pub struct PromoStat {
     activate_count: i32,
-    countries: Json<Vec<Country>>,
+    countries: Vec<Country>,
 }
 
 #[derive(Serialize, Deserialize, FromRow)]
@@ -139,55 +154,56 @@ pub struct CreatePromo {
     image_url: Option<String>,
     target: Option<Target>,
     max_count: Option<i32>,
-    active_from: Option<Json<NaiveDate>>,
-    active_until: Option<Json<NaiveDate>>,
+    active_from: Option<NaiveDate>,
+    active_until: Option<NaiveDate>,
     mode: Option<String>,
     promo_common: Option<String>,
-    promo_unique: Option<Json<Vec<String>>>,
+    promo_unique: Option<Vec<String>>,
+    // Goes live right away when absent or already past.
+    publish_at: Option<DateTime<Utc>>,
 }
 
-impl CreatePromo {
-    pub fn is_valid(&self) -> bool {
-        if self.description.is_none()
-            || self.mode.is_none()
-            || self.max_count.is_none()
-            || self.target.is_none()
-        {
-            return false;
+impl Validate for CreatePromo {
+    fn validate(&self, v: &mut Validator) {
+        if let Some(description) = v.required("description", &self.description) {
+            v.length("description", description, 10..=300);
+        }
+        if let Some(ref image_url) = self.image_url {
+            v.url("image_url", image_url);
+        }
+        if let Some(target) = v.required("target", &self.target) {
+            v.nested("target", target);
+        }
+        if let (Some(active_from), Some(active_until)) = (self.active_from, self.active_until) {
+            v.check("active_until", active_from <= active_until);
         }
 
-        if self.mode.as_ref().unwrap() != "COMMON" && self.mode.as_ref().unwrap() != "UNIQUE" {
-            return false;
-        } else if (self.mode.as_ref().unwrap() == "COMMON" && self.promo_common.is_none())
-            || (self.mode.as_ref().unwrap() == "UNIQUE" && self.promo_unique.is_none())
-        {
-            return false;
-        } else if self.mode.as_ref().unwrap() == "COMMON"
-            && (self.promo_common.as_ref().unwrap().chars().count() < 5
-                || self.promo_common.as_ref().unwrap().chars().count() > 30)
-        {
-            return false;
-        } else if self.mode.as_ref().unwrap() == "UNIQUE"
-            && (self.promo_unique.as_ref().unwrap().is_empty()
-                || self.promo_unique.as_ref().unwrap().len() > 5000)
-        {
-            return false;
-        } else if self.description.as_ref().unwrap().chars().count() < 10
-            || self.description.as_ref().unwrap().chars().count() > 300
-        {
-            return false;
-        } else if self.promo_unique.is_some() {
-            return self
-                .promo_unique
-                .as_ref()
-                .unwrap()
-                .0
-                .iter()
-                .filter(|promo| promo.chars().count() < 3 || promo.chars().count() > 30)
-                .collect::<Vec<&String>>()
-                .is_empty();
+        let max_count = v.required("max_count", &self.max_count).copied();
+        if let Some(max_count) = max_count {
+            v.range("max_count", max_count, 0..=100_000_000);
+        }
+        match v.required("mode", &self.mode).map(String::as_str) {
+            Some("COMMON") => match self.promo_common {
+                Some(ref promo_common) => v.length("promo_common", promo_common, 5..=30),
+                None => v.fail("promo_common", "required"),
+            },
+            Some("UNIQUE") => {
+                if let Some(max_count) = max_count {
+                    v.check("max_count", max_count == 1);
+                }
+                match self.promo_unique {
+                    Some(ref promo_unique) => {
+                        v.range("promo_unique", promo_unique.len(), 1..=5000);
+                        for promo in promo_unique {
+                            v.length("promo_unique", promo, 3..=30);
+                        }
+                    }
+                    None => v.fail("promo_unique", "required"),
+                }
+            }
+            Some(_) => v.fail("mode", "invalid"),
+            None => {}
         }
-        true
     }
 }
 
@@ -203,41 +219,26 @@ pub struct Target {
     pub categories: Option<Vec<String>>,
 }
 
-impl Target {
-    pub fn is_valid(&self) -> bool {
+impl Validate for Target {
+    fn validate(&self, v: &mut Validator) {
         if let Some(age_from) = self.age_from {
-            if age_from < 0 || age_from > 100 {
-                return false;
-            }
+            v.range("age_from", age_from, 0..=100);
         }
         if let Some(age_until) = self.age_until {
-            if age_until < 0 || age_until > 100 {
-                return false;
-            }
+            v.range("age_until", age_until, 0..=100);
         }
-        if self.age_from.is_some() && self.age_until.is_some() {
-            if self.age_from.unwrap() > self.age_until.unwrap() {
-                return false;
-            }
+        if let (Some(age_from), Some(age_until)) = (self.age_from, self.age_until) {
+            v.check("age_until", age_from <= age_until);
         }
         if let Some(ref country) = self.country {
-            if country.chars().count() != 2 {
-                return false;
-            }
+            v.country("country", country);
         }
         if let Some(ref categories) = self.categories {
-            if categories.len() > 20
-                || !categories
-                    .iter()
-                    .filter(|category| category.len() < 2 || category.len() > 20)
-                    .collect::<Vec<&String>>()
-                    .is_empty()
-            {
-                return false;
+            v.range("categories", categories.len(), 0..=20);
+            for category in categories {
+                v.length("categories", category, 2..=20);
             }
         }
-
-        true
     }
 }
 
@@ -247,18 +248,19 @@ pub struct Country {
     pub activate_count: i32,
 }
 
-#[derive(Clone, Serialize, Deserialize, FromRow, Hash, PartialEq, Eq)]
+#[derive(Serialize, FromRow)]
 pub struct Comment {
     pub id: String,
     pub text: String,
-    pub date: String,
+    pub date: DateTime<Utc>,
+    #[sqlx(flatten)]
     pub author: CommentAuthor,
 }
 
-#[derive(Clone, Serialize, Deserialize, FromRow, Hash, PartialEq, Eq)]
+#[derive(Serialize, FromRow)]
 pub struct CommentAuthor {
     pub name: String,
     pub surname: String,
-    pub email: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub avatar_url: Option<String>,
 }
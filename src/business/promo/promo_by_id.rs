@@ -1,162 +1,106 @@
-use std::str::FromStr;
-
-use super::{PatchPromo, Promo, PromoReadOnly, PromoStat};
-use crate::{business::auth::Company, AppState};
+use super::{PatchPromo, PromoReadOnly, PromoStat};
+use crate::{
+    business::auth::Company,
+    error::{AppError, NO_ACCESS_TO_PROMO, PROMO_NOT_FOUND},
+    extract::AppJson,
+    repository::PromoRepository,
+    scheduler, AppState,
+};
 use axum::{
     extract::{Path, State},
-    http::StatusCode,
     Extension, Json,
 };
-use chrono::NaiveDate;
 
 pub async fn get_promo(
     State(app_state): State<AppState>,
     Extension(company): Extension<Company>,
     Path(id): Path<String>,
-) -> Result<Json<PromoReadOnly>, StatusCode> {
-    let promo: Option<Promo> = sqlx::query_as(
-        r#"
-        SELECT * FROM promos WHERE promo_id = ?
-        "#,
-    )
-    .bind(id)
-    .fetch_optional(&app_state.pool)
-    .await
-    .unwrap();
-
-    if promo.is_none() {
-        return Err(StatusCode::NOT_FOUND);
-    }
-
-    let promo = promo.unwrap();
+) -> Result<Json<PromoReadOnly>, AppError> {
+    let promo = match app_state
+        .repository
+        .retrieve_promo_read_only(&id, app_state.calendar.today())
+        .await?
+    {
+        Some(promo) => promo,
+        None => return Err(AppError::NotFound(PROMO_NOT_FOUND)),
+    };
 
     if promo.company_id != company.id {
-        return Err(StatusCode::FORBIDDEN);
+        return Err(AppError::Forbidden(NO_ACCESS_TO_PROMO));
     }
 
-    Ok(Json(PromoReadOnly {
-        description: promo.description,
-        image_url: promo.image_url,
-        target: promo.target,
-        max_count: promo.max_count,
-        active_from: promo.active_from,
-        active_until: promo.active_until,
-        mode: promo.mode,
-        promo_common: promo.promo_common,
-        promo_unique: Some(promo.promo_unique.unwrap_or_default()),
-        promo_id: promo.promo_id,
-        company_id: promo.company_id,
-        company_name: promo.company_name,
-        like_count: promo.likes.0.len() as i32,
-        used_count: promo.used_count,
-        active: promo.active,
-    }))
+    Ok(Json(promo))
 }
 
 pub async fn edit_promo(
     State(app_state): State<AppState>,
     Extension(company): Extension<Company>,
     Path(id): Path<String>,
-    Json(patch_promo): Json<PatchPromo>,
-) -> Result<Json<PromoReadOnly>, StatusCode> {
-    let promo: Option<Promo> = sqlx::query_as(
-        r#"
-        SELECT * FROM promos WHERE promo_id = ?
-    "#,
-    )
-    .bind(&id)
-    .fetch_optional(&app_state.pool)
-    .await
-    .unwrap();
+    AppJson(patch_promo): AppJson<PatchPromo>,
+) -> Result<Json<PromoReadOnly>, AppError> {
+    let mut promo = match app_state
+        .repository
+        .retrieve_promo(&id, app_state.calendar.today())
+        .await?
+    {
+        Some(promo) => promo,
+        None => return Err(AppError::NotFound(PROMO_NOT_FOUND)),
+    };
 
-    if promo.is_none() {
-        return Err(StatusCode::NOT_FOUND);
+    if promo.company_id != company.id {
+        return Err(AppError::Forbidden(NO_ACCESS_TO_PROMO));
     }
+    let mut validator = app_state.validation_rules.validator();
+    let dates = patch_promo.validate(&mut validator, &promo);
+    validator.finish()?;
 
-    if promo.as_ref().unwrap().company_id != company.id {
-        return Err(StatusCode::FORBIDDEN);
-    }
-    if !patch_promo.is_valid(promo.as_ref().unwrap()) {
-        return Err(StatusCode::BAD_REQUEST);
-    }
-    let mut promo = promo.unwrap();
     promo.description = patch_promo.description.unwrap_or(promo.description);
     if let Some(image_url) = patch_promo.image_url {
         promo.image_url = Some(image_url);
     }
     promo.target = patch_promo.target.unwrap_or(promo.target);
     promo.max_count = patch_promo.max_count.unwrap_or(promo.max_count);
-    if let Some(_) = patch_promo.active_from {
-        promo.active_from = Some(sqlx::types::Json(
-            NaiveDate::from_str(&patch_promo.active_from.unwrap()).unwrap(),
-        ));
-    }
-    if let Some(_) = patch_promo.active_until {
-        promo.active_until = Some(sqlx::types::Json(
-            NaiveDate::from_str(&patch_promo.active_until.unwrap()).unwrap(),
-        ));
+    promo.active_from = dates.active_from.or(promo.active_from);
+    promo.active_until = dates.active_until.or(promo.active_until);
+    if let Some(publish_at) = patch_promo.publish_at {
+        promo.publish_at = Some(publish_at);
     }
 
-    sqlx::query(r#"
-        UPDATE promos
-        SET description = ?, image_url = ?, target = ?, max_count = ?, active_from = ?, active_until = ?
-        WHERE promo_id = ?
-        "#)
-    .bind(&promo.description)
-    .bind(&promo.image_url)
-    .bind(&promo.target)
-    .bind(promo.max_count)
-    .bind(&promo.active_from)
-    .bind(&promo.active_until)
-    .bind(id)
-    .fetch_optional(&app_state.pool)
-    .await
-    .unwrap();
+    app_state.repository.update_promo(&promo).await?;
+    scheduler::schedule_promo(&app_state.repository, &app_state.calendar, &promo).await?;
 
-    Ok(Json(PromoReadOnly {
-        description: promo.description,
-        image_url: promo.image_url,
-        target: promo.target,
-        max_count: promo.max_count,
-        active_from: promo.active_from,
-        active_until: promo.active_until,
-        mode: promo.mode,
-        promo_common: promo.promo_common,
-        promo_unique: Some(promo.promo_unique.unwrap_or_default()),
-        promo_id: promo.promo_id,
-        company_id: promo.company_id,
-        company_name: promo.company_name,
-        like_count: promo.likes.0.len() as i32,
-        used_count: promo.used_count,
-        active: promo.active,
-    }))
+    match app_state
+        .repository
+        .retrieve_promo_read_only(&id, app_state.calendar.today())
+        .await?
+    {
+        Some(promo) => Ok(Json(promo)),
+        None => Err(AppError::NotFound(PROMO_NOT_FOUND)),
+    }
 }
 
 pub async fn get_promo_stat(
     State(app_state): State<AppState>,
     Extension(company): Extension<Company>,
     Path(id): Path<String>,
-) -> Result<Json<PromoStat>, StatusCode> {
-    let promo: Option<Promo> = sqlx::query_as(
-        r#"
-        SELECT * FROM promos WHERE promo_id = ?
-        "#,
-    )
-    .bind(id)
-    .fetch_optional(&app_state.pool)
-    .await
-    .unwrap();
+) -> Result<Json<PromoStat>, AppError> {
+    let promo = match app_state
+        .repository
+        .retrieve_promo(&id, app_state.calendar.today())
+        .await?
+    {
+        Some(promo) => promo,
+        None => return Err(AppError::NotFound(PROMO_NOT_FOUND)),
+    };
 
-    if promo.is_none() {
-        return Err(StatusCode::BAD_REQUEST);
-    }
-    let promo = promo.unwrap();
     if promo.company_id != company.id {
-        return Err(StatusCode::UNAUTHORIZED);
+        return Err(AppError::Forbidden(NO_ACCESS_TO_PROMO));
     }
 
+    let countries = app_state.repository.retrieve_promo_countries(&id).await?;
+
     Ok(Json(PromoStat {
         activate_count: promo.used_count,
-        countries: promo.countries,
+        countries,
     }))
 }
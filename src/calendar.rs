@@ -0,0 +1,132 @@
+use crate::clock::Clock;
+use chrono::{DateTime, Days, FixedOffset, NaiveDate, Utc};
+use std::{env, str::FromStr};
+
+// Promo dates are calendar days in the zone the service runs in, which is
+// `PROMO_TIMEZONE` as a UTC offset such as `+03:00`. The spec's UTC+3 is the
+// default.
+#[derive(Clone)]
+pub struct Calendar {
+    clock: Clock,
+    offset: FixedOffset,
+}
+
+impl Calendar {
+    pub fn from_env(clock: Clock) -> Result<Self, String> {
+        let offset = match env::var("PROMO_TIMEZONE") {
+            Ok(value) => {
+                FixedOffset::from_str(&value).map_err(|err| format!("PROMO_TIMEZONE: {}", err))?
+            }
+            Err(_) => FixedOffset::east_opt(3 * 3600).unwrap(),
+        };
+
+        Ok(Calendar { clock, offset })
+    }
+
+    pub fn now(&self) -> DateTime<Utc> {
+        self.clock.now()
+    }
+
+    pub fn today(&self) -> NaiveDate {
+        self.now().with_timezone(&self.offset).date_naive()
+    }
+
+    // The instant a promo valid through `date` stops being active.
+    pub fn end_of(&self, date: NaiveDate) -> DateTime<Utc> {
+        (date + Days::new(1))
+            .and_hms_opt(0, 0, 0)
+            .unwrap()
+            .and_local_timezone(self.offset)
+            .unwrap()
+            .to_utc()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Calendar;
+    use crate::{
+        clock::Clock,
+        repository::{ActivationRepository, PromoRepository},
+        testing,
+    };
+    use chrono::{DateTime, Duration, FixedOffset, NaiveDate, Utc};
+
+    // Wall-clock time in the default promo zone.
+    fn at(local: &str) -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339(&format!("{}+03:00", local))
+            .unwrap()
+            .to_utc()
+    }
+
+    fn date(date: &str) -> NaiveDate {
+        date.parse().unwrap()
+    }
+
+    fn calendar(clock: &Clock) -> Calendar {
+        Calendar {
+            clock: clock.clone(),
+            offset: FixedOffset::east_opt(3 * 3600).unwrap(),
+        }
+    }
+
+    #[test]
+    fn days_turn_over_at_local_midnight() {
+        let clock = Clock::manual(at("2025-03-10T23:59:59"));
+        let calendar = calendar(&clock);
+        assert_eq!(calendar.today(), date("2025-03-10"));
+
+        clock.advance(Duration::seconds(1));
+        assert_eq!(calendar.today(), date("2025-03-11"));
+        assert_eq!(calendar.end_of(date("2025-03-10")), calendar.now());
+    }
+
+    #[cfg_attr(feature = "postgres", ignore = "needs TEST_DATABASE_URL")]
+    #[tokio::test]
+    async fn promos_are_active_through_the_whole_of_their_dates() {
+        let repository = testing::repository().await;
+        let (company, _) = testing::company(&repository).await;
+        let user = testing::user(&repository, 30, "ru").await;
+        let mut promo = testing::promo(&repository, &company, 1, None, Utc::now()).await;
+        promo.active_from = Some(date("2025-03-10"));
+        promo.active_until = Some(date("2025-03-12"));
+        repository.update_promo(&promo).await.unwrap();
+        let clock = Clock::manual(at("2025-03-09T23:59:59"));
+        let calendar = calendar(&clock);
+        let move_to = |local: &str| clock.advance(at(local) - clock.now());
+        let active = || async {
+            repository
+                .retrieve_promo(&promo.promo_id, calendar.today())
+                .await
+                .unwrap()
+                .unwrap()
+                .active
+        };
+
+        // The day before `active_from`, up to its last second.
+        assert!(!active().await);
+        clock.advance(Duration::seconds(1));
+        assert!(active().await);
+
+        move_to("2025-03-12T23:59:59");
+        assert!(active().await);
+        clock.advance(Duration::seconds(1));
+        assert!(!active().await);
+
+        // Within the dates, but with every activation used up.
+        move_to("2025-03-11T12:00:00");
+        assert!(active().await);
+        repository
+            .activate_promo(&promo.promo_id, &user.id, "ru", clock.now())
+            .await
+            .unwrap()
+            .unwrap();
+        let promo = repository
+            .retrieve_promo(&promo.promo_id, calendar.today())
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(promo.used_count, promo.max_count);
+        assert!(!promo.active);
+    }
+}
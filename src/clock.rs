@@ -0,0 +1,48 @@
+use chrono::{DateTime, Utc};
+use std::env;
+#[cfg(test)]
+use std::sync::{Arc, Mutex};
+
+// The time promo state and scheduled jobs are judged by. Setting
+// `CLOCK_FROZEN_AT` (RFC 3339) stops it at that instant, so that test
+// environments get the same feed, activations and job runs on every run.
+#[derive(Clone)]
+pub enum Clock {
+    System,
+    Frozen(DateTime<Utc>),
+    // Moved on by tests with `advance`. Clones share the time.
+    #[cfg(test)]
+    Manual(Arc<Mutex<DateTime<Utc>>>),
+}
+
+impl Clock {
+    pub fn from_env() -> Result<Self, String> {
+        match env::var("CLOCK_FROZEN_AT") {
+            Ok(value) => DateTime::parse_from_rfc3339(&value)
+                .map(|time| Clock::Frozen(time.to_utc()))
+                .map_err(|err| format!("CLOCK_FROZEN_AT: {}", err)),
+            Err(_) => Ok(Clock::System),
+        }
+    }
+
+    pub fn now(&self) -> DateTime<Utc> {
+        match self {
+            Clock::System => Utc::now(),
+            Clock::Frozen(time) => *time,
+            #[cfg(test)]
+            Clock::Manual(time) => *time.lock().unwrap(),
+        }
+    }
+
+    #[cfg(test)]
+    pub fn manual(time: DateTime<Utc>) -> Self {
+        Clock::Manual(Arc::new(Mutex::new(time)))
+    }
+
+    #[cfg(test)]
+    pub fn advance(&self, by: chrono::Duration) {
+        if let Clock::Manual(time) = self {
+            *time.lock().unwrap() += by;
+        }
+    }
+}
@@ -0,0 +1,231 @@
+use crate::{
+    auth::{hash_token, random_token, Audience},
+    error::{AppError, VERIFICATION_RESEND_THROTTLED, VERIFICATION_TOKEN_NOT_FOUND},
+    mailer::Email,
+    repository::EmailVerificationRepository,
+    AppState,
+};
+use chrono::{DateTime, Duration, Utc};
+use sqlx::FromRow;
+use std::env;
+use uuid::Uuid;
+
+const VERIFICATION_TOKEN_TTL_HOURS: i64 = 24;
+const RESEND_INTERVAL_SECONDS: i64 = 60;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum VerificationPolicy {
+    // Verification state is tracked but never blocks a request.
+    Off,
+    // Routes marked with `require_verified_email` reject unverified accounts.
+    Enforce,
+}
+
+// Read once at startup. `EMAIL_VERIFICATION` is `off` (the default) or
+// `enforce`, `PUBLIC_URL` is the address put into verification links.
+#[derive(Clone)]
+pub struct EmailVerificationConfig {
+    pub policy: VerificationPolicy,
+    pub public_url: String,
+}
+
+impl EmailVerificationConfig {
+    pub fn from_env() -> Self {
+        let policy = match env::var("EMAIL_VERIFICATION").as_deref() {
+            Ok("enforce") => VerificationPolicy::Enforce,
+            _ => VerificationPolicy::Off,
+        };
+        let public_url = env::var("PUBLIC_URL").unwrap_or_else(|_| {
+            format!(
+                "http://{}",
+                env::var("SERVER_ADDRESS").unwrap_or_else(|_| "localhost:8080".to_string())
+            )
+        });
+
+        EmailVerificationConfig { policy, public_url }
+    }
+}
+
+#[derive(FromRow, Clone, Debug)]
+pub struct VerificationToken {
+    pub id: String,
+    pub audience: String,
+    pub subject_id: String,
+    pub token_hash: String,
+    pub created_at: DateTime<Utc>,
+    pub expires_at: DateTime<Utc>,
+}
+
+// Mail failures are only logged so that sign-up still succeeds, the account
+// owner can ask for another link.
+pub async fn send_verification(
+    app_state: &AppState,
+    audience: Audience,
+    subject_id: &str,
+    email: &str,
+) -> Result<(), AppError> {
+    let now = Utc::now();
+    // Nothing has been sent to a new account, so the claim always succeeds.
+    app_state
+        .repository
+        .claim_verification_send(audience.as_str(), subject_id, now, now)
+        .await?;
+
+    mail_verification(app_state, audience, subject_id, email, now).await
+}
+
+pub async fn resend_verification(
+    app_state: &AppState,
+    audience: Audience,
+    subject_id: &str,
+    email: &str,
+) -> Result<(), AppError> {
+    let now = Utc::now();
+    let interval = Duration::seconds(RESEND_INTERVAL_SECONDS);
+    if let Some(sent_at) = app_state
+        .repository
+        .claim_verification_send(audience.as_str(), subject_id, now, now - interval)
+        .await?
+    {
+        let wait = (sent_at + interval - now).num_seconds().max(1);
+        return Err(AppError::TooManyRequests(
+            VERIFICATION_RESEND_THROTTLED,
+            wait as u64,
+        ));
+    }
+
+    mail_verification(app_state, audience, subject_id, email, now).await
+}
+
+async fn mail_verification(
+    app_state: &AppState,
+    audience: Audience,
+    subject_id: &str,
+    email: &str,
+    now: DateTime<Utc>,
+) -> Result<(), AppError> {
+    let token = random_token();
+    let verification_token = VerificationToken {
+        id: Uuid::new_v4().to_string(),
+        audience: audience.as_str().to_string(),
+        subject_id: subject_id.to_string(),
+        token_hash: hash_token(&token),
+        created_at: now,
+        expires_at: now + Duration::hours(VERIFICATION_TOKEN_TTL_HOURS),
+    };
+    app_state
+        .repository
+        .create_verification_token(&verification_token)
+        .await?;
+
+    let email = Email {
+        to: email.to_string(),
+        subject: "Подтверждение email".to_string(),
+        body: format!(
+            "Чтобы подтвердить email, перейдите по ссылке:\n{}/api/{}/auth/verify-email?token={}\nСсылка действует {} часа.",
+            app_state.email_verification.public_url,
+            audience.as_str(),
+            token,
+            VERIFICATION_TOKEN_TTL_HOURS
+        ),
+    };
+    app_state.mailer.send_in_background(email);
+    Ok(())
+}
+
+// Returns the id of the subject whose email the token confirms.
+pub async fn use_verification_token(
+    app_state: &AppState,
+    audience: Audience,
+    token: &str,
+) -> Result<String, AppError> {
+    app_state
+        .repository
+        .use_verification_token(audience.as_str(), &hash_token(token), Utc::now())
+        .await?
+        .ok_or(AppError::NotFound(VERIFICATION_TOKEN_NOT_FOUND))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::VerificationPolicy;
+    use crate::{error::EMAIL_NOT_VERIFIED, testing};
+    use axum::http::StatusCode;
+    use chrono::Utc;
+    use serde_json::Value;
+    use tokio::task::JoinSet;
+
+    #[cfg_attr(feature = "postgres", ignore = "needs TEST_DATABASE_URL")]
+    #[tokio::test]
+    async fn enforced_verification_blocks_activation_but_not_the_feed() {
+        let repository = testing::repository().await;
+        let user = testing::user(&repository, 30, "ru").await;
+        let (company, _) = testing::company(&repository).await;
+        let promo = testing::promo(&repository, &company, 10, None, Utc::now()).await;
+        let mut state = testing::app_state(repository, "127.0.0.1:9").await;
+        state.email_verification.policy = VerificationPolicy::Enforce;
+        let access_token = testing::user_token(&state, &user).await;
+        let base_url = testing::serve(state).await;
+        let client = reqwest::Client::new();
+
+        let response = client
+            .post(format!(
+                "{}/api/user/promo/{}/activate",
+                base_url, promo.promo_id
+            ))
+            .bearer_auth(&access_token)
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+        let body: Value = response.json().await.unwrap();
+        assert_eq!(body["message"], EMAIL_NOT_VERIFIED);
+
+        let response = client
+            .get(format!("{}/api/user/feed", base_url))
+            .bearer_auth(&access_token)
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let feed: Vec<Value> = response.json().await.unwrap();
+        assert_eq!(feed[0]["promo_id"], promo.promo_id.as_str());
+    }
+
+    #[cfg_attr(feature = "postgres", ignore = "needs TEST_DATABASE_URL")]
+    #[tokio::test]
+    async fn concurrent_resends_send_one_link() {
+        let repository = testing::repository().await;
+        let user = testing::user(&repository, 30, "ru").await;
+        let state = testing::app_state(repository, "127.0.0.1:9").await;
+        let mailbox = testing::mailbox(&state);
+        let access_token = testing::user_token(&state, &user).await;
+        let base_url = testing::serve(state).await;
+
+        let mut resends = JoinSet::new();
+        for _ in 0..5 {
+            resends.spawn(
+                reqwest::Client::new()
+                    .post(format!("{}/api/user/auth/verify-email/resend", base_url))
+                    .bearer_auth(&access_token)
+                    .send(),
+            );
+        }
+        let mut statuses = vec![];
+        while let Some(response) = resends.join_next().await {
+            statuses.push(response.unwrap().unwrap().status());
+        }
+        statuses.sort();
+        assert_eq!(
+            statuses,
+            [
+                StatusCode::OK,
+                StatusCode::TOO_MANY_REQUESTS,
+                StatusCode::TOO_MANY_REQUESTS,
+                StatusCode::TOO_MANY_REQUESTS,
+                StatusCode::TOO_MANY_REQUESTS,
+            ]
+        );
+        assert_eq!(mailbox.wait_for(1).await.len(), 1);
+    }
+}
@@ -0,0 +1,178 @@
+use crate::{auth::password::PasswordError, sign_in_attempts::StoreError, validation::FieldError};
+use axum::{
+    http::{header, HeaderValue, StatusCode},
+    response::{IntoResponse, Response},
+    Json,
+};
+use jsonwebtoken::errors::ErrorKind;
+use serde_json::json;
+use std::fmt;
+
+pub const PROMO_NOT_FOUND: &str = "Промокод не найден.";
+pub const NO_ACCESS_TO_PROMO: &str = "Промокод не принадлежит этой компании.";
+pub const PROMO_OR_COMMENT_NOT_FOUND: &str = "Такого промокода или комментария не существует.";
+pub const NO_ACCESS_TO_COMMENT: &str = "Комментарий не принадлежит пользователю.";
+pub const EMAIL_ALREADY_REGISTERED: &str = "Такой email уже зарегистрирован.";
+pub const ACTIVATION_FORBIDDEN: &str = "Вы не можете использовать этот промокод.";
+pub const INSUFFICIENT_ROLE: &str = "Недостаточно прав для этого действия.";
+pub const API_KEY_NOT_FOUND: &str = "API-ключ не найден.";
+pub const RESET_TOKEN_NOT_FOUND: &str = "Код сброса пароля недействителен или истёк.";
+pub const VERIFICATION_TOKEN_NOT_FOUND: &str =
+    "Ссылка для подтверждения email недействительна или устарела.";
+pub const EMAIL_NOT_VERIFIED: &str = "Подтвердите email, чтобы выполнить это действие.";
+pub const EMAIL_ALREADY_VERIFIED: &str = "Email уже подтверждён.";
+pub const VERIFICATION_RESEND_THROTTLED: &str = "Письмо уже отправлено, повторите попытку позже.";
+pub const INVITATION_NOT_FOUND: &str = "Приглашение не найдено или уже использовано.";
+pub const TWO_FACTOR_ALREADY_ENABLED: &str = "Двухфакторная аутентификация уже включена.";
+pub const TWO_FACTOR_NOT_ENABLED: &str = "Двухфакторная аутентификация не включена.";
+pub const SIGN_IN_LOCKED: &str = "Слишком много неудачных попыток входа, повторите попытку позже.";
+pub const SESSION_NOT_FOUND: &str = "Сессия не найдена.";
+pub const TWO_FACTOR_ENROLLMENT_NOT_FOUND: &str =
+    "Подключение двухфакторной аутентификации не начато.";
+
+#[derive(Debug)]
+pub enum AppError {
+    // Carries every broken rule of the request, never empty.
+    Validation(Vec<FieldError>),
+    MalformedRequest(String),
+    InvalidCredentials,
+    InvalidTwoFactorCode,
+    Unauthorized,
+    Forbidden(&'static str),
+    NotFound(&'static str),
+    Conflict(&'static str),
+    // Carries the number of seconds the client should wait before retrying.
+    TooManyRequests(&'static str, u64),
+    Database(sqlx::Error),
+    Password(PasswordError),
+    Token(jsonwebtoken::errors::Error),
+}
+
+impl AppError {
+    fn status(&self) -> StatusCode {
+        match self {
+            AppError::Validation(_) | AppError::MalformedRequest(_) => StatusCode::BAD_REQUEST,
+            AppError::InvalidCredentials
+            | AppError::InvalidTwoFactorCode
+            | AppError::Unauthorized => StatusCode::UNAUTHORIZED,
+            AppError::Forbidden(_) => StatusCode::FORBIDDEN,
+            AppError::NotFound(_) => StatusCode::NOT_FOUND,
+            AppError::Conflict(_) => StatusCode::CONFLICT,
+            AppError::TooManyRequests(..) => StatusCode::TOO_MANY_REQUESTS,
+            AppError::Token(err) if is_signing_error(err) => StatusCode::INTERNAL_SERVER_ERROR,
+            AppError::Token(_) => StatusCode::UNAUTHORIZED,
+            AppError::Database(_) | AppError::Password(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+
+    fn message(&self) -> String {
+        match self {
+            AppError::Validation(errors) => {
+                let mut fields: Vec<&str> = vec![];
+                for err in errors {
+                    if !fields.contains(&err.field.as_str()) {
+                        fields.push(&err.field);
+                    }
+                }
+                match fields.as_slice() {
+                    [field] => format!("Ошибка в данных запроса: некорректное поле {}.", field),
+                    _ => format!(
+                        "Ошибка в данных запроса: некорректные поля {}.",
+                        fields.join(", ")
+                    ),
+                }
+            }
+            AppError::MalformedRequest(details) => {
+                format!("Ошибка в данных запроса: {}", details)
+            }
+            AppError::InvalidCredentials => "Неверный email или пароль.".to_string(),
+            AppError::InvalidTwoFactorCode => "Неверный код подтверждения.".to_string(),
+            AppError::Unauthorized => "Пользователь не авторизован.".to_string(),
+            AppError::Forbidden(message)
+            | AppError::NotFound(message)
+            | AppError::Conflict(message)
+            | AppError::TooManyRequests(message, _) => message.to_string(),
+            AppError::Token(err) if !is_signing_error(err) => {
+                "Пользователь не авторизован.".to_string()
+            }
+            _ => "Внутренняя ошибка сервера.".to_string(),
+        }
+    }
+}
+
+// Key and algorithm problems are our misconfiguration, every other token error
+// means the client sent a bad token.
+fn is_signing_error(err: &jsonwebtoken::errors::Error) -> bool {
+    matches!(
+        err.kind(),
+        ErrorKind::InvalidEcdsaKey
+            | ErrorKind::InvalidRsaKey(_)
+            | ErrorKind::RsaFailedSigning
+            | ErrorKind::InvalidAlgorithmName
+            | ErrorKind::InvalidKeyFormat
+            | ErrorKind::MissingAlgorithm
+            | ErrorKind::Crypto(_)
+    )
+}
+
+impl fmt::Display for AppError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AppError::Database(err) => write!(f, "database error: {}", err),
+            AppError::Password(err) => write!(f, "password hashing error: {}", err),
+            AppError::Token(err) => write!(f, "token error: {}", err),
+            _ => write!(f, "{}", self.message()),
+        }
+    }
+}
+
+impl IntoResponse for AppError {
+    fn into_response(self) -> Response {
+        let status = self.status();
+        if status == StatusCode::INTERNAL_SERVER_ERROR {
+            tracing::error!("{}", self);
+        }
+
+        let mut body = json!({
+            "status": "error",
+            "message": self.message()
+        });
+        if let AppError::Validation(errors) = &self {
+            body["errors"] = json!(errors);
+        }
+        let mut response = (status, Json(body)).into_response();
+
+        if let AppError::TooManyRequests(_, retry_after) = self {
+            response
+                .headers_mut()
+                .insert(header::RETRY_AFTER, HeaderValue::from(retry_after));
+        }
+        response
+    }
+}
+
+impl From<sqlx::Error> for AppError {
+    fn from(err: sqlx::Error) -> Self {
+        AppError::Database(err)
+    }
+}
+
+impl From<StoreError> for AppError {
+    fn from(err: StoreError) -> Self {
+        match err {
+            StoreError::Database(err) => AppError::Database(err),
+        }
+    }
+}
+
+impl From<PasswordError> for AppError {
+    fn from(err: PasswordError) -> Self {
+        AppError::Password(err)
+    }
+}
+
+impl From<jsonwebtoken::errors::Error> for AppError {
+    fn from(err: jsonwebtoken::errors::Error) -> Self {
+        AppError::Token(err)
+    }
+}
@@ -0,0 +1,75 @@
+use crate::error::AppError;
+use axum::{
+    extract::{ConnectInfo, FromRequest, FromRequestParts, Query, Request},
+    http::{header, request::Parts},
+    Json,
+};
+use serde::de::DeserializeOwned;
+use std::net::{IpAddr, SocketAddr};
+
+const MAX_USER_AGENT_LENGTH: usize = 512;
+
+// `Json` and `Query` wrappers that reject malformed input with an `AppError`,
+// so clients get the spec's error body instead of axum's plain-text rejection.
+pub struct AppJson<T>(pub T);
+
+impl<S, T> FromRequest<S> for AppJson<T>
+where
+    S: Send + Sync,
+    T: DeserializeOwned,
+{
+    type Rejection = AppError;
+
+    async fn from_request(req: Request, state: &S) -> Result<Self, Self::Rejection> {
+        let Json(value) = Json::<T>::from_request(req, state)
+            .await
+            .map_err(|rejection| AppError::MalformedRequest(rejection.body_text()))?;
+        Ok(AppJson(value))
+    }
+}
+
+pub struct AppQuery<T>(pub T);
+
+impl<S, T> FromRequestParts<S> for AppQuery<T>
+where
+    S: Send + Sync,
+    T: DeserializeOwned,
+{
+    type Rejection = AppError;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        let Query(value) = Query::<T>::from_request_parts(parts, state)
+            .await
+            .map_err(|rejection| AppError::MalformedRequest(rejection.body_text()))?;
+        Ok(AppQuery(value))
+    }
+}
+
+// Where a request comes from, as recorded on user sessions.
+pub struct ClientInfo {
+    pub ip_address: IpAddr,
+    pub user_agent: Option<String>,
+}
+
+impl<S> FromRequestParts<S> for ClientInfo
+where
+    S: Send + Sync,
+{
+    type Rejection = AppError;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        let ConnectInfo(address) = ConnectInfo::<SocketAddr>::from_request_parts(parts, state)
+            .await
+            .map_err(|rejection| AppError::MalformedRequest(rejection.body_text()))?;
+        let user_agent = parts
+            .headers
+            .get(header::USER_AGENT)
+            .and_then(|value| value.to_str().ok())
+            .map(|value| value.chars().take(MAX_USER_AGENT_LENGTH).collect());
+
+        Ok(ClientInfo {
+            ip_address: address.ip(),
+            user_agent,
+        })
+    }
+}
@@ -0,0 +1,34 @@
+use super::{Email, MailError, Mailer};
+use chrono::Utc;
+use std::{fs, path::PathBuf};
+use uuid::Uuid;
+
+#[derive(Clone)]
+pub struct FileMailer {
+    dir: PathBuf,
+}
+
+impl FileMailer {
+    pub fn new(dir: PathBuf) -> Result<Self, MailError> {
+        fs::create_dir_all(&dir).map_err(MailError::Io)?;
+        Ok(FileMailer { dir })
+    }
+}
+
+impl Mailer for FileMailer {
+    async fn send(&self, email: &Email) -> Result<(), MailError> {
+        let path = self.dir.join(format!(
+            "{}-{}.eml",
+            Utc::now().format("%Y%m%dT%H%M%S%.6f"),
+            Uuid::new_v4()
+        ));
+        let contents = format!(
+            "To: {}\nSubject: {}\n\n{}\n",
+            email.to, email.subject, email.body
+        );
+
+        tokio::fs::write(path, contents)
+            .await
+            .map_err(MailError::Io)
+    }
+}
@@ -0,0 +1,35 @@
+use super::{Email, MailError, Mailer};
+use std::{
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+// Keeps every message for tests to read back.
+#[derive(Clone, Default)]
+pub struct MemoryMailer {
+    sent: Arc<Mutex<Vec<Email>>>,
+}
+
+impl MemoryMailer {
+    pub fn sent(&self) -> Vec<Email> {
+        self.sent.lock().unwrap().clone()
+    }
+
+    // Mail goes out in the background, so give it a moment to arrive.
+    pub async fn wait_for(&self, count: usize) -> Vec<Email> {
+        for _ in 0..100 {
+            if self.sent.lock().unwrap().len() >= count {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+        self.sent()
+    }
+}
+
+impl Mailer for MemoryMailer {
+    async fn send(&self, email: &Email) -> Result<(), MailError> {
+        self.sent.lock().unwrap().push(email.clone());
+        Ok(())
+    }
+}
@@ -0,0 +1,127 @@
+use file::FileMailer;
+#[cfg(test)]
+use memory::MemoryMailer;
+use smtp::SmtpMailer;
+use std::{env, fmt};
+
+pub mod file;
+#[cfg(test)]
+pub mod memory;
+pub mod smtp;
+
+#[derive(Clone)]
+pub struct Email {
+    pub to: String,
+    pub subject: String,
+    pub body: String,
+}
+
+#[derive(Debug)]
+pub enum MailError {
+    Address(lettre::address::AddressError),
+    Message(lettre::error::Error),
+    Smtp(lettre::transport::smtp::Error),
+    Io(std::io::Error),
+    Config(String),
+}
+
+impl fmt::Display for MailError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MailError::Address(err) => write!(f, "invalid address: {}", err),
+            MailError::Message(err) => write!(f, "invalid message: {}", err),
+            MailError::Smtp(err) => write!(f, "smtp error: {}", err),
+            MailError::Io(err) => write!(f, "io error: {}", err),
+            MailError::Config(err) => write!(f, "mail configuration: {}", err),
+        }
+    }
+}
+
+pub trait Mailer {
+    async fn send(&self, email: &Email) -> Result<(), MailError>;
+}
+
+// The transport is picked at startup by `MAIL_TRANSPORT`:
+// - `smtp` sends through `SMTP_URL` and is the default when that is set;
+// - `file` writes every message to `MAIL_DIR`, tokens and all, for local runs
+//   that read them from there. It has to be asked for explicitly;
+// - `none` drops messages and only logs who they were for. It is the default
+//   without `SMTP_URL`.
+#[derive(Clone)]
+pub enum AppMailer {
+    Smtp(Box<SmtpMailer>),
+    File(FileMailer),
+    Disabled,
+    #[cfg(test)]
+    Memory(MemoryMailer),
+}
+
+impl AppMailer {
+    pub fn from_env() -> Result<Self, MailError> {
+        let smtp_url = env::var("SMTP_URL").ok();
+        let transport = env::var("MAIL_TRANSPORT").unwrap_or_else(|_| {
+            match smtp_url {
+                Some(_) => "smtp",
+                None => "none",
+            }
+            .to_string()
+        });
+
+        match transport.as_str() {
+            "smtp" => {
+                let url = smtp_url.ok_or_else(|| {
+                    MailError::Config("MAIL_TRANSPORT=smtp needs SMTP_URL".to_string())
+                })?;
+                Ok(AppMailer::Smtp(Box::new(SmtpMailer::new(
+                    &url,
+                    &env::var("MAIL_FROM").unwrap_or_else(|_| "noreply@localhost".to_string()),
+                )?)))
+            }
+            "file" => Ok(AppMailer::File(FileMailer::new(
+                env::var("MAIL_DIR")
+                    .map(Into::into)
+                    .unwrap_or_else(|_| env::temp_dir().join("prod_project_mail")),
+            )?)),
+            "none" => Ok(AppMailer::Disabled),
+            other => Err(MailError::Config(format!(
+                "unknown MAIL_TRANSPORT {}",
+                other
+            ))),
+        }
+    }
+
+    // Sends without holding up the request, so that answering does not take
+    // longer when there is mail to send. Failures are only logged.
+    pub fn send_in_background(&self, email: Email) {
+        let mailer = self.clone();
+        tokio::spawn(async move {
+            if let Err(err) = mailer.send(&email).await {
+                tracing::warn!(
+                    "Unable to send \"{}\" to {}: {}",
+                    email.subject,
+                    email.to,
+                    err
+                );
+            }
+        });
+    }
+}
+
+impl Mailer for AppMailer {
+    async fn send(&self, email: &Email) -> Result<(), MailError> {
+        match self {
+            AppMailer::Smtp(mailer) => mailer.send(email).await,
+            AppMailer::File(mailer) => mailer.send(email).await,
+            AppMailer::Disabled => {
+                tracing::info!(
+                    "Mail is disabled, dropped \"{}\" to {}",
+                    email.subject,
+                    email.to
+                );
+                Ok(())
+            }
+            #[cfg(test)]
+            AppMailer::Memory(mailer) => mailer.send(email).await,
+        }
+    }
+}
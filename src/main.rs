@@ -1,49 +1,120 @@
-use axum;
-use sqlx::{postgres::PgPoolOptions, PgPool};
-use std::{
-    collections::HashSet,
-    env,
-    sync::{Arc, Mutex},
-};
+use antifraud::AntifraudClient;
+use auth::{keys::JwtKeys, password::PasswordHasher};
+use calendar::Calendar;
+use clock::Clock;
+use email_verification::EmailVerificationConfig;
+use mailer::AppMailer;
+use repository::Repository;
+use revocation::RevokedTokens;
+use scheduler::Scheduler;
+use sessions::SessionConfig;
+use sign_in_attempts::SignInAttempts;
+use std::{env, net::SocketAddr};
 use tokio::net::TcpListener;
+use tracing_subscriber::EnvFilter;
+use validation::ValidationRules;
 
+mod antifraud;
+mod auth;
 mod business;
+mod calendar;
+mod clock;
+mod email_verification;
+mod error;
+mod extract;
+mod mailer;
+mod pagination;
+mod password_reset;
+mod refresh;
+mod repository;
+mod revocation;
 mod routes;
+mod scheduler;
+mod sessions;
+mod sign_in_attempts;
 mod user;
+mod validation;
+
+#[cfg(test)]
+mod testing;
 
 #[derive(Clone)]
 pub struct AppState {
-    pool: PgPool,
-    revoked_tokens: Arc<Mutex<HashSet<String>>>,
+    repository: Repository,
+    revoked_tokens: RevokedTokens,
+    antifraud: AntifraudClient,
+    jwt_keys: JwtKeys,
+    password_hasher: PasswordHasher,
+    validation_rules: ValidationRules,
+    mailer: AppMailer,
+    email_verification: EmailVerificationConfig,
+    sign_in_attempts: SignInAttempts,
+    sessions: SessionConfig,
+    calendar: Calendar,
 }
 
 #[tokio::main]
 async fn main() {
+    // `RUST_LOG` picks what gets logged, `info` and up by default.
+    tracing_subscriber::fmt()
+        .with_env_filter(
+            EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("info")),
+        )
+        .init();
+
     let listener = TcpListener::bind(&env::var("SERVER_ADDRESS").unwrap())
         .await
         .expect("Unable to connect to the server");
 
-    // let pool = SqlitePool::connect(&env::var("DATABASE_URL").unwrap())
-    //     .await
-    //     .unwrap();
-
-    let db = PgPoolOptions::new()
-        .max_connections(5)
-        .connect(&env::var("DATABASE_URL").unwrap())
+    let repository = Repository::connect(&env::var("DATABASE_URL").unwrap())
         .await
         .unwrap();
-    sqlx::migrate!().run(&db).await.unwrap();
+
+    let antifraud =
+        AntifraudClient::new(&env::var("ANTIFRAUD_ADDRESS").unwrap(), repository.clone());
+
+    let revoked_tokens = RevokedTokens::load(repository.clone()).await.unwrap();
+
+    let jwt_keys = JwtKeys::from_env().expect("Unable to load JWT keys");
+
+    let password_hasher = PasswordHasher::from_env().expect("Invalid Argon2 parameters");
+
+    let validation_rules =
+        ValidationRules::from_env().expect("Invalid password policy configuration");
+
+    let mailer = AppMailer::from_env().expect("Unable to configure the mailer");
+
+    let sign_in_attempts = SignInAttempts::from_env(repository.clone());
+
+    let clock = Clock::from_env().expect("Invalid frozen clock time");
+    let calendar = Calendar::from_env(clock).expect("Invalid promo timezone");
+
+    Scheduler::from_env(repository.clone(), calendar.clone())
+        .expect("Invalid job scheduler configuration")
+        .spawn();
 
     let state = AppState {
-        pool: db,
-        revoked_tokens: Arc::new(Mutex::new(HashSet::new())),
+        repository,
+        revoked_tokens,
+        antifraud,
+        jwt_keys,
+        password_hasher,
+        validation_rules,
+        mailer,
+        email_verification: EmailVerificationConfig::from_env(),
+        sign_in_attempts,
+        sessions: SessionConfig::from_env(),
+        calendar,
     };
 
     let app = routes::app(state).await;
 
-    println!("Listening on {}", listener.local_addr().unwrap());
+    tracing::info!("Listening on {}", listener.local_addr().unwrap());
 
-    axum::serve(listener, app)
-        .await
-        .expect("Error serving application");
+    axum::serve(
+        listener,
+        app.into_make_service_with_connect_info::<SocketAddr>(),
+    )
+    .await
+    .expect("Error serving application");
 }
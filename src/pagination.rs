@@ -0,0 +1,13 @@
+use serde::Deserialize;
+
+#[derive(Deserialize)]
+pub struct Pagination {
+    #[serde(default = "default_limit")]
+    pub limit: u32,
+    #[serde(default)]
+    pub offset: u32,
+}
+
+pub fn default_limit() -> u32 {
+    10
+}
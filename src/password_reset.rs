@@ -0,0 +1,199 @@
+use crate::{
+    auth::{hash_token, random_token, Audience},
+    error::{AppError, RESET_TOKEN_NOT_FOUND},
+    mailer::{Email, Mailer},
+    refresh,
+    repository::{MemberRepository, PasswordResetRepository, UserRepository},
+    sessions, AppState,
+};
+use chrono::{DateTime, Duration, Utc};
+use sqlx::FromRow;
+use uuid::Uuid;
+
+const RESET_TOKEN_TTL_MINUTES: i64 = 60;
+
+// Reset tokens are single-use: consuming one also discards every other
+// pending token of the subject. Only their hash is stored.
+#[derive(FromRow, Clone, Debug)]
+pub struct PasswordResetToken {
+    pub id: String,
+    pub audience: String,
+    pub subject_id: String,
+    pub token_hash: String,
+    pub created_at: DateTime<Utc>,
+    pub expires_at: DateTime<Utc>,
+}
+
+// Looks the address up and mails a reset token to the account it belongs to,
+// if any, in the background. The caller answers right away, so the response
+// neither says nor takes longer when the address is registered. Failures are
+// only logged.
+pub fn request_reset(app_state: AppState, audience: Audience, email: String) {
+    tokio::spawn(async move {
+        match reset_email(&app_state, audience, &email).await {
+            Ok(Some(email)) => {
+                if let Err(err) = app_state.mailer.send(&email).await {
+                    tracing::warn!("Unable to send a password reset email: {}", err);
+                }
+            }
+            Ok(None) => {}
+            Err(err) => tracing::warn!("Unable to issue a password reset token: {}", err),
+        }
+    });
+}
+
+// Issues a reset token to the account the address belongs to and returns the
+// email that carries it.
+async fn reset_email(
+    app_state: &AppState,
+    audience: Audience,
+    email: &str,
+) -> Result<Option<Email>, AppError> {
+    let subject = match audience {
+        Audience::User => app_state
+            .repository
+            .retrieve_user_by_email(email)
+            .await?
+            .map(|user| (user.id, user.email)),
+        Audience::Business => app_state
+            .repository
+            .retrieve_member_by_email(email)
+            .await?
+            .map(|member| (member.id, member.email)),
+    };
+    let Some((subject_id, email)) = subject else {
+        return Ok(None);
+    };
+
+    let token = random_token();
+    let now = Utc::now();
+    let reset_token = PasswordResetToken {
+        id: Uuid::new_v4().to_string(),
+        audience: audience.as_str().to_string(),
+        subject_id,
+        token_hash: hash_token(&token),
+        created_at: now,
+        expires_at: now + Duration::minutes(RESET_TOKEN_TTL_MINUTES),
+    };
+    app_state
+        .repository
+        .create_password_reset_token(&reset_token)
+        .await?;
+
+    Ok(Some(Email {
+        to: email,
+        subject: "Восстановление пароля".to_string(),
+        body: format!(
+            "Код для сброса пароля: {}\nКод действует {} минут.",
+            token, RESET_TOKEN_TTL_MINUTES
+        ),
+    }))
+}
+
+// Returns the id of the subject the token was issued to.
+pub async fn use_reset_token(
+    app_state: &AppState,
+    audience: Audience,
+    token: &str,
+) -> Result<String, AppError> {
+    match app_state
+        .repository
+        .use_password_reset_token(audience.as_str(), &hash_token(token), Utc::now())
+        .await?
+    {
+        Some(reset_token) => Ok(reset_token.subject_id),
+        None => Err(AppError::NotFound(RESET_TOKEN_NOT_FOUND)),
+    }
+}
+
+// Signs the subject out everywhere once the password has changed.
+pub async fn end_sessions(
+    app_state: &AppState,
+    audience: Audience,
+    subject_id: &str,
+) -> Result<(), AppError> {
+    app_state.revoked_tokens.end_sessions(subject_id).await?;
+    match audience {
+        Audience::Business => refresh::revoke(&app_state.repository, audience, subject_id).await,
+        Audience::User => sessions::end_all(app_state, subject_id).await,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::testing;
+    use axum::http::StatusCode;
+    use serde_json::json;
+
+    const NEW_PASSWORD: &str = "N3w-Passw0rd!";
+
+    #[cfg_attr(feature = "postgres", ignore = "needs TEST_DATABASE_URL")]
+    #[tokio::test]
+    async fn reset_tokens_are_mailed_to_the_account() {
+        let repository = testing::repository().await;
+        let user = testing::user(&repository, 25, "ru").await;
+        let state = testing::app_state(repository, "127.0.0.1:9").await;
+        let mailbox = testing::mailbox(&state);
+        let base_url = testing::serve(state).await;
+        let client = reqwest::Client::new();
+
+        let response = client
+            .post(format!("{}/api/user/auth/password-reset", base_url))
+            .json(&json!({ "email": user.email }))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let sent = mailbox.wait_for(1).await;
+        assert_eq!(sent.len(), 1);
+        assert_eq!(sent[0].to, user.email);
+        let token = sent[0]
+            .body
+            .lines()
+            .next()
+            .and_then(|line| line.rsplit(' ').next())
+            .unwrap()
+            .to_string();
+
+        let confirm = || {
+            client
+                .post(format!("{}/api/user/auth/password-reset/confirm", base_url))
+                .json(&json!({ "token": token, "password": NEW_PASSWORD }))
+                .send()
+        };
+        assert_eq!(confirm().await.unwrap().status(), StatusCode::OK);
+        assert_eq!(confirm().await.unwrap().status(), StatusCode::NOT_FOUND);
+
+        let response = client
+            .post(format!("{}/api/user/auth/sign-in", base_url))
+            .json(&json!({ "email": user.email, "password": NEW_PASSWORD }))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+
+    #[cfg_attr(feature = "postgres", ignore = "needs TEST_DATABASE_URL")]
+    #[tokio::test]
+    async fn unknown_addresses_get_the_same_answer_and_no_mail() {
+        let repository = testing::repository().await;
+        let state = testing::app_state(repository, "127.0.0.1:9").await;
+        let mailbox = testing::mailbox(&state);
+        let base_url = testing::serve(state).await;
+
+        let response = reqwest::Client::new()
+            .post(format!("{}/api/user/auth/password-reset", base_url))
+            .json(&json!({ "email": "nobody@example.com" }))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(
+            response.json::<serde_json::Value>().await.unwrap(),
+            json!({ "status": "ok" })
+        );
+
+        assert!(mailbox.wait_for(1).await.is_empty());
+    }
+}
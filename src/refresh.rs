@@ -0,0 +1,277 @@
+use crate::{
+    auth::{hash_token, random_token, Audience},
+    error::AppError,
+    repository::{RefreshTokenRepository, Repository, UserSessionRepository},
+    AppState,
+};
+use chrono::{DateTime, Duration, Utc};
+use sqlx::FromRow;
+use uuid::Uuid;
+
+const REFRESH_TOKEN_TTL_DAYS: i64 = 30;
+
+// Only the SHA-256 of a refresh token is stored. Every rotation marks the
+// presented token as used and issues a successor in the same family, so a
+// used token showing up again means it leaked and the family is revoked.
+#[derive(FromRow, Clone, Debug)]
+pub struct RefreshToken {
+    pub id: String,
+    pub family_id: String,
+    pub audience: String,
+    pub subject_id: String,
+    pub token_hash: String,
+    pub created_at: DateTime<Utc>,
+    pub expires_at: DateTime<Utc>,
+    pub used_at: Option<DateTime<Utc>>,
+    pub revoked_at: Option<DateTime<Utc>>,
+}
+
+// Starts a new family and revokes the subject's previous refresh tokens, the
+// same way signing in again revokes earlier access tokens.
+pub async fn issue(
+    repository: &Repository,
+    audience: Audience,
+    subject_id: &str,
+) -> Result<String, AppError> {
+    repository
+        .revoke_subject_refresh_tokens(audience.as_str(), subject_id, Utc::now())
+        .await?;
+
+    let (token, _) = start_family(
+        repository,
+        audience,
+        subject_id,
+        &Uuid::new_v4().to_string(),
+    )
+    .await?;
+    Ok(token)
+}
+
+// Starts a family with a chosen id, leaving the subject's other families
+// alone. Returns the token and its record.
+pub async fn start_family(
+    repository: &Repository,
+    audience: Audience,
+    subject_id: &str,
+    family_id: &str,
+) -> Result<(String, RefreshToken), AppError> {
+    let (token, refresh_token) = generate(audience, subject_id, family_id, Utc::now());
+    repository.create_refresh_token(&refresh_token).await?;
+
+    Ok((token, refresh_token))
+}
+
+// Exchanges a refresh token for its successor. Returns the successor's record
+// and the new token.
+pub async fn rotate(
+    app_state: &AppState,
+    audience: Audience,
+    token: &str,
+) -> Result<(RefreshToken, String), AppError> {
+    let repository = &app_state.repository;
+    let now = Utc::now();
+    let current = match repository
+        .retrieve_refresh_token(&hash_token(token))
+        .await?
+    {
+        Some(current) if current.audience == audience.as_str() => current,
+        _ => return Err(AppError::Unauthorized),
+    };
+
+    if current.revoked_at.is_some() || current.expires_at <= now {
+        return Err(AppError::Unauthorized);
+    }
+
+    let (token, successor) = generate(audience, &current.subject_id, &current.family_id, now);
+    if current.used_at.is_some()
+        || !repository
+            .rotate_refresh_token(&current.id, &successor, now)
+            .await?
+    {
+        end_family(app_state, audience, &current, now).await?;
+        return Err(AppError::Unauthorized);
+    }
+
+    Ok((successor, token))
+}
+
+// Cuts off a leaked family along with the access token last issued from it:
+// the user session the family belongs to, or the member's current token.
+async fn end_family(
+    app_state: &AppState,
+    audience: Audience,
+    token: &RefreshToken,
+    now: DateTime<Utc>,
+) -> Result<(), AppError> {
+    app_state
+        .repository
+        .revoke_refresh_token_family(&token.family_id, now)
+        .await?;
+
+    match audience {
+        Audience::User => {
+            if let Some(session) = app_state
+                .repository
+                .retrieve_user_session(&token.family_id)
+                .await?
+            {
+                app_state
+                    .repository
+                    .revoke_user_session(&session.user_id, &session.id, now)
+                    .await?;
+                app_state
+                    .revoked_tokens
+                    .revoke(&session.access_token_id, session.expires_at)
+                    .await?;
+            }
+        }
+        Audience::Business => {
+            app_state
+                .revoked_tokens
+                .end_sessions(&token.subject_id)
+                .await?
+        }
+    }
+    Ok(())
+}
+
+pub async fn revoke(
+    repository: &Repository,
+    audience: Audience,
+    subject_id: &str,
+) -> Result<(), AppError> {
+    Ok(repository
+        .revoke_subject_refresh_tokens(audience.as_str(), subject_id, Utc::now())
+        .await?)
+}
+
+fn generate(
+    audience: Audience,
+    subject_id: &str,
+    family_id: &str,
+    now: DateTime<Utc>,
+) -> (String, RefreshToken) {
+    let token = random_token();
+
+    let refresh_token = RefreshToken {
+        id: Uuid::new_v4().to_string(),
+        family_id: family_id.to_string(),
+        audience: audience.as_str().to_string(),
+        subject_id: subject_id.to_string(),
+        token_hash: hash_token(&token),
+        created_at: now,
+        expires_at: now + Duration::days(REFRESH_TOKEN_TTL_DAYS),
+        used_at: None,
+        revoked_at: None,
+    };
+
+    (token, refresh_token)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{business::auth::sign_in::issue_tokens, extract::ClientInfo, sessions, testing};
+    use axum::http::StatusCode;
+    use serde_json::{json, Value};
+    use std::net::Ipv4Addr;
+
+    async fn refresh(base_url: &str, route: &str, refresh_token: &str) -> (StatusCode, Value) {
+        let response = reqwest::Client::new()
+            .post(format!("{}/api/{}/auth/refresh", base_url, route))
+            .json(&json!({ "refresh_token": refresh_token }))
+            .send()
+            .await
+            .unwrap();
+        let status = response.status();
+        (status, response.json().await.unwrap())
+    }
+
+    async fn get(base_url: &str, path: &str, token: &str) -> StatusCode {
+        reqwest::Client::new()
+            .get(format!("{}{}", base_url, path))
+            .bearer_auth(token)
+            .send()
+            .await
+            .unwrap()
+            .status()
+    }
+
+    #[cfg_attr(feature = "postgres", ignore = "needs TEST_DATABASE_URL")]
+    #[tokio::test]
+    async fn replaying_a_rotated_token_revokes_the_family() {
+        let repository = testing::repository().await;
+        let user = testing::user(&repository, 25, "ru").await;
+        let state = testing::app_state(repository, "127.0.0.1:9").await;
+        let client = ClientInfo {
+            ip_address: Ipv4Addr::LOCALHOST.into(),
+            user_agent: None,
+        };
+        let (_, first) = sessions::start(&state, &user.id, &client).await.unwrap();
+        let base_url = testing::serve(state).await;
+
+        let (status, rotated) = refresh(&base_url, "user", &first).await;
+        assert_eq!(status, StatusCode::OK);
+        let access_token = rotated["token"].as_str().unwrap();
+        let successor = rotated["refresh_token"].as_str().unwrap();
+        assert_eq!(
+            get(&base_url, "/api/user/sessions", access_token).await,
+            StatusCode::OK
+        );
+
+        // The rotated token works once, showing it again means it leaked.
+        let (status, _) = refresh(&base_url, "user", &first).await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+        let (status, _) = refresh(&base_url, "user", successor).await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+        assert_eq!(
+            get(&base_url, "/api/user/sessions", access_token).await,
+            StatusCode::UNAUTHORIZED
+        );
+    }
+
+    #[cfg_attr(feature = "postgres", ignore = "needs TEST_DATABASE_URL")]
+    #[tokio::test]
+    async fn replaying_a_member_token_revokes_the_access_token() {
+        let repository = testing::repository().await;
+        let (_, owner) = testing::company(&repository).await;
+        let state = testing::app_state(repository, "127.0.0.1:9").await;
+        let tokens = issue_tokens(&state, &owner).await.unwrap();
+        let first = tokens["refresh_token"].as_str().unwrap().to_string();
+        let base_url = testing::serve(state).await;
+
+        let (status, rotated) = refresh(&base_url, "business", &first).await;
+        assert_eq!(status, StatusCode::OK);
+        let access_token = rotated["token"].as_str().unwrap();
+        assert_eq!(
+            get(&base_url, "/api/business/promo", access_token).await,
+            StatusCode::OK
+        );
+
+        let (status, _) = refresh(&base_url, "business", &first).await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+        assert_eq!(
+            get(&base_url, "/api/business/promo", access_token).await,
+            StatusCode::UNAUTHORIZED
+        );
+    }
+
+    #[cfg_attr(feature = "postgres", ignore = "needs TEST_DATABASE_URL")]
+    #[tokio::test]
+    async fn user_tokens_are_rejected_on_the_business_route() {
+        let repository = testing::repository().await;
+        let user = testing::user(&repository, 25, "ru").await;
+        let state = testing::app_state(repository, "127.0.0.1:9").await;
+        let client = ClientInfo {
+            ip_address: Ipv4Addr::LOCALHOST.into(),
+            user_agent: None,
+        };
+        let (_, refresh_token) = sessions::start(&state, &user.id, &client).await.unwrap();
+        let base_url = testing::serve(state).await;
+
+        let (status, _) = refresh(&base_url, "business", &refresh_token).await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+        // Being turned away by the other audience does not burn the token.
+        let (status, _) = refresh(&base_url, "user", &refresh_token).await;
+        assert_eq!(status, StatusCode::OK);
+    }
+}
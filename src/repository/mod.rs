@@ -0,0 +1,502 @@
+use crate::{
+    antifraud::Verdict,
+    business::{
+        api_keys::ApiKey,
+        auth::{
+            two_factor::{MemberTotp, TwoFactorChallenge},
+            Company, CompanyMember,
+        },
+        members::Invitation,
+        promo::{
+            events::PromoEvent,
+            list::{ListedPromo, PromoFilter, PromoPage},
+            Comment, Country, Promo, PromoForUser, PromoReadOnly,
+        },
+    },
+    email_verification::VerificationToken,
+    password_reset::PasswordResetToken,
+    refresh::RefreshToken,
+    scheduler::{JobKind, ScheduledJob},
+    sessions::UserSession,
+    sign_in_attempts::SignInFailures,
+    user::{
+        account::{ExportedActivation, ExportedComment, ExportedLike},
+        feed::FeedFilter,
+        promo::search::PromoSearchHit,
+        User,
+    },
+};
+use chrono::{DateTime, NaiveDate, Utc};
+
+#[cfg(feature = "postgres")]
+pub mod postgres;
+#[cfg(feature = "sqlite")]
+pub mod sqlite;
+
+#[cfg(feature = "postgres")]
+pub type Repository = postgres::PgRepository;
+#[cfg(all(feature = "sqlite", not(feature = "postgres")))]
+pub type Repository = sqlite::SqliteRepository;
+
+#[cfg(not(any(feature = "postgres", feature = "sqlite")))]
+compile_error!("either the `postgres` or the `sqlite` feature must be enabled");
+
+pub trait UserRepository {
+    async fn retrieve_user_by_email(&self, email: &str) -> Result<Option<User>, sqlx::Error>;
+    async fn retrieve_user_by_id(&self, id: &str) -> Result<Option<User>, sqlx::Error>;
+    async fn create_user(&self, user: &User) -> Result<(), sqlx::Error>;
+    async fn update_user(&self, user: &User) -> Result<(), sqlx::Error>;
+    async fn mark_user_email_verified(
+        &self,
+        id: &str,
+        now: DateTime<Utc>,
+    ) -> Result<(), sqlx::Error>;
+    async fn retrieve_user_likes(&self, user_id: &str) -> Result<Vec<ExportedLike>, sqlx::Error>;
+    async fn retrieve_user_comments(
+        &self,
+        user_id: &str,
+    ) -> Result<Vec<ExportedComment>, sqlx::Error>;
+    async fn retrieve_user_activations(
+        &self,
+        user_id: &str,
+    ) -> Result<Vec<ExportedActivation>, sqlx::Error>;
+    // Deletes the user's comments, sessions, tokens and antifraud verdict and
+    // leaves an anonymous row behind that no lookup returns any more.
+    async fn delete_user(&self, user: &User, now: DateTime<Utc>) -> Result<(), sqlx::Error>;
+}
+
+pub trait CompanyRepository {
+    async fn retrieve_company_by_id(&self, id: &str) -> Result<Option<Company>, sqlx::Error>;
+    // Creates the company together with its owner account.
+    async fn create_company(
+        &self,
+        company: &Company,
+        owner: &CompanyMember,
+    ) -> Result<(), sqlx::Error>;
+}
+
+pub trait MemberRepository {
+    async fn retrieve_member_by_email(
+        &self,
+        email: &str,
+    ) -> Result<Option<CompanyMember>, sqlx::Error>;
+    async fn retrieve_member_by_id(&self, id: &str) -> Result<Option<CompanyMember>, sqlx::Error>;
+    async fn update_member_password(
+        &self,
+        id: &str,
+        password_hash: &str,
+    ) -> Result<(), sqlx::Error>;
+    async fn mark_member_email_verified(
+        &self,
+        id: &str,
+        now: DateTime<Utc>,
+    ) -> Result<(), sqlx::Error>;
+    async fn retrieve_company_members(
+        &self,
+        company_id: &str,
+    ) -> Result<Vec<CompanyMember>, sqlx::Error>;
+    async fn create_invitation(&self, invitation: &Invitation) -> Result<(), sqlx::Error>;
+    async fn retrieve_invitation(
+        &self,
+        token_hash: &str,
+    ) -> Result<Option<Invitation>, sqlx::Error>;
+    // Marks the invitation accepted and creates the member. Returns `false` when
+    // the invitation was already accepted.
+    async fn accept_invitation(
+        &self,
+        invitation_id: &str,
+        member: &CompanyMember,
+        now: DateTime<Utc>,
+    ) -> Result<bool, sqlx::Error>;
+}
+
+pub trait PromoRepository {
+    async fn create_promo(
+        &self,
+        promo: &Promo,
+        promo_unique: Option<&[String]>,
+    ) -> Result<(), sqlx::Error>;
+    async fn retrieve_promo(
+        &self,
+        promo_id: &str,
+        today: NaiveDate,
+    ) -> Result<Option<Promo>, sqlx::Error>;
+    async fn retrieve_promo_read_only(
+        &self,
+        promo_id: &str,
+        today: NaiveDate,
+    ) -> Result<Option<PromoReadOnly>, sqlx::Error>;
+    async fn count_company_promos(
+        &self,
+        company_id: &str,
+        filter: &PromoFilter,
+        today: NaiveDate,
+    ) -> Result<i64, sqlx::Error>;
+    async fn list_company_promos(
+        &self,
+        company_id: &str,
+        filter: &PromoFilter,
+        page: &PromoPage,
+        today: NaiveDate,
+    ) -> Result<Vec<ListedPromo>, sqlx::Error>;
+    async fn update_promo(&self, promo: &Promo) -> Result<(), sqlx::Error>;
+    async fn retrieve_promo_countries(&self, promo_id: &str) -> Result<Vec<Country>, sqlx::Error>;
+    // Only published promos exist as far as users are concerned.
+    async fn promo_exists(&self, promo_id: &str) -> Result<bool, sqlx::Error>;
+    // Publishes the promo and records the event. Returns `false` when it was
+    // already published.
+    async fn publish_promo(&self, promo_id: &str, now: DateTime<Utc>) -> Result<bool, sqlx::Error>;
+    async fn record_promo_event(
+        &self,
+        promo_id: &str,
+        kind: &str,
+        now: DateTime<Utc>,
+    ) -> Result<(), sqlx::Error>;
+    async fn retrieve_promo_events(&self, promo_id: &str) -> Result<Vec<PromoEvent>, sqlx::Error>;
+    async fn count_feed(
+        &self,
+        user_id: &str,
+        filter: &FeedFilter,
+        today: NaiveDate,
+    ) -> Result<i64, sqlx::Error>;
+    async fn retrieve_feed(
+        &self,
+        user_id: &str,
+        filter: &FeedFilter,
+        today: NaiveDate,
+        limit: i64,
+        offset: i64,
+    ) -> Result<Vec<PromoForUser>, sqlx::Error>;
+    async fn count_search(
+        &self,
+        user_id: &str,
+        text: &str,
+        filter: &FeedFilter,
+        today: NaiveDate,
+    ) -> Result<i64, sqlx::Error>;
+    async fn search_promos(
+        &self,
+        user_id: &str,
+        text: &str,
+        filter: &FeedFilter,
+        today: NaiveDate,
+        limit: i64,
+        offset: i64,
+    ) -> Result<Vec<PromoSearchHit>, sqlx::Error>;
+    async fn retrieve_promo_for_user(
+        &self,
+        user_id: &str,
+        promo_id: &str,
+        today: NaiveDate,
+    ) -> Result<Option<PromoForUser>, sqlx::Error>;
+    async fn add_like(&self, promo_id: &str, user_id: &str) -> Result<(), sqlx::Error>;
+    async fn remove_like(&self, promo_id: &str, user_id: &str) -> Result<(), sqlx::Error>;
+}
+
+pub trait CommentRepository {
+    async fn create_comment(
+        &self,
+        comment_id: &str,
+        promo_id: &str,
+        user_id: &str,
+        text: &str,
+    ) -> Result<(), sqlx::Error>;
+    async fn retrieve_comment(
+        &self,
+        promo_id: &str,
+        comment_id: &str,
+    ) -> Result<Option<Comment>, sqlx::Error>;
+    async fn retrieve_comment_author_id(
+        &self,
+        promo_id: &str,
+        comment_id: &str,
+    ) -> Result<Option<String>, sqlx::Error>;
+    async fn retrieve_comments(&self, promo_id: &str) -> Result<Vec<Comment>, sqlx::Error>;
+    async fn update_comment(&self, comment_id: &str, text: &str) -> Result<(), sqlx::Error>;
+    async fn delete_comment(&self, comment_id: &str) -> Result<(), sqlx::Error>;
+}
+
+pub trait ActivationRepository {
+    // Returns `None` when the promo has no activations left. Records an
+    // `exhausted` event when this activation used up the last one.
+    async fn activate_promo(
+        &self,
+        promo_id: &str,
+        user_id: &str,
+        country: &str,
+        now: DateTime<Utc>,
+    ) -> Result<Option<String>, sqlx::Error>;
+    // Whether `code` is one of the unique codes the promo was created with.
+    async fn unique_code_exists(&self, promo_id: &str, code: &str) -> Result<bool, sqlx::Error>;
+    async fn count_activations(&self, user_id: &str) -> Result<i64, sqlx::Error>;
+    async fn retrieve_activation_history(
+        &self,
+        user_id: &str,
+        today: NaiveDate,
+        limit: i64,
+        offset: i64,
+    ) -> Result<Vec<PromoForUser>, sqlx::Error>;
+}
+
+pub trait VerdictRepository {
+    async fn retrieve_verdict(
+        &self,
+        user_email: &str,
+        now: DateTime<Utc>,
+    ) -> Result<Option<Verdict>, sqlx::Error>;
+    async fn store_verdict(
+        &self,
+        user_email: &str,
+        ok: bool,
+        cache_until: DateTime<Utc>,
+    ) -> Result<(), sqlx::Error>;
+}
+
+pub trait TokenRepository {
+    async fn retrieve_revoked_tokens(
+        &self,
+        now: DateTime<Utc>,
+    ) -> Result<Vec<(String, DateTime<Utc>)>, sqlx::Error>;
+    // Records a freshly issued token and revokes every other live token of the
+    // same subject, returning the ones it revoked.
+    async fn replace_issued_tokens(
+        &self,
+        subject_id: &str,
+        jti: &str,
+        expires_at: DateTime<Utc>,
+        now: DateTime<Utc>,
+    ) -> Result<Vec<(String, DateTime<Utc>)>, sqlx::Error>;
+    // Revokes every live token of the subject, returning the ones it revoked.
+    async fn revoke_issued_tokens(
+        &self,
+        subject_id: &str,
+        now: DateTime<Utc>,
+    ) -> Result<Vec<(String, DateTime<Utc>)>, sqlx::Error>;
+    async fn revoke_token(&self, jti: &str, expires_at: DateTime<Utc>) -> Result<(), sqlx::Error>;
+    async fn delete_expired_tokens(&self, now: DateTime<Utc>) -> Result<(), sqlx::Error>;
+}
+
+pub trait RefreshTokenRepository {
+    async fn create_refresh_token(&self, refresh_token: &RefreshToken) -> Result<(), sqlx::Error>;
+    async fn retrieve_refresh_token(
+        &self,
+        token_hash: &str,
+    ) -> Result<Option<RefreshToken>, sqlx::Error>;
+    // Marks `used_id` as used and stores its successor. Returns `false` when the
+    // token was already used or revoked by a concurrent request.
+    async fn rotate_refresh_token(
+        &self,
+        used_id: &str,
+        refresh_token: &RefreshToken,
+        now: DateTime<Utc>,
+    ) -> Result<bool, sqlx::Error>;
+    async fn revoke_refresh_token_family(
+        &self,
+        family_id: &str,
+        now: DateTime<Utc>,
+    ) -> Result<(), sqlx::Error>;
+    async fn revoke_subject_refresh_tokens(
+        &self,
+        audience: &str,
+        subject_id: &str,
+        now: DateTime<Utc>,
+    ) -> Result<(), sqlx::Error>;
+}
+
+pub trait ApiKeyRepository {
+    async fn create_api_key(&self, api_key: &ApiKey) -> Result<(), sqlx::Error>;
+    async fn retrieve_company_api_keys(&self, company_id: &str)
+        -> Result<Vec<ApiKey>, sqlx::Error>;
+    // Looks up an unrevoked key and records that it was used.
+    async fn use_api_key(
+        &self,
+        key_hash: &str,
+        now: DateTime<Utc>,
+    ) -> Result<Option<ApiKey>, sqlx::Error>;
+    async fn revoke_api_key(
+        &self,
+        company_id: &str,
+        id: &str,
+        now: DateTime<Utc>,
+    ) -> Result<bool, sqlx::Error>;
+}
+
+pub trait PasswordResetRepository {
+    async fn create_password_reset_token(
+        &self,
+        reset_token: &PasswordResetToken,
+    ) -> Result<(), sqlx::Error>;
+    // Consumes an unused, unexpired token together with every other pending
+    // token of the same subject. Returns the consumed token.
+    async fn use_password_reset_token(
+        &self,
+        audience: &str,
+        token_hash: &str,
+        now: DateTime<Utc>,
+    ) -> Result<Option<PasswordResetToken>, sqlx::Error>;
+}
+
+pub trait EmailVerificationRepository {
+    async fn create_verification_token(
+        &self,
+        verification_token: &VerificationToken,
+    ) -> Result<(), sqlx::Error>;
+    // Records a send to the subject at `now` unless the previous one went out
+    // after `last_sent_before`. Returns the time of that previous send when it
+    // holds this one back.
+    async fn claim_verification_send(
+        &self,
+        audience: &str,
+        subject_id: &str,
+        now: DateTime<Utc>,
+        last_sent_before: DateTime<Utc>,
+    ) -> Result<Option<DateTime<Utc>>, sqlx::Error>;
+    // Consumes an unused, unexpired token. Returns the subject it was issued to.
+    async fn use_verification_token(
+        &self,
+        audience: &str,
+        token_hash: &str,
+        now: DateTime<Utc>,
+    ) -> Result<Option<String>, sqlx::Error>;
+}
+
+pub trait TwoFactorRepository {
+    async fn retrieve_member_totp(
+        &self,
+        member_id: &str,
+    ) -> Result<Option<MemberTotp>, sqlx::Error>;
+    // Stores a new enrollment unless 2FA is already enabled for the member.
+    async fn save_pending_member_totp(&self, member_totp: &MemberTotp) -> Result<(), sqlx::Error>;
+    // Enables a pending enrollment with the step of the code that confirmed it
+    // and stores the recovery codes. Returns `false` when it was not pending.
+    async fn enable_member_totp(
+        &self,
+        member_id: &str,
+        step: i64,
+        recovery_code_hashes: &[String],
+        now: DateTime<Utc>,
+    ) -> Result<bool, sqlx::Error>;
+    // Records the step of an accepted code. Returns `false` when a code of this
+    // or a later step was already used.
+    async fn use_totp_step(&self, member_id: &str, step: i64) -> Result<bool, sqlx::Error>;
+    async fn use_recovery_code(
+        &self,
+        member_id: &str,
+        code_hash: &str,
+        now: DateTime<Utc>,
+    ) -> Result<bool, sqlx::Error>;
+    async fn replace_recovery_codes(
+        &self,
+        member_id: &str,
+        recovery_code_hashes: &[String],
+    ) -> Result<(), sqlx::Error>;
+    // Removes the secret together with the recovery codes.
+    async fn delete_member_totp(&self, member_id: &str) -> Result<(), sqlx::Error>;
+    async fn create_two_factor_challenge(
+        &self,
+        challenge: &TwoFactorChallenge,
+    ) -> Result<(), sqlx::Error>;
+    // Counts an attempt against an unused, unexpired challenge before its code
+    // is checked, so concurrent guesses can't exceed `max_attempts` together.
+    // Returns `None` once the challenge can't be answered any more.
+    async fn reserve_two_factor_attempt(
+        &self,
+        token_hash: &str,
+        now: DateTime<Utc>,
+        max_attempts: i32,
+    ) -> Result<Option<TwoFactorChallenge>, sqlx::Error>;
+    // Returns `false` when the challenge was already used.
+    async fn use_two_factor_challenge(
+        &self,
+        id: &str,
+        now: DateTime<Utc>,
+    ) -> Result<bool, sqlx::Error>;
+}
+
+pub trait SignInFailureRepository {
+    // Counts a failure unless `locked_until` of the failures so far is after
+    // `now`, see `sign_in_attempts::FailureStore::reserve`.
+    async fn reserve_sign_in_attempt(
+        &self,
+        attempt_key: &str,
+        now: DateTime<Utc>,
+        reset_before: DateTime<Utc>,
+        locked_until: impl Fn(&SignInFailures) -> Option<DateTime<Utc>> + Send,
+    ) -> Result<Option<DateTime<Utc>>, sqlx::Error>;
+    async fn release_sign_in_attempt(&self, attempt_key: &str) -> Result<(), sqlx::Error>;
+    async fn clear_sign_in_failures(&self, attempt_key: &str) -> Result<(), sqlx::Error>;
+    async fn delete_stale_sign_in_failures(
+        &self,
+        reset_before: DateTime<Utc>,
+    ) -> Result<(), sqlx::Error>;
+}
+
+pub trait UserSessionRepository {
+    async fn create_user_session(&self, session: &UserSession) -> Result<(), sqlx::Error>;
+    async fn retrieve_user_session(&self, id: &str) -> Result<Option<UserSession>, sqlx::Error>;
+    // Sessions that are neither revoked nor expired, most recently seen first.
+    async fn retrieve_user_sessions(
+        &self,
+        user_id: &str,
+        now: DateTime<Utc>,
+    ) -> Result<Vec<UserSession>, sqlx::Error>;
+    async fn touch_user_session(&self, id: &str, now: DateTime<Utc>) -> Result<(), sqlx::Error>;
+    // Moves a live session over to a freshly issued access token. Returns
+    // `false` when the session was revoked in the meantime.
+    async fn refresh_user_session(&self, session: &UserSession) -> Result<bool, sqlx::Error>;
+    // Returns `false` when the user has no such live session.
+    async fn revoke_user_session(
+        &self,
+        user_id: &str,
+        id: &str,
+        now: DateTime<Utc>,
+    ) -> Result<bool, sqlx::Error>;
+    // Revokes every live session of the user except `keep_id`, returning the
+    // ids of the revoked ones.
+    async fn revoke_user_sessions(
+        &self,
+        user_id: &str,
+        keep_id: Option<&str>,
+        now: DateTime<Utc>,
+    ) -> Result<Vec<String>, sqlx::Error>;
+    async fn delete_stale_user_sessions(
+        &self,
+        user_id: &str,
+        now: DateTime<Utc>,
+    ) -> Result<(), sqlx::Error>;
+}
+
+pub trait JobRepository {
+    // Replaces the pending job of that kind for the promo, if there is one.
+    async fn schedule_job(
+        &self,
+        kind: JobKind,
+        promo_id: &str,
+        run_at: DateTime<Utc>,
+        now: DateTime<Utc>,
+    ) -> Result<(), sqlx::Error>;
+    async fn cancel_job(&self, kind: JobKind, promo_id: &str) -> Result<(), sqlx::Error>;
+    // Locks up to `limit` due jobs until `locked_until` and counts an attempt
+    // for each. Jobs locked by a run that hasn't finished are skipped.
+    async fn claim_due_jobs(
+        &self,
+        now: DateTime<Utc>,
+        locked_until: DateTime<Utc>,
+        limit: i64,
+    ) -> Result<Vec<ScheduledJob>, sqlx::Error>;
+    async fn complete_job(
+        &self,
+        id: i64,
+        now: DateTime<Utc>,
+        error: Option<&str>,
+    ) -> Result<(), sqlx::Error>;
+    async fn retry_job(
+        &self,
+        id: i64,
+        run_at: DateTime<Utc>,
+        error: &str,
+    ) -> Result<(), sqlx::Error>;
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,184 @@
+use super::{
+    promos::{insert_promo_event, PROMO_FOR_USER_SELECT},
+    PgRepository,
+};
+use crate::{business::promo::PromoForUser, repository::ActivationRepository};
+use chrono::{DateTime, NaiveDate, Utc};
+use sqlx::prelude::FromRow;
+
+#[derive(FromRow)]
+struct LockedPromo {
+    mode: String,
+    max_count: i32,
+    used_count: i32,
+    promo_common: Option<String>,
+}
+
+impl ActivationRepository for PgRepository {
+    async fn activate_promo(
+        &self,
+        promo_id: &str,
+        user_id: &str,
+        country: &str,
+        now: DateTime<Utc>,
+    ) -> Result<Option<String>, sqlx::Error> {
+        let mut tx = self.pool.begin().await?;
+
+        // Holding the promo row lock serializes concurrent activations of the
+        // same promo until the counters below are committed.
+        let promo: Option<LockedPromo> = sqlx::query_as(
+            r#"
+            SELECT mode, max_count, used_count, promo_common FROM promos
+            WHERE promo_id = $1
+            FOR UPDATE
+            "#,
+        )
+        .bind(promo_id)
+        .fetch_optional(&mut *tx)
+        .await?;
+
+        let Some(promo) = promo else {
+            return Ok(None);
+        };
+
+        let code = if promo.mode == "UNIQUE" {
+            sqlx::query_scalar(
+                r#"
+                UPDATE promo_unique_codes
+                SET activated_by = $1, activated_at = $3
+                WHERE id = (
+                    SELECT id FROM promo_unique_codes
+                    WHERE promo_id = $2 AND activated_at IS NULL
+                    ORDER BY position
+                    LIMIT 1
+                    FOR UPDATE SKIP LOCKED
+                )
+                RETURNING code
+                "#,
+            )
+            .bind(user_id)
+            .bind(promo_id)
+            .bind(now)
+            .fetch_optional(&mut *tx)
+            .await?
+        } else if promo.used_count < promo.max_count {
+            promo.promo_common
+        } else {
+            None
+        };
+
+        let Some(code) = code else {
+            return Ok(None);
+        };
+
+        let used_count: i32 = sqlx::query_scalar(
+            r#"
+            UPDATE promos
+            SET used_count = used_count + 1
+            WHERE promo_id = $1
+            RETURNING used_count
+            "#,
+        )
+        .bind(promo_id)
+        .fetch_one(&mut *tx)
+        .await?;
+
+        sqlx::query(
+            r#"
+            INSERT INTO promo_countries (promo_id, country, activate_count)
+            VALUES ($1, $2, 1)
+            ON CONFLICT (promo_id, country)
+            DO UPDATE SET activate_count = promo_countries.activate_count + 1
+            "#,
+        )
+        .bind(promo_id)
+        .bind(country.to_lowercase())
+        .execute(&mut *tx)
+        .await?;
+
+        sqlx::query(
+            r#"
+            INSERT INTO promo_activations (promo_id, user_id, activated_at)
+            VALUES ($1, $2, $3)
+            "#,
+        )
+        .bind(promo_id)
+        .bind(user_id)
+        .bind(now)
+        .execute(&mut *tx)
+        .await?;
+
+        let exhausted = if promo.mode == "UNIQUE" {
+            !sqlx::query_scalar::<_, bool>(
+                r#"
+                SELECT EXISTS (
+                    SELECT 1 FROM promo_unique_codes
+                    WHERE promo_id = $1 AND activated_at IS NULL
+                )
+                "#,
+            )
+            .bind(promo_id)
+            .fetch_one(&mut *tx)
+            .await?
+        } else {
+            used_count >= promo.max_count
+        };
+        if exhausted {
+            insert_promo_event(&mut *tx, promo_id, "exhausted", now).await?;
+        }
+
+        tx.commit().await?;
+
+        Ok(Some(code))
+    }
+
+    async fn unique_code_exists(&self, promo_id: &str, code: &str) -> Result<bool, sqlx::Error> {
+        sqlx::query_scalar(
+            r#"
+            SELECT EXISTS (
+                SELECT 1 FROM promo_unique_codes WHERE promo_id = $1 AND code = $2
+            )
+            "#,
+        )
+        .bind(promo_id)
+        .bind(code)
+        .fetch_one(&self.pool)
+        .await
+    }
+
+    async fn count_activations(&self, user_id: &str) -> Result<i64, sqlx::Error> {
+        sqlx::query_scalar(
+            r#"
+            SELECT COUNT(*) FROM promo_activations WHERE user_id = $1
+            "#,
+        )
+        .bind(user_id)
+        .fetch_one(&self.pool)
+        .await
+    }
+
+    async fn retrieve_activation_history(
+        &self,
+        user_id: &str,
+        today: NaiveDate,
+        limit: i64,
+        offset: i64,
+    ) -> Result<Vec<PromoForUser>, sqlx::Error> {
+        sqlx::query_as(&format!(
+            r#"
+            {}
+            JOIN promo_activations h ON h.promo_id = p.promo_id
+            WHERE h.user_id = $2
+            ORDER BY h.activated_at DESC, h.id DESC
+            LIMIT $3 OFFSET $4
+            "#,
+            *PROMO_FOR_USER_SELECT
+        ))
+        .bind(today)
+        .bind(user_id)
+        .bind(limit)
+        .bind(offset)
+        .fetch_all(&self.pool)
+        .await
+    }
+}
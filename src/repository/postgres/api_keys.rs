@@ -0,0 +1,80 @@
+use super::PgRepository;
+use crate::{business::api_keys::ApiKey, repository::ApiKeyRepository};
+use chrono::{DateTime, Utc};
+
+impl ApiKeyRepository for PgRepository {
+    async fn create_api_key(&self, api_key: &ApiKey) -> Result<(), sqlx::Error> {
+        sqlx::query(
+            r#"
+            INSERT INTO api_keys (id, company_id, name, role, prefix, key_hash, created_at)
+            VALUES ($1, $2, $3, $4, $5, $6, $7)
+            "#,
+        )
+        .bind(&api_key.id)
+        .bind(&api_key.company_id)
+        .bind(&api_key.name)
+        .bind(&api_key.role)
+        .bind(&api_key.prefix)
+        .bind(&api_key.key_hash)
+        .bind(api_key.created_at)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    async fn retrieve_company_api_keys(
+        &self,
+        company_id: &str,
+    ) -> Result<Vec<ApiKey>, sqlx::Error> {
+        sqlx::query_as(
+            r#"
+            SELECT * FROM api_keys WHERE company_id = $1 ORDER BY created_at DESC
+            "#,
+        )
+        .bind(company_id)
+        .fetch_all(&self.pool)
+        .await
+    }
+
+    async fn use_api_key(
+        &self,
+        key_hash: &str,
+        now: DateTime<Utc>,
+    ) -> Result<Option<ApiKey>, sqlx::Error> {
+        sqlx::query_as(
+            r#"
+            UPDATE api_keys
+            SET last_used_at = $1
+            WHERE key_hash = $2 AND revoked_at IS NULL
+            RETURNING *
+            "#,
+        )
+        .bind(now)
+        .bind(key_hash)
+        .fetch_optional(&self.pool)
+        .await
+    }
+
+    async fn revoke_api_key(
+        &self,
+        company_id: &str,
+        id: &str,
+        now: DateTime<Utc>,
+    ) -> Result<bool, sqlx::Error> {
+        let revoked = sqlx::query(
+            r#"
+            UPDATE api_keys
+            SET revoked_at = $1
+            WHERE id = $2 AND company_id = $3 AND revoked_at IS NULL
+            "#,
+        )
+        .bind(now)
+        .bind(id)
+        .bind(company_id)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(revoked.rows_affected() > 0)
+    }
+}
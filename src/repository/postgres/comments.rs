@@ -0,0 +1,103 @@
+use super::PgRepository;
+use crate::{business::promo::Comment, repository::CommentRepository};
+
+const COMMENT_SELECT: &str = r#"
+    SELECT c.id, c.text, c.created_at AS date, u.name, u.surname, u.avatar_url
+    FROM promo_comments c
+    JOIN users u ON u.id = c.user_id
+"#;
+
+impl CommentRepository for PgRepository {
+    async fn create_comment(
+        &self,
+        comment_id: &str,
+        promo_id: &str,
+        user_id: &str,
+        text: &str,
+    ) -> Result<(), sqlx::Error> {
+        sqlx::query(
+            r#"
+            INSERT INTO promo_comments (id, promo_id, user_id, text)
+            VALUES ($1, $2, $3, $4)
+            "#,
+        )
+        .bind(comment_id)
+        .bind(promo_id)
+        .bind(user_id)
+        .bind(text)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    async fn retrieve_comment(
+        &self,
+        promo_id: &str,
+        comment_id: &str,
+    ) -> Result<Option<Comment>, sqlx::Error> {
+        sqlx::query_as(&format!(
+            "{} WHERE c.id = $1 AND c.promo_id = $2",
+            COMMENT_SELECT
+        ))
+        .bind(comment_id)
+        .bind(promo_id)
+        .fetch_optional(&self.pool)
+        .await
+    }
+
+    async fn retrieve_comment_author_id(
+        &self,
+        promo_id: &str,
+        comment_id: &str,
+    ) -> Result<Option<String>, sqlx::Error> {
+        sqlx::query_scalar(
+            r#"
+            SELECT user_id FROM promo_comments WHERE id = $1 AND promo_id = $2
+            "#,
+        )
+        .bind(comment_id)
+        .bind(promo_id)
+        .fetch_optional(&self.pool)
+        .await
+    }
+
+    async fn retrieve_comments(&self, promo_id: &str) -> Result<Vec<Comment>, sqlx::Error> {
+        sqlx::query_as(&format!(
+            "{} WHERE c.promo_id = $1 ORDER BY c.created_at DESC",
+            COMMENT_SELECT
+        ))
+        .bind(promo_id)
+        .fetch_all(&self.pool)
+        .await
+    }
+
+    async fn update_comment(&self, comment_id: &str, text: &str) -> Result<(), sqlx::Error> {
+        sqlx::query(
+            r#"
+            UPDATE promo_comments
+            SET text = $1
+            WHERE id = $2
+            "#,
+        )
+        .bind(text)
+        .bind(comment_id)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    async fn delete_comment(&self, comment_id: &str) -> Result<(), sqlx::Error> {
+        sqlx::query(
+            r#"
+            DELETE FROM promo_comments WHERE id = $1
+            "#,
+        )
+        .bind(comment_id)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,167 @@
+use super::PgRepository;
+use crate::{
+    business::{auth::CompanyMember, members::Invitation},
+    repository::MemberRepository,
+};
+use chrono::{DateTime, Utc};
+
+impl MemberRepository for PgRepository {
+    async fn retrieve_member_by_email(
+        &self,
+        email: &str,
+    ) -> Result<Option<CompanyMember>, sqlx::Error> {
+        sqlx::query_as(
+            r#"
+            SELECT * FROM company_members WHERE email = $1
+            "#,
+        )
+        .bind(email)
+        .fetch_optional(&self.pool)
+        .await
+    }
+
+    async fn retrieve_member_by_id(&self, id: &str) -> Result<Option<CompanyMember>, sqlx::Error> {
+        sqlx::query_as(
+            r#"
+            SELECT * FROM company_members WHERE id = $1
+            "#,
+        )
+        .bind(id)
+        .fetch_optional(&self.pool)
+        .await
+    }
+
+    async fn update_member_password(
+        &self,
+        id: &str,
+        password_hash: &str,
+    ) -> Result<(), sqlx::Error> {
+        sqlx::query(
+            r#"
+            UPDATE company_members SET password_hash = $1 WHERE id = $2
+            "#,
+        )
+        .bind(password_hash)
+        .bind(id)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    async fn mark_member_email_verified(
+        &self,
+        id: &str,
+        now: DateTime<Utc>,
+    ) -> Result<(), sqlx::Error> {
+        sqlx::query(
+            r#"
+            UPDATE company_members
+            SET email_verified_at = $1
+            WHERE id = $2 AND email_verified_at IS NULL
+            "#,
+        )
+        .bind(now)
+        .bind(id)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    async fn retrieve_company_members(
+        &self,
+        company_id: &str,
+    ) -> Result<Vec<CompanyMember>, sqlx::Error> {
+        sqlx::query_as(
+            r#"
+            SELECT * FROM company_members WHERE company_id = $1 ORDER BY email
+            "#,
+        )
+        .bind(company_id)
+        .fetch_all(&self.pool)
+        .await
+    }
+
+    async fn create_invitation(&self, invitation: &Invitation) -> Result<(), sqlx::Error> {
+        sqlx::query(
+            r#"
+            INSERT INTO company_invitations (
+                id, company_id, email, role, token_hash, created_at, expires_at
+            )
+            VALUES ($1, $2, $3, $4, $5, $6, $7)
+            "#,
+        )
+        .bind(&invitation.id)
+        .bind(&invitation.company_id)
+        .bind(&invitation.email)
+        .bind(&invitation.role)
+        .bind(&invitation.token_hash)
+        .bind(invitation.created_at)
+        .bind(invitation.expires_at)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    async fn retrieve_invitation(
+        &self,
+        token_hash: &str,
+    ) -> Result<Option<Invitation>, sqlx::Error> {
+        sqlx::query_as(
+            r#"
+            SELECT * FROM company_invitations WHERE token_hash = $1
+            "#,
+        )
+        .bind(token_hash)
+        .fetch_optional(&self.pool)
+        .await
+    }
+
+    async fn accept_invitation(
+        &self,
+        invitation_id: &str,
+        member: &CompanyMember,
+        now: DateTime<Utc>,
+    ) -> Result<bool, sqlx::Error> {
+        let mut tx = self.pool.begin().await?;
+
+        let accepted = sqlx::query(
+            r#"
+            UPDATE company_invitations
+            SET accepted_at = $1
+            WHERE id = $2 AND accepted_at IS NULL
+            "#,
+        )
+        .bind(now)
+        .bind(invitation_id)
+        .execute(&mut *tx)
+        .await?;
+
+        if accepted.rows_affected() == 0 {
+            return Ok(false);
+        }
+
+        sqlx::query(
+            r#"
+            INSERT INTO company_members
+                (id, company_id, name, email, password_hash, role, email_verified_at)
+            VALUES ($1, $2, $3, $4, $5, $6, $7)
+            "#,
+        )
+        .bind(&member.id)
+        .bind(&member.company_id)
+        .bind(&member.name)
+        .bind(&member.email)
+        .bind(&member.password_hash)
+        .bind(&member.role)
+        .bind(member.email_verified_at)
+        .execute(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+
+        Ok(true)
+    }
+}
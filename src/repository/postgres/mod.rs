@@ -0,0 +1,62 @@
+use sqlx::{postgres::PgPoolOptions, PgPool};
+
+mod activations;
+mod api_keys;
+mod comments;
+mod companies;
+mod email_verifications;
+mod jobs;
+mod members;
+mod password_resets;
+mod promos;
+mod refresh_tokens;
+mod sign_in_failures;
+mod tokens;
+mod two_factor;
+mod user_sessions;
+mod users;
+mod verdicts;
+
+#[derive(Clone)]
+pub struct PgRepository {
+    pool: PgPool,
+}
+
+impl PgRepository {
+    pub async fn connect(database_url: &str) -> Result<Self, sqlx::Error> {
+        let pool = PgPoolOptions::new()
+            .max_connections(5)
+            .connect(database_url)
+            .await?;
+        sqlx::migrate!("migrations/postgres").run(&pool).await?;
+
+        Ok(PgRepository { pool })
+    }
+}
+
+#[cfg(test)]
+impl PgRepository {
+    // Migrates a schema of its own, so that tests can share one database
+    // without seeing each other's rows.
+    pub async fn connect_isolated(database_url: &str) -> Result<Self, sqlx::Error> {
+        use sqlx::{postgres::PgConnectOptions, Connection, Executor, PgConnection};
+        use std::str::FromStr;
+
+        let schema = format!("test_{}", uuid::Uuid::new_v4().simple());
+        let mut connection = PgConnection::connect(database_url).await?;
+        connection
+            .execute(format!("CREATE SCHEMA {}", schema).as_str())
+            .await?;
+        connection.close().await?;
+
+        let options =
+            PgConnectOptions::from_str(database_url)?.options([("search_path", schema.as_str())]);
+        let pool = PgPoolOptions::new()
+            .max_connections(5)
+            .connect_with(options)
+            .await?;
+        sqlx::migrate!("migrations/postgres").run(&pool).await?;
+
+        Ok(PgRepository { pool })
+    }
+}
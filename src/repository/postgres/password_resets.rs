@@ -0,0 +1,73 @@
+use super::PgRepository;
+use crate::{password_reset::PasswordResetToken, repository::PasswordResetRepository};
+use chrono::{DateTime, Utc};
+
+impl PasswordResetRepository for PgRepository {
+    async fn create_password_reset_token(
+        &self,
+        reset_token: &PasswordResetToken,
+    ) -> Result<(), sqlx::Error> {
+        sqlx::query(
+            r#"
+            INSERT INTO password_reset_tokens (
+                id, audience, subject_id, token_hash, created_at, expires_at
+            )
+            VALUES ($1, $2, $3, $4, $5, $6)
+            "#,
+        )
+        .bind(&reset_token.id)
+        .bind(&reset_token.audience)
+        .bind(&reset_token.subject_id)
+        .bind(&reset_token.token_hash)
+        .bind(reset_token.created_at)
+        .bind(reset_token.expires_at)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    async fn use_password_reset_token(
+        &self,
+        audience: &str,
+        token_hash: &str,
+        now: DateTime<Utc>,
+    ) -> Result<Option<PasswordResetToken>, sqlx::Error> {
+        let mut tx = self.pool.begin().await?;
+
+        let reset_token: Option<PasswordResetToken> = sqlx::query_as(
+            r#"
+            UPDATE password_reset_tokens
+            SET used_at = $1
+            WHERE audience = $2 AND token_hash = $3 AND used_at IS NULL AND expires_at > $1
+            RETURNING *
+            "#,
+        )
+        .bind(now)
+        .bind(audience)
+        .bind(token_hash)
+        .fetch_optional(&mut *tx)
+        .await?;
+
+        let Some(reset_token) = reset_token else {
+            return Ok(None);
+        };
+
+        sqlx::query(
+            r#"
+            UPDATE password_reset_tokens
+            SET used_at = $1
+            WHERE audience = $2 AND subject_id = $3 AND used_at IS NULL
+            "#,
+        )
+        .bind(now)
+        .bind(audience)
+        .bind(&reset_token.subject_id)
+        .execute(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+
+        Ok(Some(reset_token))
+    }
+}
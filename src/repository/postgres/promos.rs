@@ -0,0 +1,566 @@
+use super::PgRepository;
+use crate::{
+    business::promo::{
+        events::PromoEvent,
+        list::{ListedPromo, PromoFilter, PromoPage, PromoSort},
+        Country, Promo, PromoForUser, PromoReadOnly,
+    },
+    repository::PromoRepository,
+    user::{
+        feed::FeedFilter,
+        promo::search::{PromoSearchHit, MATCH_END, MATCH_START},
+    },
+};
+use chrono::{DateTime, NaiveDate, Utc};
+use sqlx::{Executor, Postgres};
+use std::sync::LazyLock;
+
+// Whether a promo can be activated on the day bound as `$1`: it has been
+// published, the day lies within `[active_from; active_until]` and codes are
+// left, which for COMMON promos means activations stay below `max_count`.
+const PROMO_ACTIVE: &str = r#"
+    (
+        p.published_at IS NOT NULL
+        AND (p.active_from IS NULL OR p.active_from <= $1)
+        AND (p.active_until IS NULL OR p.active_until >= $1)
+        AND CASE WHEN p.mode = 'UNIQUE' THEN EXISTS (
+            SELECT 1 FROM promo_unique_codes uc
+            WHERE uc.promo_id = p.promo_id AND uc.activated_at IS NULL
+        ) ELSE p.used_count < p.max_count END
+    )
+"#;
+
+// Expects the current day as `$1`.
+static PROMO_READ_ONLY_COLUMNS: LazyLock<String> = LazyLock::new(|| {
+    format!(
+        r#"
+        p.description, p.image_url, p.target, p.max_count, p.active_from, p.active_until,
+        p.mode, p.promo_common, p.promo_id, p.company_id, p.company_name, p.used_count,
+        p.publish_at, p.published_at, {} AS active,
+        CASE WHEN p.mode = 'UNIQUE' THEN (
+            SELECT COALESCE(jsonb_agg(u.code ORDER BY u.position), '[]'::jsonb)
+            FROM promo_unique_codes u
+            WHERE u.promo_id = p.promo_id
+        ) END AS promo_unique,
+        CAST((SELECT COUNT(*) FROM promo_likes l WHERE l.promo_id = p.promo_id) AS INTEGER) AS like_count
+"#,
+        PROMO_ACTIVE
+    )
+});
+
+static PROMO_READ_ONLY_SELECT: LazyLock<String> =
+    LazyLock::new(|| format!("SELECT {} FROM promos p", *PROMO_READ_ONLY_COLUMNS));
+
+// Expects the current day as `$1` and the requesting user's id as `$2`.
+static PROMO_FOR_USER_COLUMNS: LazyLock<String> = LazyLock::new(|| {
+    format!(
+        r#"
+        p.promo_id, p.company_id, p.company_name, p.description, p.image_url,
+        {} AS active,
+        EXISTS (
+            SELECT 1 FROM promo_activations a WHERE a.promo_id = p.promo_id AND a.user_id = $2
+        ) AS is_activated_by_user,
+        CAST((SELECT COUNT(*) FROM promo_likes l WHERE l.promo_id = p.promo_id) AS INTEGER) AS like_count,
+        EXISTS (
+            SELECT 1 FROM promo_likes l WHERE l.promo_id = p.promo_id AND l.user_id = $2
+        ) AS is_liked_by_user,
+        CAST((SELECT COUNT(*) FROM promo_comments c WHERE c.promo_id = p.promo_id) AS INTEGER) AS comment_count
+"#,
+        PROMO_ACTIVE
+    )
+});
+
+pub(super) static PROMO_FOR_USER_SELECT: LazyLock<String> =
+    LazyLock::new(|| format!("SELECT {} FROM promos p", *PROMO_FOR_USER_COLUMNS));
+
+// Applies a company listing filter, with the current day as `$1`, then the
+// countries, mode, activity, date range and text as `$3` to `$8`.
+static LIST_CONDITIONS: LazyLock<String> = LazyLock::new(|| {
+    format!(
+        r#"
+    (cardinality($3::TEXT[]) = 0 OR p.target->>'country' IS NULL
+        OR LOWER(p.target->>'country') = ANY($3))
+    AND ($4::TEXT IS NULL OR p.mode = $4)
+    AND ($5::BOOLEAN IS NULL OR {} = $5)
+    AND ($6::DATE IS NULL OR p.active_until IS NULL OR p.active_until >= $6)
+    AND ($7::DATE IS NULL OR p.active_from IS NULL OR p.active_from <= $7)
+    AND ($8::TEXT IS NULL OR strpos(LOWER(p.description), LOWER($8)) > 0)
+"#,
+        PROMO_ACTIVE
+    )
+});
+
+// The sort key of a listing and the type to read a cursor's key back as.
+fn sort_key(sort: PromoSort) -> (&'static str, &'static str) {
+    match sort {
+        PromoSort::CreateDate => ("p.create_date", "TIMESTAMPTZ"),
+        PromoSort::ActiveFrom => ("COALESCE(p.active_from, DATE '0001-01-01')", "DATE"),
+        PromoSort::ActiveUntil => ("COALESCE(p.active_until, DATE '9999-12-31')", "DATE"),
+        PromoSort::LikeCount => (
+            "(SELECT COUNT(*) FROM promo_likes l WHERE l.promo_id = p.promo_id)",
+            "BIGINT",
+        ),
+        PromoSort::UsedCount => ("p.used_count", "INTEGER"),
+    }
+}
+
+// Matches promos against the target settings of the user joined as `u` and
+// applies the feed filter, with the category as `$3` and activity as `$4`.
+// Categories take no part in target matching, and unpublished promos never
+// match.
+static FEED_CONDITIONS: LazyLock<String> = LazyLock::new(|| {
+    format!(
+        r#"
+    p.published_at IS NOT NULL
+    AND (p.target->>'age_from' IS NULL
+        OR (p.target->>'age_from')::INTEGER <= (u.other->>'age')::INTEGER)
+    AND (p.target->>'age_until' IS NULL
+        OR (p.target->>'age_until')::INTEGER >= (u.other->>'age')::INTEGER)
+    AND (p.target->>'country' IS NULL
+        OR LOWER(p.target->>'country') = LOWER(u.other->>'country'))
+    AND ($3::TEXT IS NULL OR EXISTS (
+        SELECT 1 FROM jsonb_array_elements_text(p.target->'categories') AS c(category)
+        WHERE LOWER(c.category) = LOWER($3)
+    ))
+    AND ($4::BOOLEAN IS NULL OR {} = $4)
+"#,
+        PROMO_ACTIVE
+    )
+});
+
+// How well the promo matches the search query joined as `q`, in `[0; 1)`,
+// raised by up to a half for promos with many likes.
+const SEARCH_SCORE: &str = r#"
+    ts_rank(p.search_vector, q, 32) * (
+        1 + 0.5 * (SELECT COUNT(*) FROM promo_likes l WHERE l.promo_id = p.promo_id)
+            / ((SELECT COUNT(*) FROM promo_likes l WHERE l.promo_id = p.promo_id) + 10.0)
+    )
+"#;
+
+pub(super) async fn insert_promo_event<'e>(
+    executor: impl Executor<'e, Database = Postgres>,
+    promo_id: &str,
+    kind: &str,
+    now: DateTime<Utc>,
+) -> Result<(), sqlx::Error> {
+    sqlx::query(
+        r#"
+        INSERT INTO promo_events (promo_id, kind, occurred_at)
+        VALUES ($1, $2, $3)
+        "#,
+    )
+    .bind(promo_id)
+    .bind(kind)
+    .bind(now)
+    .execute(executor)
+    .await?;
+
+    Ok(())
+}
+
+impl PromoRepository for PgRepository {
+    async fn create_promo(
+        &self,
+        promo: &Promo,
+        promo_unique: Option<&[String]>,
+    ) -> Result<(), sqlx::Error> {
+        let mut tx = self.pool.begin().await?;
+
+        sqlx::query(
+            r#"
+            INSERT INTO promos (
+                description, image_url, target, max_count, create_date, active_from, active_until,
+                mode, promo_common, promo_id, company_id, company_name, used_count,
+                publish_at, published_at
+            )
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15)
+            "#,
+        )
+        .bind(&promo.description)
+        .bind(&promo.image_url)
+        .bind(&promo.target)
+        .bind(promo.max_count)
+        .bind(promo.create_date)
+        .bind(promo.active_from)
+        .bind(promo.active_until)
+        .bind(&promo.mode)
+        .bind(&promo.promo_common)
+        .bind(&promo.promo_id)
+        .bind(&promo.company_id)
+        .bind(&promo.company_name)
+        .bind(promo.used_count)
+        .bind(promo.publish_at)
+        .bind(promo.published_at)
+        .execute(&mut *tx)
+        .await?;
+
+        if let Some(published_at) = promo.published_at {
+            insert_promo_event(&mut *tx, &promo.promo_id, "published", published_at).await?;
+        }
+
+        if let Some(promo_unique) = promo_unique {
+            sqlx::query(
+                r#"
+                INSERT INTO promo_unique_codes (promo_id, code, position)
+                SELECT $1, code, position
+                FROM UNNEST($2::TEXT[]) WITH ORDINALITY AS codes(code, position)
+                "#,
+            )
+            .bind(&promo.promo_id)
+            .bind(promo_unique)
+            .execute(&mut *tx)
+            .await?;
+        }
+
+        tx.commit().await
+    }
+
+    async fn retrieve_promo(
+        &self,
+        promo_id: &str,
+        today: NaiveDate,
+    ) -> Result<Option<Promo>, sqlx::Error> {
+        sqlx::query_as(&format!(
+            "SELECT p.*, {} AS active FROM promos p WHERE p.promo_id = $2",
+            PROMO_ACTIVE
+        ))
+        .bind(today)
+        .bind(promo_id)
+        .fetch_optional(&self.pool)
+        .await
+    }
+
+    async fn retrieve_promo_read_only(
+        &self,
+        promo_id: &str,
+        today: NaiveDate,
+    ) -> Result<Option<PromoReadOnly>, sqlx::Error> {
+        sqlx::query_as(&format!(
+            "{} WHERE p.promo_id = $2",
+            *PROMO_READ_ONLY_SELECT
+        ))
+        .bind(today)
+        .bind(promo_id)
+        .fetch_optional(&self.pool)
+        .await
+    }
+
+    async fn count_company_promos(
+        &self,
+        company_id: &str,
+        filter: &PromoFilter,
+        today: NaiveDate,
+    ) -> Result<i64, sqlx::Error> {
+        sqlx::query_scalar(&format!(
+            "SELECT COUNT(*) FROM promos p WHERE p.company_id = $2 AND {}",
+            *LIST_CONDITIONS
+        ))
+        .bind(today)
+        .bind(company_id)
+        .bind(&filter.countries)
+        .bind(&filter.mode)
+        .bind(filter.active)
+        .bind(filter.date_from)
+        .bind(filter.date_until)
+        .bind(&filter.text)
+        .fetch_one(&self.pool)
+        .await
+    }
+
+    async fn list_company_promos(
+        &self,
+        company_id: &str,
+        filter: &PromoFilter,
+        page: &PromoPage,
+        today: NaiveDate,
+    ) -> Result<Vec<ListedPromo>, sqlx::Error> {
+        let (key, key_type) = sort_key(page.sort);
+        // Times are spelled out in RFC 3339, the form cursors carry them in.
+        let key_text = match page.sort {
+            PromoSort::CreateDate => format!(
+                r#"to_char({} AT TIME ZONE 'UTC', 'YYYY-MM-DD"T"HH24:MI:SS.US"+00:00"')"#,
+                key
+            ),
+            _ => format!("CAST({} AS TEXT)", key),
+        };
+        sqlx::query_as(&format!(
+            r#"
+            SELECT {columns}, {key_text} AS sort_key
+            FROM promos p
+            WHERE p.company_id = $2 AND {conditions}
+                AND ($11::TEXT IS NULL OR ({key}, p.promo_id) < (CAST($11 AS {key_type}), $12))
+            ORDER BY {key} DESC, p.promo_id DESC
+            LIMIT $9 OFFSET $10
+            "#,
+            columns = *PROMO_READ_ONLY_COLUMNS,
+            conditions = *LIST_CONDITIONS,
+        ))
+        .bind(today)
+        .bind(company_id)
+        .bind(&filter.countries)
+        .bind(&filter.mode)
+        .bind(filter.active)
+        .bind(filter.date_from)
+        .bind(filter.date_until)
+        .bind(&filter.text)
+        .bind(page.limit)
+        .bind(page.offset)
+        .bind(page.after.as_ref().map(|cursor| &cursor.sort_key))
+        .bind(page.after.as_ref().map(|cursor| &cursor.promo_id))
+        .fetch_all(&self.pool)
+        .await
+    }
+
+    async fn update_promo(&self, promo: &Promo) -> Result<(), sqlx::Error> {
+        sqlx::query(
+            r#"
+            UPDATE promos
+            SET description = $1, image_url = $2, target = $3, max_count = $4,
+                active_from = $5, active_until = $6, publish_at = $7
+            WHERE promo_id = $8
+            "#,
+        )
+        .bind(&promo.description)
+        .bind(&promo.image_url)
+        .bind(&promo.target)
+        .bind(promo.max_count)
+        .bind(promo.active_from)
+        .bind(promo.active_until)
+        .bind(promo.publish_at)
+        .bind(&promo.promo_id)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    async fn retrieve_promo_countries(&self, promo_id: &str) -> Result<Vec<Country>, sqlx::Error> {
+        sqlx::query_as(
+            r#"
+            SELECT country AS name, activate_count FROM promo_countries
+            WHERE promo_id = $1
+            ORDER BY country
+            "#,
+        )
+        .bind(promo_id)
+        .fetch_all(&self.pool)
+        .await
+    }
+
+    async fn promo_exists(&self, promo_id: &str) -> Result<bool, sqlx::Error> {
+        sqlx::query_scalar(
+            r#"
+            SELECT EXISTS (
+                SELECT 1 FROM promos WHERE promo_id = $1 AND published_at IS NOT NULL
+            )
+            "#,
+        )
+        .bind(promo_id)
+        .fetch_one(&self.pool)
+        .await
+    }
+
+    async fn publish_promo(&self, promo_id: &str, now: DateTime<Utc>) -> Result<bool, sqlx::Error> {
+        let mut tx = self.pool.begin().await?;
+
+        let published = sqlx::query(
+            r#"
+            UPDATE promos SET published_at = $2
+            WHERE promo_id = $1 AND published_at IS NULL
+            "#,
+        )
+        .bind(promo_id)
+        .bind(now)
+        .execute(&mut *tx)
+        .await?
+        .rows_affected()
+            > 0;
+        if published {
+            insert_promo_event(&mut *tx, promo_id, "published", now).await?;
+        }
+
+        tx.commit().await?;
+
+        Ok(published)
+    }
+
+    async fn record_promo_event(
+        &self,
+        promo_id: &str,
+        kind: &str,
+        now: DateTime<Utc>,
+    ) -> Result<(), sqlx::Error> {
+        insert_promo_event(&self.pool, promo_id, kind, now).await
+    }
+
+    async fn retrieve_promo_events(&self, promo_id: &str) -> Result<Vec<PromoEvent>, sqlx::Error> {
+        sqlx::query_as(
+            r#"
+            SELECT id, kind, occurred_at FROM promo_events
+            WHERE promo_id = $1
+            ORDER BY id
+            "#,
+        )
+        .bind(promo_id)
+        .fetch_all(&self.pool)
+        .await
+    }
+
+    async fn count_feed(
+        &self,
+        user_id: &str,
+        filter: &FeedFilter,
+        today: NaiveDate,
+    ) -> Result<i64, sqlx::Error> {
+        sqlx::query_scalar(&format!(
+            r#"
+            SELECT COUNT(*) FROM promos p
+            JOIN users u ON u.id = $2
+            WHERE {}
+            "#,
+            *FEED_CONDITIONS
+        ))
+        .bind(today)
+        .bind(user_id)
+        .bind(filter.category.as_deref())
+        .bind(filter.active)
+        .fetch_one(&self.pool)
+        .await
+    }
+
+    async fn retrieve_feed(
+        &self,
+        user_id: &str,
+        filter: &FeedFilter,
+        today: NaiveDate,
+        limit: i64,
+        offset: i64,
+    ) -> Result<Vec<PromoForUser>, sqlx::Error> {
+        sqlx::query_as(&format!(
+            r#"
+            {}
+            JOIN users u ON u.id = $2
+            WHERE {}
+            ORDER BY p.create_date DESC, p.promo_id
+            LIMIT $5 OFFSET $6
+            "#,
+            *PROMO_FOR_USER_SELECT, *FEED_CONDITIONS
+        ))
+        .bind(today)
+        .bind(user_id)
+        .bind(filter.category.as_deref())
+        .bind(filter.active)
+        .bind(limit)
+        .bind(offset)
+        .fetch_all(&self.pool)
+        .await
+    }
+
+    async fn count_search(
+        &self,
+        user_id: &str,
+        text: &str,
+        filter: &FeedFilter,
+        today: NaiveDate,
+    ) -> Result<i64, sqlx::Error> {
+        sqlx::query_scalar(&format!(
+            r#"
+            SELECT COUNT(*) FROM promos p
+            JOIN users u ON u.id = $2
+            WHERE p.search_vector @@ websearch_to_tsquery('russian', $5) AND {}
+            "#,
+            *FEED_CONDITIONS
+        ))
+        .bind(today)
+        .bind(user_id)
+        .bind(filter.category.as_deref())
+        .bind(filter.active)
+        .bind(text)
+        .fetch_one(&self.pool)
+        .await
+    }
+
+    async fn search_promos(
+        &self,
+        user_id: &str,
+        text: &str,
+        filter: &FeedFilter,
+        today: NaiveDate,
+        limit: i64,
+        offset: i64,
+    ) -> Result<Vec<PromoSearchHit>, sqlx::Error> {
+        sqlx::query_as(&format!(
+            r#"
+            SELECT {}, ts_headline('russian', p.description, q, $8) AS snippet
+            FROM promos p
+            JOIN users u ON u.id = $2
+            CROSS JOIN websearch_to_tsquery('russian', $5) AS q
+            WHERE p.search_vector @@ q AND {}
+            ORDER BY {} DESC, p.create_date DESC, p.promo_id
+            LIMIT $6 OFFSET $7
+            "#,
+            *PROMO_FOR_USER_COLUMNS, *FEED_CONDITIONS, SEARCH_SCORE
+        ))
+        .bind(today)
+        .bind(user_id)
+        .bind(filter.category.as_deref())
+        .bind(filter.active)
+        .bind(text)
+        .bind(limit)
+        .bind(offset)
+        .bind(format!(
+            "StartSel={}, StopSel={}, MinWords=15, MaxWords=35",
+            MATCH_START, MATCH_END
+        ))
+        .fetch_all(&self.pool)
+        .await
+    }
+
+    async fn retrieve_promo_for_user(
+        &self,
+        user_id: &str,
+        promo_id: &str,
+        today: NaiveDate,
+    ) -> Result<Option<PromoForUser>, sqlx::Error> {
+        sqlx::query_as(&format!(
+            "{} WHERE p.promo_id = $3 AND p.published_at IS NOT NULL",
+            *PROMO_FOR_USER_SELECT
+        ))
+        .bind(today)
+        .bind(user_id)
+        .bind(promo_id)
+        .fetch_optional(&self.pool)
+        .await
+    }
+
+    async fn add_like(&self, promo_id: &str, user_id: &str) -> Result<(), sqlx::Error> {
+        sqlx::query(
+            r#"
+            INSERT INTO promo_likes (promo_id, user_id)
+            VALUES ($1, $2)
+            ON CONFLICT DO NOTHING
+            "#,
+        )
+        .bind(promo_id)
+        .bind(user_id)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    async fn remove_like(&self, promo_id: &str, user_id: &str) -> Result<(), sqlx::Error> {
+        sqlx::query(
+            r#"
+            DELETE FROM promo_likes
+            WHERE promo_id = $1 AND user_id = $2
+            "#,
+        )
+        .bind(promo_id)
+        .bind(user_id)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,99 @@
+use super::PgRepository;
+use crate::{repository::SignInFailureRepository, sign_in_attempts::SignInFailures};
+use chrono::{DateTime, Utc};
+
+impl SignInFailureRepository for PgRepository {
+    async fn reserve_sign_in_attempt(
+        &self,
+        attempt_key: &str,
+        now: DateTime<Utc>,
+        reset_before: DateTime<Utc>,
+        locked_until: impl Fn(&SignInFailures) -> Option<DateTime<Utc>> + Send,
+    ) -> Result<Option<DateTime<Utc>>, sqlx::Error> {
+        let mut tx = self.pool.begin().await?;
+
+        // The first statement locks the row, so concurrent attempts under the
+        // same key see each other's failures.
+        let failures: SignInFailures = sqlx::query_as(
+            r#"
+            INSERT INTO sign_in_failures (attempt_key, failures, last_failed_at)
+            VALUES ($1, 0, $2)
+            ON CONFLICT (attempt_key) DO UPDATE
+            SET failures = CASE
+                    WHEN sign_in_failures.last_failed_at < $3 THEN 0
+                    ELSE sign_in_failures.failures
+                END
+            RETURNING failures, last_failed_at
+            "#,
+        )
+        .bind(attempt_key)
+        .bind(now)
+        .bind(reset_before)
+        .fetch_one(&mut *tx)
+        .await?;
+
+        if let Some(locked_until) = locked_until(&failures).filter(|until| *until > now) {
+            tx.commit().await?;
+            return Ok(Some(locked_until));
+        }
+
+        sqlx::query(
+            r#"
+            UPDATE sign_in_failures
+            SET failures = failures + 1, last_failed_at = $2
+            WHERE attempt_key = $1
+            "#,
+        )
+        .bind(attempt_key)
+        .bind(now)
+        .execute(&mut *tx)
+        .await?;
+        tx.commit().await?;
+
+        Ok(None)
+    }
+
+    async fn release_sign_in_attempt(&self, attempt_key: &str) -> Result<(), sqlx::Error> {
+        sqlx::query(
+            r#"
+            UPDATE sign_in_failures
+            SET failures = failures - 1
+            WHERE attempt_key = $1 AND failures > 0
+            "#,
+        )
+        .bind(attempt_key)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    async fn clear_sign_in_failures(&self, attempt_key: &str) -> Result<(), sqlx::Error> {
+        sqlx::query(
+            r#"
+            DELETE FROM sign_in_failures WHERE attempt_key = $1
+            "#,
+        )
+        .bind(attempt_key)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    async fn delete_stale_sign_in_failures(
+        &self,
+        reset_before: DateTime<Utc>,
+    ) -> Result<(), sqlx::Error> {
+        sqlx::query(
+            r#"
+            DELETE FROM sign_in_failures WHERE last_failed_at < $1
+            "#,
+        )
+        .bind(reset_before)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,152 @@
+use super::PgRepository;
+use crate::repository::TokenRepository;
+use chrono::{DateTime, Utc};
+
+impl TokenRepository for PgRepository {
+    async fn retrieve_revoked_tokens(
+        &self,
+        now: DateTime<Utc>,
+    ) -> Result<Vec<(String, DateTime<Utc>)>, sqlx::Error> {
+        sqlx::query_as(
+            r#"
+            SELECT jti, expires_at FROM revoked_tokens WHERE expires_at > $1
+            "#,
+        )
+        .bind(now)
+        .fetch_all(&self.pool)
+        .await
+    }
+
+    async fn replace_issued_tokens(
+        &self,
+        subject_id: &str,
+        jti: &str,
+        expires_at: DateTime<Utc>,
+        now: DateTime<Utc>,
+    ) -> Result<Vec<(String, DateTime<Utc>)>, sqlx::Error> {
+        let mut tx = self.pool.begin().await?;
+
+        let revoked = sqlx::query_as(
+            r#"
+            INSERT INTO revoked_tokens (jti, expires_at)
+            SELECT jti, expires_at FROM issued_tokens
+            WHERE subject_id = $1 AND expires_at > $2
+            ON CONFLICT (jti) DO NOTHING
+            RETURNING jti, expires_at
+            "#,
+        )
+        .bind(subject_id)
+        .bind(now)
+        .fetch_all(&mut *tx)
+        .await?;
+
+        sqlx::query(
+            r#"
+            DELETE FROM issued_tokens WHERE subject_id = $1
+            "#,
+        )
+        .bind(subject_id)
+        .execute(&mut *tx)
+        .await?;
+
+        sqlx::query(
+            r#"
+            INSERT INTO issued_tokens (jti, subject_id, expires_at)
+            VALUES ($1, $2, $3)
+            "#,
+        )
+        .bind(jti)
+        .bind(subject_id)
+        .bind(expires_at)
+        .execute(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+
+        Ok(revoked)
+    }
+
+    async fn revoke_issued_tokens(
+        &self,
+        subject_id: &str,
+        now: DateTime<Utc>,
+    ) -> Result<Vec<(String, DateTime<Utc>)>, sqlx::Error> {
+        let mut tx = self.pool.begin().await?;
+
+        let revoked = sqlx::query_as(
+            r#"
+            INSERT INTO revoked_tokens (jti, expires_at)
+            SELECT jti, expires_at FROM issued_tokens
+            WHERE subject_id = $1 AND expires_at > $2
+            ON CONFLICT (jti) DO NOTHING
+            RETURNING jti, expires_at
+            "#,
+        )
+        .bind(subject_id)
+        .bind(now)
+        .fetch_all(&mut *tx)
+        .await?;
+
+        sqlx::query(
+            r#"
+            DELETE FROM issued_tokens WHERE subject_id = $1
+            "#,
+        )
+        .bind(subject_id)
+        .execute(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+
+        Ok(revoked)
+    }
+
+    async fn revoke_token(&self, jti: &str, expires_at: DateTime<Utc>) -> Result<(), sqlx::Error> {
+        let mut tx = self.pool.begin().await?;
+
+        sqlx::query(
+            r#"
+            INSERT INTO revoked_tokens (jti, expires_at)
+            VALUES ($1, $2)
+            ON CONFLICT (jti) DO NOTHING
+            "#,
+        )
+        .bind(jti)
+        .bind(expires_at)
+        .execute(&mut *tx)
+        .await?;
+
+        sqlx::query(
+            r#"
+            DELETE FROM issued_tokens WHERE jti = $1
+            "#,
+        )
+        .bind(jti)
+        .execute(&mut *tx)
+        .await?;
+
+        tx.commit().await
+    }
+
+    async fn delete_expired_tokens(&self, now: DateTime<Utc>) -> Result<(), sqlx::Error> {
+        sqlx::query(
+            r#"
+            DELETE FROM revoked_tokens WHERE expires_at <= $1
+            "#,
+        )
+        .bind(now)
+        .execute(&self.pool)
+        .await?;
+
+        sqlx::query(
+            r#"
+            DELETE FROM issued_tokens WHERE expires_at <= $1
+            "#,
+        )
+        .bind(now)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+}
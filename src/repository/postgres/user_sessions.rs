@@ -0,0 +1,158 @@
+use super::PgRepository;
+use crate::{repository::UserSessionRepository, sessions::UserSession};
+use chrono::{DateTime, Utc};
+
+impl UserSessionRepository for PgRepository {
+    async fn create_user_session(&self, session: &UserSession) -> Result<(), sqlx::Error> {
+        sqlx::query(
+            r#"
+            INSERT INTO user_sessions (
+                id, user_id, access_token_id, ip_address, user_agent, device,
+                created_at, last_seen_at, expires_at
+            )
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)
+            "#,
+        )
+        .bind(&session.id)
+        .bind(&session.user_id)
+        .bind(&session.access_token_id)
+        .bind(&session.ip_address)
+        .bind(&session.user_agent)
+        .bind(&session.device)
+        .bind(session.created_at)
+        .bind(session.last_seen_at)
+        .bind(session.expires_at)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    async fn retrieve_user_session(&self, id: &str) -> Result<Option<UserSession>, sqlx::Error> {
+        sqlx::query_as(
+            r#"
+            SELECT * FROM user_sessions WHERE id = $1
+            "#,
+        )
+        .bind(id)
+        .fetch_optional(&self.pool)
+        .await
+    }
+
+    async fn retrieve_user_sessions(
+        &self,
+        user_id: &str,
+        now: DateTime<Utc>,
+    ) -> Result<Vec<UserSession>, sqlx::Error> {
+        sqlx::query_as(
+            r#"
+            SELECT * FROM user_sessions
+            WHERE user_id = $1 AND revoked_at IS NULL AND expires_at > $2
+            ORDER BY last_seen_at DESC
+            "#,
+        )
+        .bind(user_id)
+        .bind(now)
+        .fetch_all(&self.pool)
+        .await
+    }
+
+    async fn touch_user_session(&self, id: &str, now: DateTime<Utc>) -> Result<(), sqlx::Error> {
+        sqlx::query(
+            r#"
+            UPDATE user_sessions SET last_seen_at = $1 WHERE id = $2
+            "#,
+        )
+        .bind(now)
+        .bind(id)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    async fn refresh_user_session(&self, session: &UserSession) -> Result<bool, sqlx::Error> {
+        let result = sqlx::query(
+            r#"
+            UPDATE user_sessions
+            SET access_token_id = $1, ip_address = $2, user_agent = $3, device = $4,
+                last_seen_at = $5, expires_at = $6
+            WHERE id = $7 AND revoked_at IS NULL
+            "#,
+        )
+        .bind(&session.access_token_id)
+        .bind(&session.ip_address)
+        .bind(&session.user_agent)
+        .bind(&session.device)
+        .bind(session.last_seen_at)
+        .bind(session.expires_at)
+        .bind(&session.id)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.rows_affected() == 1)
+    }
+
+    async fn revoke_user_session(
+        &self,
+        user_id: &str,
+        id: &str,
+        now: DateTime<Utc>,
+    ) -> Result<bool, sqlx::Error> {
+        let result = sqlx::query(
+            r#"
+            UPDATE user_sessions
+            SET revoked_at = $1
+            WHERE id = $2 AND user_id = $3 AND revoked_at IS NULL AND expires_at > $1
+            "#,
+        )
+        .bind(now)
+        .bind(id)
+        .bind(user_id)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.rows_affected() == 1)
+    }
+
+    async fn revoke_user_sessions(
+        &self,
+        user_id: &str,
+        keep_id: Option<&str>,
+        now: DateTime<Utc>,
+    ) -> Result<Vec<String>, sqlx::Error> {
+        sqlx::query_scalar(
+            r#"
+            UPDATE user_sessions
+            SET revoked_at = $1
+            WHERE user_id = $2 AND revoked_at IS NULL AND expires_at > $1
+                AND ($3 IS NULL OR id <> $3)
+            RETURNING id
+            "#,
+        )
+        .bind(now)
+        .bind(user_id)
+        .bind(keep_id)
+        .fetch_all(&self.pool)
+        .await
+    }
+
+    async fn delete_stale_user_sessions(
+        &self,
+        user_id: &str,
+        now: DateTime<Utc>,
+    ) -> Result<(), sqlx::Error> {
+        sqlx::query(
+            r#"
+            DELETE FROM user_sessions
+            WHERE user_id = $1 AND (revoked_at IS NOT NULL OR expires_at <= $2)
+            "#,
+        )
+        .bind(user_id)
+        .bind(now)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,54 @@
+use super::SqliteRepository;
+use crate::{
+    business::auth::{Company, CompanyMember},
+    repository::CompanyRepository,
+};
+
+impl CompanyRepository for SqliteRepository {
+    async fn retrieve_company_by_id(&self, id: &str) -> Result<Option<Company>, sqlx::Error> {
+        sqlx::query_as(
+            r#"
+            SELECT * FROM companies WHERE id = $1
+            "#,
+        )
+        .bind(id)
+        .fetch_optional(&self.pool)
+        .await
+    }
+
+    async fn create_company(
+        &self,
+        company: &Company,
+        owner: &CompanyMember,
+    ) -> Result<(), sqlx::Error> {
+        let mut tx = self.pool.begin().await?;
+
+        sqlx::query(
+            r#"
+            INSERT INTO companies (id, name)
+            VALUES ($1, $2)
+            "#,
+        )
+        .bind(&company.id)
+        .bind(&company.name)
+        .execute(&mut *tx)
+        .await?;
+
+        sqlx::query(
+            r#"
+            INSERT INTO company_members (id, company_id, name, email, password_hash, role)
+            VALUES ($1, $2, $3, $4, $5, $6)
+            "#,
+        )
+        .bind(&owner.id)
+        .bind(&owner.company_id)
+        .bind(&owner.name)
+        .bind(&owner.email)
+        .bind(&owner.password_hash)
+        .bind(&owner.role)
+        .execute(&mut *tx)
+        .await?;
+
+        tx.commit().await
+    }
+}
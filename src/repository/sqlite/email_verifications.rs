@@ -0,0 +1,91 @@
+use super::SqliteRepository;
+use crate::{email_verification::VerificationToken, repository::EmailVerificationRepository};
+use chrono::{DateTime, Utc};
+
+impl EmailVerificationRepository for SqliteRepository {
+    async fn create_verification_token(
+        &self,
+        verification_token: &VerificationToken,
+    ) -> Result<(), sqlx::Error> {
+        sqlx::query(
+            r#"
+            INSERT INTO email_verification_tokens (
+                id, audience, subject_id, token_hash, created_at, expires_at
+            )
+            VALUES ($1, $2, $3, $4, $5, $6)
+            "#,
+        )
+        .bind(&verification_token.id)
+        .bind(&verification_token.audience)
+        .bind(&verification_token.subject_id)
+        .bind(&verification_token.token_hash)
+        .bind(verification_token.created_at)
+        .bind(verification_token.expires_at)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    async fn claim_verification_send(
+        &self,
+        audience: &str,
+        subject_id: &str,
+        now: DateTime<Utc>,
+        last_sent_before: DateTime<Utc>,
+    ) -> Result<Option<DateTime<Utc>>, sqlx::Error> {
+        let claimed: Option<DateTime<Utc>> = sqlx::query_scalar(
+            r#"
+            INSERT INTO email_verification_sends (audience, subject_id, sent_at)
+            VALUES ($1, $2, $3)
+            ON CONFLICT (audience, subject_id) DO UPDATE
+            SET sent_at = excluded.sent_at
+            WHERE email_verification_sends.sent_at <= $4
+            RETURNING sent_at
+            "#,
+        )
+        .bind(audience)
+        .bind(subject_id)
+        .bind(now)
+        .bind(last_sent_before)
+        .fetch_optional(&self.pool)
+        .await?;
+        if claimed.is_some() {
+            return Ok(None);
+        }
+
+        // Only tells the caller how long to wait, so it needn't be atomic
+        // with the claim.
+        sqlx::query_scalar(
+            r#"
+            SELECT sent_at FROM email_verification_sends
+            WHERE audience = $1 AND subject_id = $2
+            "#,
+        )
+        .bind(audience)
+        .bind(subject_id)
+        .fetch_optional(&self.pool)
+        .await
+    }
+
+    async fn use_verification_token(
+        &self,
+        audience: &str,
+        token_hash: &str,
+        now: DateTime<Utc>,
+    ) -> Result<Option<String>, sqlx::Error> {
+        sqlx::query_scalar(
+            r#"
+            UPDATE email_verification_tokens
+            SET used_at = $1
+            WHERE audience = $2 AND token_hash = $3 AND used_at IS NULL AND expires_at > $1
+            RETURNING subject_id
+            "#,
+        )
+        .bind(now)
+        .bind(audience)
+        .bind(token_hash)
+        .fetch_optional(&self.pool)
+        .await
+    }
+}
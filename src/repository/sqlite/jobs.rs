@@ -0,0 +1,132 @@
+use super::SqliteRepository;
+use crate::{
+    repository::JobRepository,
+    scheduler::{JobKind, ScheduledJob},
+};
+use chrono::{DateTime, Utc};
+
+impl JobRepository for SqliteRepository {
+    async fn schedule_job(
+        &self,
+        kind: JobKind,
+        promo_id: &str,
+        run_at: DateTime<Utc>,
+        now: DateTime<Utc>,
+    ) -> Result<(), sqlx::Error> {
+        let mut tx = self.pool.begin().await?;
+
+        sqlx::query(
+            r#"
+            DELETE FROM scheduled_jobs
+            WHERE promo_id = $1 AND kind = $2 AND completed_at IS NULL
+            "#,
+        )
+        .bind(promo_id)
+        .bind(kind.name())
+        .execute(&mut *tx)
+        .await?;
+
+        sqlx::query(
+            r#"
+            INSERT INTO scheduled_jobs (kind, promo_id, run_at, created_at)
+            VALUES ($1, $2, $3, $4)
+            "#,
+        )
+        .bind(kind.name())
+        .bind(promo_id)
+        .bind(run_at)
+        .bind(now)
+        .execute(&mut *tx)
+        .await?;
+
+        tx.commit().await
+    }
+
+    async fn cancel_job(&self, kind: JobKind, promo_id: &str) -> Result<(), sqlx::Error> {
+        sqlx::query(
+            r#"
+            DELETE FROM scheduled_jobs
+            WHERE promo_id = $1 AND kind = $2 AND completed_at IS NULL
+            "#,
+        )
+        .bind(promo_id)
+        .bind(kind.name())
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    async fn claim_due_jobs(
+        &self,
+        now: DateTime<Utc>,
+        locked_until: DateTime<Utc>,
+        limit: i64,
+    ) -> Result<Vec<ScheduledJob>, sqlx::Error> {
+        // Writes are serialized by SQLite, so no other run can claim the same
+        // jobs between the select and the update.
+        sqlx::query_as(
+            r#"
+            UPDATE scheduled_jobs
+            SET attempts = attempts + 1, locked_until = $2
+            WHERE id IN (
+                SELECT id FROM scheduled_jobs
+                WHERE completed_at IS NULL AND run_at <= $1
+                    AND (locked_until IS NULL OR locked_until <= $1)
+                ORDER BY run_at, id
+                LIMIT $3
+            )
+            RETURNING id, kind, promo_id, attempts
+            "#,
+        )
+        .bind(now)
+        .bind(locked_until)
+        .bind(limit)
+        .fetch_all(&self.pool)
+        .await
+    }
+
+    async fn complete_job(
+        &self,
+        id: i64,
+        now: DateTime<Utc>,
+        error: Option<&str>,
+    ) -> Result<(), sqlx::Error> {
+        sqlx::query(
+            r#"
+            UPDATE scheduled_jobs
+            SET completed_at = $2, locked_until = NULL, last_error = $3
+            WHERE id = $1
+            "#,
+        )
+        .bind(id)
+        .bind(now)
+        .bind(error)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    async fn retry_job(
+        &self,
+        id: i64,
+        run_at: DateTime<Utc>,
+        error: &str,
+    ) -> Result<(), sqlx::Error> {
+        sqlx::query(
+            r#"
+            UPDATE scheduled_jobs
+            SET run_at = $2, locked_until = NULL, last_error = $3
+            WHERE id = $1
+            "#,
+        )
+        .bind(id)
+        .bind(run_at)
+        .bind(error)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+}
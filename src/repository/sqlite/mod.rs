@@ -0,0 +1,46 @@
+use sqlx::{
+    sqlite::{SqliteConnectOptions, SqliteJournalMode, SqlitePoolOptions},
+    SqlitePool,
+};
+use std::str::FromStr;
+
+mod activations;
+mod api_keys;
+mod comments;
+mod companies;
+mod email_verifications;
+mod jobs;
+mod members;
+mod password_resets;
+mod promos;
+mod refresh_tokens;
+mod sign_in_failures;
+mod tokens;
+mod two_factor;
+mod user_sessions;
+mod users;
+mod verdicts;
+
+#[derive(Clone)]
+pub struct SqliteRepository {
+    pool: SqlitePool,
+}
+
+impl SqliteRepository {
+    pub async fn connect(database_url: &str) -> Result<Self, sqlx::Error> {
+        let options = SqliteConnectOptions::from_str(database_url)?
+            .create_if_missing(true)
+            .foreign_keys(true)
+            .journal_mode(SqliteJournalMode::Wal)
+            .collation("unicode_nocase", |a, b| {
+                a.to_lowercase().cmp(&b.to_lowercase())
+            });
+        let pool = SqlitePoolOptions::new()
+            .max_connections(5)
+            .connect_with(options)
+            .await?;
+        sqlx::migrate!("migrations/sqlite").run(&pool).await?;
+
+        Ok(SqliteRepository { pool })
+    }
+}
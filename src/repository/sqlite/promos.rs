@@ -0,0 +1,571 @@
+use super::SqliteRepository;
+use crate::{
+    business::promo::{
+        events::PromoEvent,
+        list::{ListedPromo, PromoFilter, PromoPage, PromoSort},
+        Country, Promo, PromoForUser, PromoReadOnly,
+    },
+    repository::PromoRepository,
+    user::{
+        feed::FeedFilter,
+        promo::search::{PromoSearchHit, MATCH_END, MATCH_START},
+    },
+};
+use chrono::{DateTime, NaiveDate, Utc};
+use sqlx::{types::Json, Executor, Sqlite};
+use std::sync::LazyLock;
+
+// Whether a promo can be activated on the day bound as `$1`: it has been
+// published, the day lies within `[active_from; active_until]` and codes are
+// left, which for COMMON promos means activations stay below `max_count`.
+const PROMO_ACTIVE: &str = r#"
+    (
+        p.published_at IS NOT NULL
+        AND (p.active_from IS NULL OR p.active_from <= $1)
+        AND (p.active_until IS NULL OR p.active_until >= $1)
+        AND CASE WHEN p.mode = 'UNIQUE' THEN EXISTS (
+            SELECT 1 FROM promo_unique_codes uc
+            WHERE uc.promo_id = p.promo_id AND uc.activated_at IS NULL
+        ) ELSE p.used_count < p.max_count END
+    )
+"#;
+
+// Expects the current day as `$1`.
+static PROMO_READ_ONLY_COLUMNS: LazyLock<String> = LazyLock::new(|| {
+    format!(
+        r#"
+        p.description, p.image_url, p.target, p.max_count, p.active_from, p.active_until,
+        p.mode, p.promo_common, p.promo_id, p.company_id, p.company_name, p.used_count,
+        p.publish_at, p.published_at, {} AS active,
+        CASE WHEN p.mode = 'UNIQUE' THEN (
+            SELECT json_group_array(u.code ORDER BY u.position)
+            FROM promo_unique_codes u
+            WHERE u.promo_id = p.promo_id
+        ) END AS promo_unique,
+        CAST((SELECT COUNT(*) FROM promo_likes l WHERE l.promo_id = p.promo_id) AS INTEGER) AS like_count
+"#,
+        PROMO_ACTIVE
+    )
+});
+
+static PROMO_READ_ONLY_SELECT: LazyLock<String> =
+    LazyLock::new(|| format!("SELECT {} FROM promos p", *PROMO_READ_ONLY_COLUMNS));
+
+// Expects the current day as `$1` and the requesting user's id as `$2`.
+static PROMO_FOR_USER_COLUMNS: LazyLock<String> = LazyLock::new(|| {
+    format!(
+        r#"
+        p.promo_id, p.company_id, p.company_name, p.description, p.image_url,
+        {} AS active,
+        EXISTS (
+            SELECT 1 FROM promo_activations a WHERE a.promo_id = p.promo_id AND a.user_id = $2
+        ) AS is_activated_by_user,
+        CAST((SELECT COUNT(*) FROM promo_likes l WHERE l.promo_id = p.promo_id) AS INTEGER) AS like_count,
+        EXISTS (
+            SELECT 1 FROM promo_likes l WHERE l.promo_id = p.promo_id AND l.user_id = $2
+        ) AS is_liked_by_user,
+        CAST((SELECT COUNT(*) FROM promo_comments c WHERE c.promo_id = p.promo_id) AS INTEGER) AS comment_count
+"#,
+        PROMO_ACTIVE
+    )
+});
+
+pub(super) static PROMO_FOR_USER_SELECT: LazyLock<String> =
+    LazyLock::new(|| format!("SELECT {} FROM promos p", *PROMO_FOR_USER_COLUMNS));
+
+// Applies a company listing filter, with the current day as `$1`, then the
+// countries (a JSON array), mode, activity, date range and text as `$3` to
+// `$8`.
+static LIST_CONDITIONS: LazyLock<String> = LazyLock::new(|| {
+    format!(
+        r#"
+    (json_array_length($3) = 0 OR json_extract(p.target, '$.country') IS NULL
+        OR LOWER(json_extract(p.target, '$.country')) IN (SELECT value FROM json_each($3)))
+    AND ($4 IS NULL OR p.mode = $4)
+    AND ($5 IS NULL OR {} = $5)
+    AND ($6 IS NULL OR p.active_until IS NULL OR p.active_until >= $6)
+    AND ($7 IS NULL OR p.active_from IS NULL OR p.active_from <= $7)
+    AND ($8 IS NULL OR instr(LOWER(p.description), LOWER($8)) > 0)
+"#,
+        PROMO_ACTIVE
+    )
+});
+
+// The sort key of a listing and the type to read a cursor's key back as.
+fn sort_key(sort: PromoSort) -> (&'static str, &'static str) {
+    match sort {
+        PromoSort::CreateDate => ("p.create_date", "TEXT"),
+        PromoSort::ActiveFrom => ("COALESCE(p.active_from, '0001-01-01')", "TEXT"),
+        PromoSort::ActiveUntil => ("COALESCE(p.active_until, '9999-12-31')", "TEXT"),
+        PromoSort::LikeCount => (
+            "(SELECT COUNT(*) FROM promo_likes l WHERE l.promo_id = p.promo_id)",
+            "INTEGER",
+        ),
+        PromoSort::UsedCount => ("p.used_count", "INTEGER"),
+    }
+}
+
+// Matches promos against the target settings of the user joined as `u` and
+// applies the feed filter, with the category as `$3` and activity as `$4`.
+// Categories take no part in target matching, and unpublished promos never
+// match. SQLite's `LOWER` only folds ASCII, so categories are compared with
+// the `unicode_nocase` collation.
+static FEED_CONDITIONS: LazyLock<String> = LazyLock::new(|| {
+    format!(
+        r#"
+    p.published_at IS NOT NULL
+    AND (json_extract(p.target, '$.age_from') IS NULL
+        OR json_extract(p.target, '$.age_from') <= json_extract(u.other, '$.age'))
+    AND (json_extract(p.target, '$.age_until') IS NULL
+        OR json_extract(p.target, '$.age_until') >= json_extract(u.other, '$.age'))
+    AND (json_extract(p.target, '$.country') IS NULL
+        OR LOWER(json_extract(p.target, '$.country')) = LOWER(json_extract(u.other, '$.country')))
+    AND ($3 IS NULL OR EXISTS (
+        SELECT 1 FROM json_each(p.target, '$.categories') AS c
+        WHERE c.value = $3 COLLATE unicode_nocase
+    ))
+    AND ($4 IS NULL OR {} = $4)
+"#,
+        PROMO_ACTIVE
+    )
+});
+
+// How well the promo matches the search query, raised by up to a half for
+// promos with many likes. `bm25` is negative, lower meaning more relevant.
+const SEARCH_SCORE: &str = r#"
+    -bm25(promo_search, 0.0, 2.0, 1.0) * (
+        1 + 0.5 * (SELECT COUNT(*) FROM promo_likes l WHERE l.promo_id = p.promo_id)
+            / ((SELECT COUNT(*) FROM promo_likes l WHERE l.promo_id = p.promo_id) + 10.0)
+    )
+"#;
+
+// FTS5 has a query syntax of its own. Quoting each word keeps user input from
+// being read as operators, and matching words as prefixes makes up a little
+// for the tokenizer only stemming English.
+fn fts_query(text: &str) -> String {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(|word| format!("\"{}\"*", word))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+pub(super) async fn insert_promo_event<'e>(
+    executor: impl Executor<'e, Database = Sqlite>,
+    promo_id: &str,
+    kind: &str,
+    now: DateTime<Utc>,
+) -> Result<(), sqlx::Error> {
+    sqlx::query(
+        r#"
+        INSERT INTO promo_events (promo_id, kind, occurred_at)
+        VALUES ($1, $2, $3)
+        "#,
+    )
+    .bind(promo_id)
+    .bind(kind)
+    .bind(now)
+    .execute(executor)
+    .await?;
+
+    Ok(())
+}
+
+impl PromoRepository for SqliteRepository {
+    async fn create_promo(
+        &self,
+        promo: &Promo,
+        promo_unique: Option<&[String]>,
+    ) -> Result<(), sqlx::Error> {
+        let mut tx = self.pool.begin().await?;
+
+        sqlx::query(
+            r#"
+            INSERT INTO promos (
+                description, image_url, target, max_count, create_date, active_from, active_until,
+                mode, promo_common, promo_id, company_id, company_name, used_count,
+                publish_at, published_at
+            )
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15)
+            "#,
+        )
+        .bind(&promo.description)
+        .bind(&promo.image_url)
+        .bind(&promo.target)
+        .bind(promo.max_count)
+        .bind(promo.create_date)
+        .bind(promo.active_from)
+        .bind(promo.active_until)
+        .bind(&promo.mode)
+        .bind(&promo.promo_common)
+        .bind(&promo.promo_id)
+        .bind(&promo.company_id)
+        .bind(&promo.company_name)
+        .bind(promo.used_count)
+        .bind(promo.publish_at)
+        .bind(promo.published_at)
+        .execute(&mut *tx)
+        .await?;
+
+        if let Some(published_at) = promo.published_at {
+            insert_promo_event(&mut *tx, &promo.promo_id, "published", published_at).await?;
+        }
+
+        if let Some(promo_unique) = promo_unique {
+            sqlx::query(
+                r#"
+                INSERT INTO promo_unique_codes (promo_id, code, position)
+                SELECT $1, value, key + 1
+                FROM json_each($2)
+                "#,
+            )
+            .bind(&promo.promo_id)
+            .bind(Json(promo_unique))
+            .execute(&mut *tx)
+            .await?;
+        }
+
+        tx.commit().await
+    }
+
+    async fn retrieve_promo(
+        &self,
+        promo_id: &str,
+        today: NaiveDate,
+    ) -> Result<Option<Promo>, sqlx::Error> {
+        sqlx::query_as(&format!(
+            "SELECT p.*, {} AS active FROM promos p WHERE p.promo_id = $2",
+            PROMO_ACTIVE
+        ))
+        .bind(today)
+        .bind(promo_id)
+        .fetch_optional(&self.pool)
+        .await
+    }
+
+    async fn retrieve_promo_read_only(
+        &self,
+        promo_id: &str,
+        today: NaiveDate,
+    ) -> Result<Option<PromoReadOnly>, sqlx::Error> {
+        sqlx::query_as(&format!(
+            "{} WHERE p.promo_id = $2",
+            *PROMO_READ_ONLY_SELECT
+        ))
+        .bind(today)
+        .bind(promo_id)
+        .fetch_optional(&self.pool)
+        .await
+    }
+
+    async fn count_company_promos(
+        &self,
+        company_id: &str,
+        filter: &PromoFilter,
+        today: NaiveDate,
+    ) -> Result<i64, sqlx::Error> {
+        sqlx::query_scalar(&format!(
+            "SELECT COUNT(*) FROM promos p WHERE p.company_id = $2 AND {}",
+            *LIST_CONDITIONS
+        ))
+        .bind(today)
+        .bind(company_id)
+        .bind(Json(&filter.countries))
+        .bind(&filter.mode)
+        .bind(filter.active)
+        .bind(filter.date_from)
+        .bind(filter.date_until)
+        .bind(&filter.text)
+        .fetch_one(&self.pool)
+        .await
+    }
+
+    async fn list_company_promos(
+        &self,
+        company_id: &str,
+        filter: &PromoFilter,
+        page: &PromoPage,
+        today: NaiveDate,
+    ) -> Result<Vec<ListedPromo>, sqlx::Error> {
+        let (key, key_type) = sort_key(page.sort);
+        sqlx::query_as(&format!(
+            r#"
+            SELECT {columns}, CAST({key} AS TEXT) AS sort_key
+            FROM promos p
+            WHERE p.company_id = $2 AND {conditions}
+                AND ($11 IS NULL OR ({key}, p.promo_id) < (CAST($11 AS {key_type}), $12))
+            ORDER BY {key} DESC, p.promo_id DESC
+            LIMIT $9 OFFSET $10
+            "#,
+            columns = *PROMO_READ_ONLY_COLUMNS,
+            conditions = *LIST_CONDITIONS,
+        ))
+        .bind(today)
+        .bind(company_id)
+        .bind(Json(&filter.countries))
+        .bind(&filter.mode)
+        .bind(filter.active)
+        .bind(filter.date_from)
+        .bind(filter.date_until)
+        .bind(&filter.text)
+        .bind(page.limit)
+        .bind(page.offset)
+        .bind(page.after.as_ref().map(|cursor| &cursor.sort_key))
+        .bind(page.after.as_ref().map(|cursor| &cursor.promo_id))
+        .fetch_all(&self.pool)
+        .await
+    }
+
+    async fn update_promo(&self, promo: &Promo) -> Result<(), sqlx::Error> {
+        sqlx::query(
+            r#"
+            UPDATE promos
+            SET description = $1, image_url = $2, target = $3, max_count = $4,
+                active_from = $5, active_until = $6, publish_at = $7
+            WHERE promo_id = $8
+            "#,
+        )
+        .bind(&promo.description)
+        .bind(&promo.image_url)
+        .bind(&promo.target)
+        .bind(promo.max_count)
+        .bind(promo.active_from)
+        .bind(promo.active_until)
+        .bind(promo.publish_at)
+        .bind(&promo.promo_id)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    async fn retrieve_promo_countries(&self, promo_id: &str) -> Result<Vec<Country>, sqlx::Error> {
+        sqlx::query_as(
+            r#"
+            SELECT country AS name, activate_count FROM promo_countries
+            WHERE promo_id = $1
+            ORDER BY country
+            "#,
+        )
+        .bind(promo_id)
+        .fetch_all(&self.pool)
+        .await
+    }
+
+    async fn promo_exists(&self, promo_id: &str) -> Result<bool, sqlx::Error> {
+        sqlx::query_scalar(
+            r#"
+            SELECT EXISTS (
+                SELECT 1 FROM promos WHERE promo_id = $1 AND published_at IS NOT NULL
+            )
+            "#,
+        )
+        .bind(promo_id)
+        .fetch_one(&self.pool)
+        .await
+    }
+
+    async fn publish_promo(&self, promo_id: &str, now: DateTime<Utc>) -> Result<bool, sqlx::Error> {
+        let mut tx = self.pool.begin().await?;
+
+        let published = sqlx::query(
+            r#"
+            UPDATE promos SET published_at = $2
+            WHERE promo_id = $1 AND published_at IS NULL
+            "#,
+        )
+        .bind(promo_id)
+        .bind(now)
+        .execute(&mut *tx)
+        .await?
+        .rows_affected()
+            > 0;
+        if published {
+            insert_promo_event(&mut *tx, promo_id, "published", now).await?;
+        }
+
+        tx.commit().await?;
+
+        Ok(published)
+    }
+
+    async fn record_promo_event(
+        &self,
+        promo_id: &str,
+        kind: &str,
+        now: DateTime<Utc>,
+    ) -> Result<(), sqlx::Error> {
+        insert_promo_event(&self.pool, promo_id, kind, now).await
+    }
+
+    async fn retrieve_promo_events(&self, promo_id: &str) -> Result<Vec<PromoEvent>, sqlx::Error> {
+        sqlx::query_as(
+            r#"
+            SELECT id, kind, occurred_at FROM promo_events
+            WHERE promo_id = $1
+            ORDER BY id
+            "#,
+        )
+        .bind(promo_id)
+        .fetch_all(&self.pool)
+        .await
+    }
+
+    async fn count_feed(
+        &self,
+        user_id: &str,
+        filter: &FeedFilter,
+        today: NaiveDate,
+    ) -> Result<i64, sqlx::Error> {
+        sqlx::query_scalar(&format!(
+            r#"
+            SELECT COUNT(*) FROM promos p
+            JOIN users u ON u.id = $2
+            WHERE {}
+            "#,
+            *FEED_CONDITIONS
+        ))
+        .bind(today)
+        .bind(user_id)
+        .bind(filter.category.as_deref())
+        .bind(filter.active)
+        .fetch_one(&self.pool)
+        .await
+    }
+
+    async fn retrieve_feed(
+        &self,
+        user_id: &str,
+        filter: &FeedFilter,
+        today: NaiveDate,
+        limit: i64,
+        offset: i64,
+    ) -> Result<Vec<PromoForUser>, sqlx::Error> {
+        sqlx::query_as(&format!(
+            r#"
+            {}
+            JOIN users u ON u.id = $2
+            WHERE {}
+            ORDER BY p.create_date DESC, p.promo_id
+            LIMIT $5 OFFSET $6
+            "#,
+            *PROMO_FOR_USER_SELECT, *FEED_CONDITIONS
+        ))
+        .bind(today)
+        .bind(user_id)
+        .bind(filter.category.as_deref())
+        .bind(filter.active)
+        .bind(limit)
+        .bind(offset)
+        .fetch_all(&self.pool)
+        .await
+    }
+
+    async fn count_search(
+        &self,
+        user_id: &str,
+        text: &str,
+        filter: &FeedFilter,
+        today: NaiveDate,
+    ) -> Result<i64, sqlx::Error> {
+        sqlx::query_scalar(&format!(
+            r#"
+            SELECT COUNT(*) FROM promo_search
+            JOIN promos p ON p.promo_id = promo_search.promo_id
+            JOIN users u ON u.id = $2
+            WHERE promo_search MATCH $5 AND {}
+            "#,
+            *FEED_CONDITIONS
+        ))
+        .bind(today)
+        .bind(user_id)
+        .bind(filter.category.as_deref())
+        .bind(filter.active)
+        .bind(fts_query(text))
+        .fetch_one(&self.pool)
+        .await
+    }
+
+    async fn search_promos(
+        &self,
+        user_id: &str,
+        text: &str,
+        filter: &FeedFilter,
+        today: NaiveDate,
+        limit: i64,
+        offset: i64,
+    ) -> Result<Vec<PromoSearchHit>, sqlx::Error> {
+        sqlx::query_as(&format!(
+            r#"
+            SELECT {}, snippet(promo_search, 1, $8, $9, '…', 24) AS snippet
+            FROM promo_search
+            JOIN promos p ON p.promo_id = promo_search.promo_id
+            JOIN users u ON u.id = $2
+            WHERE promo_search MATCH $5 AND {}
+            ORDER BY {} DESC, p.create_date DESC, p.promo_id
+            LIMIT $6 OFFSET $7
+            "#,
+            *PROMO_FOR_USER_COLUMNS, *FEED_CONDITIONS, SEARCH_SCORE
+        ))
+        .bind(today)
+        .bind(user_id)
+        .bind(filter.category.as_deref())
+        .bind(filter.active)
+        .bind(fts_query(text))
+        .bind(limit)
+        .bind(offset)
+        .bind(MATCH_START)
+        .bind(MATCH_END)
+        .fetch_all(&self.pool)
+        .await
+    }
+
+    async fn retrieve_promo_for_user(
+        &self,
+        user_id: &str,
+        promo_id: &str,
+        today: NaiveDate,
+    ) -> Result<Option<PromoForUser>, sqlx::Error> {
+        sqlx::query_as(&format!(
+            "{} WHERE p.promo_id = $3 AND p.published_at IS NOT NULL",
+            *PROMO_FOR_USER_SELECT
+        ))
+        .bind(today)
+        .bind(user_id)
+        .bind(promo_id)
+        .fetch_optional(&self.pool)
+        .await
+    }
+
+    async fn add_like(&self, promo_id: &str, user_id: &str) -> Result<(), sqlx::Error> {
+        sqlx::query(
+            r#"
+            INSERT INTO promo_likes (promo_id, user_id, created_at)
+            VALUES ($1, $2, $3)
+            ON CONFLICT DO NOTHING
+            "#,
+        )
+        .bind(promo_id)
+        .bind(user_id)
+        .bind(Utc::now())
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    async fn remove_like(&self, promo_id: &str, user_id: &str) -> Result<(), sqlx::Error> {
+        sqlx::query(
+            r#"
+            DELETE FROM promo_likes
+            WHERE promo_id = $1 AND user_id = $2
+            "#,
+        )
+        .bind(promo_id)
+        .bind(user_id)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+}
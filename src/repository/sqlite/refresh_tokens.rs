@@ -0,0 +1,130 @@
+use super::SqliteRepository;
+use crate::{refresh::RefreshToken, repository::RefreshTokenRepository};
+use chrono::{DateTime, Utc};
+
+impl RefreshTokenRepository for SqliteRepository {
+    async fn create_refresh_token(&self, refresh_token: &RefreshToken) -> Result<(), sqlx::Error> {
+        sqlx::query(
+            r#"
+            INSERT INTO refresh_tokens (
+                id, family_id, audience, subject_id, token_hash, created_at, expires_at
+            )
+            VALUES ($1, $2, $3, $4, $5, $6, $7)
+            "#,
+        )
+        .bind(&refresh_token.id)
+        .bind(&refresh_token.family_id)
+        .bind(&refresh_token.audience)
+        .bind(&refresh_token.subject_id)
+        .bind(&refresh_token.token_hash)
+        .bind(refresh_token.created_at)
+        .bind(refresh_token.expires_at)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    async fn retrieve_refresh_token(
+        &self,
+        token_hash: &str,
+    ) -> Result<Option<RefreshToken>, sqlx::Error> {
+        sqlx::query_as(
+            r#"
+            SELECT * FROM refresh_tokens WHERE token_hash = $1
+            "#,
+        )
+        .bind(token_hash)
+        .fetch_optional(&self.pool)
+        .await
+    }
+
+    async fn rotate_refresh_token(
+        &self,
+        used_id: &str,
+        refresh_token: &RefreshToken,
+        now: DateTime<Utc>,
+    ) -> Result<bool, sqlx::Error> {
+        let mut tx = self.pool.begin().await?;
+
+        let used = sqlx::query(
+            r#"
+            UPDATE refresh_tokens
+            SET used_at = $1
+            WHERE id = $2 AND used_at IS NULL AND revoked_at IS NULL
+            "#,
+        )
+        .bind(now)
+        .bind(used_id)
+        .execute(&mut *tx)
+        .await?;
+
+        if used.rows_affected() == 0 {
+            return Ok(false);
+        }
+
+        sqlx::query(
+            r#"
+            INSERT INTO refresh_tokens (
+                id, family_id, audience, subject_id, token_hash, created_at, expires_at
+            )
+            VALUES ($1, $2, $3, $4, $5, $6, $7)
+            "#,
+        )
+        .bind(&refresh_token.id)
+        .bind(&refresh_token.family_id)
+        .bind(&refresh_token.audience)
+        .bind(&refresh_token.subject_id)
+        .bind(&refresh_token.token_hash)
+        .bind(refresh_token.created_at)
+        .bind(refresh_token.expires_at)
+        .execute(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+
+        Ok(true)
+    }
+
+    async fn revoke_refresh_token_family(
+        &self,
+        family_id: &str,
+        now: DateTime<Utc>,
+    ) -> Result<(), sqlx::Error> {
+        sqlx::query(
+            r#"
+            UPDATE refresh_tokens
+            SET revoked_at = $1
+            WHERE family_id = $2 AND revoked_at IS NULL
+            "#,
+        )
+        .bind(now)
+        .bind(family_id)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    async fn revoke_subject_refresh_tokens(
+        &self,
+        audience: &str,
+        subject_id: &str,
+        now: DateTime<Utc>,
+    ) -> Result<(), sqlx::Error> {
+        sqlx::query(
+            r#"
+            UPDATE refresh_tokens
+            SET revoked_at = $1
+            WHERE audience = $2 AND subject_id = $3 AND revoked_at IS NULL
+            "#,
+        )
+        .bind(now)
+        .bind(audience)
+        .bind(subject_id)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+}
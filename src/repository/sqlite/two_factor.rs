@@ -0,0 +1,250 @@
+use super::SqliteRepository;
+use crate::{
+    business::auth::two_factor::{MemberTotp, TwoFactorChallenge},
+    repository::TwoFactorRepository,
+};
+use chrono::{DateTime, Utc};
+use sqlx::SqliteConnection;
+use uuid::Uuid;
+
+impl TwoFactorRepository for SqliteRepository {
+    async fn retrieve_member_totp(
+        &self,
+        member_id: &str,
+    ) -> Result<Option<MemberTotp>, sqlx::Error> {
+        sqlx::query_as(
+            r#"
+            SELECT * FROM member_totp WHERE member_id = $1
+            "#,
+        )
+        .bind(member_id)
+        .fetch_optional(&self.pool)
+        .await
+    }
+
+    async fn save_pending_member_totp(&self, member_totp: &MemberTotp) -> Result<(), sqlx::Error> {
+        sqlx::query(
+            r#"
+            INSERT INTO member_totp (member_id, secret, created_at)
+            VALUES ($1, $2, $3)
+            ON CONFLICT (member_id) DO UPDATE
+            SET secret = excluded.secret, created_at = excluded.created_at
+            WHERE member_totp.enabled_at IS NULL
+            "#,
+        )
+        .bind(&member_totp.member_id)
+        .bind(&member_totp.secret)
+        .bind(member_totp.created_at)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    async fn enable_member_totp(
+        &self,
+        member_id: &str,
+        step: i64,
+        recovery_code_hashes: &[String],
+        now: DateTime<Utc>,
+    ) -> Result<bool, sqlx::Error> {
+        let mut tx = self.pool.begin().await?;
+
+        let enabled = sqlx::query(
+            r#"
+            UPDATE member_totp
+            SET enabled_at = $1, last_used_step = $2
+            WHERE member_id = $3 AND enabled_at IS NULL
+            "#,
+        )
+        .bind(now)
+        .bind(step)
+        .bind(member_id)
+        .execute(&mut *tx)
+        .await?;
+
+        if enabled.rows_affected() == 0 {
+            return Ok(false);
+        }
+
+        store_recovery_codes(&mut tx, member_id, recovery_code_hashes).await?;
+        tx.commit().await?;
+
+        Ok(true)
+    }
+
+    async fn use_totp_step(&self, member_id: &str, step: i64) -> Result<bool, sqlx::Error> {
+        let used = sqlx::query(
+            r#"
+            UPDATE member_totp
+            SET last_used_step = $1
+            WHERE member_id = $2
+                AND enabled_at IS NOT NULL
+                AND (last_used_step IS NULL OR last_used_step < $1)
+            "#,
+        )
+        .bind(step)
+        .bind(member_id)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(used.rows_affected() > 0)
+    }
+
+    async fn use_recovery_code(
+        &self,
+        member_id: &str,
+        code_hash: &str,
+        now: DateTime<Utc>,
+    ) -> Result<bool, sqlx::Error> {
+        let used = sqlx::query(
+            r#"
+            UPDATE member_recovery_codes
+            SET used_at = $1
+            WHERE member_id = $2 AND code_hash = $3 AND used_at IS NULL
+            "#,
+        )
+        .bind(now)
+        .bind(member_id)
+        .bind(code_hash)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(used.rows_affected() > 0)
+    }
+
+    async fn replace_recovery_codes(
+        &self,
+        member_id: &str,
+        recovery_code_hashes: &[String],
+    ) -> Result<(), sqlx::Error> {
+        let mut tx = self.pool.begin().await?;
+        store_recovery_codes(&mut tx, member_id, recovery_code_hashes).await?;
+        tx.commit().await?;
+
+        Ok(())
+    }
+
+    async fn delete_member_totp(&self, member_id: &str) -> Result<(), sqlx::Error> {
+        let mut tx = self.pool.begin().await?;
+
+        sqlx::query(
+            r#"
+            DELETE FROM member_recovery_codes WHERE member_id = $1
+            "#,
+        )
+        .bind(member_id)
+        .execute(&mut *tx)
+        .await?;
+
+        sqlx::query(
+            r#"
+            DELETE FROM member_totp WHERE member_id = $1
+            "#,
+        )
+        .bind(member_id)
+        .execute(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+
+        Ok(())
+    }
+
+    async fn create_two_factor_challenge(
+        &self,
+        challenge: &TwoFactorChallenge,
+    ) -> Result<(), sqlx::Error> {
+        sqlx::query(
+            r#"
+            INSERT INTO two_factor_challenges (
+                id, member_id, token_hash, created_at, expires_at, attempts
+            )
+            VALUES ($1, $2, $3, $4, $5, $6)
+            "#,
+        )
+        .bind(&challenge.id)
+        .bind(&challenge.member_id)
+        .bind(&challenge.token_hash)
+        .bind(challenge.created_at)
+        .bind(challenge.expires_at)
+        .bind(challenge.attempts)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    async fn reserve_two_factor_attempt(
+        &self,
+        token_hash: &str,
+        now: DateTime<Utc>,
+        max_attempts: i32,
+    ) -> Result<Option<TwoFactorChallenge>, sqlx::Error> {
+        sqlx::query_as(
+            r#"
+            UPDATE two_factor_challenges
+            SET attempts = attempts + 1
+            WHERE token_hash = $1 AND used_at IS NULL AND expires_at > $2 AND attempts < $3
+            RETURNING *
+            "#,
+        )
+        .bind(token_hash)
+        .bind(now)
+        .bind(max_attempts)
+        .fetch_optional(&self.pool)
+        .await
+    }
+
+    async fn use_two_factor_challenge(
+        &self,
+        id: &str,
+        now: DateTime<Utc>,
+    ) -> Result<bool, sqlx::Error> {
+        let used = sqlx::query(
+            r#"
+            UPDATE two_factor_challenges
+            SET used_at = $1
+            WHERE id = $2 AND used_at IS NULL
+            "#,
+        )
+        .bind(now)
+        .bind(id)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(used.rows_affected() > 0)
+    }
+}
+
+// Replaces every recovery code of the member, used or not.
+async fn store_recovery_codes(
+    conn: &mut SqliteConnection,
+    member_id: &str,
+    recovery_code_hashes: &[String],
+) -> Result<(), sqlx::Error> {
+    sqlx::query(
+        r#"
+        DELETE FROM member_recovery_codes WHERE member_id = $1
+        "#,
+    )
+    .bind(member_id)
+    .execute(&mut *conn)
+    .await?;
+
+    for code_hash in recovery_code_hashes {
+        sqlx::query(
+            r#"
+            INSERT INTO member_recovery_codes (id, member_id, code_hash)
+            VALUES ($1, $2, $3)
+            "#,
+        )
+        .bind(Uuid::new_v4().to_string())
+        .bind(member_id)
+        .bind(code_hash)
+        .execute(&mut *conn)
+        .await?;
+    }
+
+    Ok(())
+}
@@ -0,0 +1,180 @@
+use super::SqliteRepository;
+use crate::{
+    repository::UserRepository,
+    user::{
+        account::{ExportedActivation, ExportedComment, ExportedLike},
+        User,
+    },
+};
+use chrono::{DateTime, Utc};
+
+impl UserRepository for SqliteRepository {
+    async fn retrieve_user_by_email(&self, email: &str) -> Result<Option<User>, sqlx::Error> {
+        sqlx::query_as(
+            r#"
+            SELECT * FROM users WHERE email = $1 AND deleted_at IS NULL
+            "#,
+        )
+        .bind(email)
+        .fetch_optional(&self.pool)
+        .await
+    }
+
+    async fn retrieve_user_by_id(&self, id: &str) -> Result<Option<User>, sqlx::Error> {
+        sqlx::query_as(
+            r#"
+            SELECT * FROM users WHERE id = $1 AND deleted_at IS NULL
+            "#,
+        )
+        .bind(id)
+        .fetch_optional(&self.pool)
+        .await
+    }
+
+    async fn create_user(&self, user: &User) -> Result<(), sqlx::Error> {
+        sqlx::query(
+            r#"
+            INSERT INTO users (id, name, surname, email, avatar_url, other, password_hash)
+            VALUES ($1, $2, $3, $4, $5, $6, $7)
+            "#,
+        )
+        .bind(&user.id)
+        .bind(&user.name)
+        .bind(&user.surname)
+        .bind(&user.email)
+        .bind(&user.avatar_url)
+        .bind(&user.other)
+        .bind(&user.password_hash)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    async fn update_user(&self, user: &User) -> Result<(), sqlx::Error> {
+        sqlx::query(
+            r#"
+            UPDATE users
+            SET name = $1, surname = $2, avatar_url = $3, other = $4, password_hash = $5
+            WHERE id = $6
+            "#,
+        )
+        .bind(&user.name)
+        .bind(&user.surname)
+        .bind(&user.avatar_url)
+        .bind(&user.other)
+        .bind(&user.password_hash)
+        .bind(&user.id)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    async fn mark_user_email_verified(
+        &self,
+        id: &str,
+        now: DateTime<Utc>,
+    ) -> Result<(), sqlx::Error> {
+        sqlx::query(
+            r#"
+            UPDATE users SET email_verified_at = $1 WHERE id = $2 AND email_verified_at IS NULL
+            "#,
+        )
+        .bind(now)
+        .bind(id)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    async fn retrieve_user_likes(&self, user_id: &str) -> Result<Vec<ExportedLike>, sqlx::Error> {
+        sqlx::query_as(
+            r#"
+            SELECT l.promo_id, p.company_name, p.description, l.created_at AS liked_at
+            FROM promo_likes l
+            JOIN promos p ON p.promo_id = l.promo_id
+            WHERE l.user_id = $1
+            ORDER BY l.created_at
+            "#,
+        )
+        .bind(user_id)
+        .fetch_all(&self.pool)
+        .await
+    }
+
+    async fn retrieve_user_comments(
+        &self,
+        user_id: &str,
+    ) -> Result<Vec<ExportedComment>, sqlx::Error> {
+        sqlx::query_as(
+            r#"
+            SELECT id, promo_id, text, created_at FROM promo_comments
+            WHERE user_id = $1
+            ORDER BY created_at
+            "#,
+        )
+        .bind(user_id)
+        .fetch_all(&self.pool)
+        .await
+    }
+
+    async fn retrieve_user_activations(
+        &self,
+        user_id: &str,
+    ) -> Result<Vec<ExportedActivation>, sqlx::Error> {
+        sqlx::query_as(
+            r#"
+            SELECT a.promo_id, p.company_name, p.description, a.activated_at
+            FROM promo_activations a
+            JOIN promos p ON p.promo_id = a.promo_id
+            WHERE a.user_id = $1
+            ORDER BY a.activated_at
+            "#,
+        )
+        .bind(user_id)
+        .fetch_all(&self.pool)
+        .await
+    }
+
+    async fn delete_user(&self, user: &User, now: DateTime<Utc>) -> Result<(), sqlx::Error> {
+        let mut tx = self.pool.begin().await?;
+
+        for query in [
+            "DELETE FROM promo_comments WHERE user_id = $1",
+            "DELETE FROM user_sessions WHERE user_id = $1",
+            "DELETE FROM refresh_tokens WHERE audience = 'user' AND subject_id = $1",
+            "DELETE FROM password_reset_tokens WHERE audience = 'user' AND subject_id = $1",
+            "DELETE FROM email_verification_tokens WHERE audience = 'user' AND subject_id = $1",
+            "DELETE FROM email_verification_sends WHERE audience = 'user' AND subject_id = $1",
+        ] {
+            sqlx::query(query).bind(&user.id).execute(&mut *tx).await?;
+        }
+
+        sqlx::query(
+            r#"
+            DELETE FROM antifraud_verdicts WHERE user_email = $1
+            "#,
+        )
+        .bind(&user.email)
+        .execute(&mut *tx)
+        .await?;
+
+        // Likes and activations stay attached to the anonymous row.
+        sqlx::query(
+            r#"
+            UPDATE users
+            SET name = '', surname = '', email = 'deleted:' || id, avatar_url = NULL,
+                other = '{}', password_hash = '', email_verified_at = NULL, deleted_at = $1
+            WHERE id = $2
+            "#,
+        )
+        .bind(now)
+        .bind(&user.id)
+        .execute(&mut *tx)
+        .await?;
+
+        tx.commit().await
+    }
+}
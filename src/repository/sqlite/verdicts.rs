@@ -0,0 +1,57 @@
+use super::SqliteRepository;
+use crate::{antifraud::Verdict, repository::VerdictRepository};
+use chrono::{DateTime, Utc};
+use sqlx::prelude::FromRow;
+
+#[derive(FromRow)]
+struct CachedVerdict {
+    ok: bool,
+    cache_until: DateTime<Utc>,
+}
+
+impl VerdictRepository for SqliteRepository {
+    async fn retrieve_verdict(
+        &self,
+        user_email: &str,
+        now: DateTime<Utc>,
+    ) -> Result<Option<Verdict>, sqlx::Error> {
+        let cached: Option<CachedVerdict> = sqlx::query_as(
+            r#"
+            SELECT ok, cache_until FROM antifraud_verdicts
+            WHERE user_email = $1 AND cache_until > $2
+            "#,
+        )
+        .bind(user_email)
+        .bind(now)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(cached.map(|cached| Verdict {
+            ok: cached.ok,
+            cache_until: Some(cached.cache_until),
+        }))
+    }
+
+    async fn store_verdict(
+        &self,
+        user_email: &str,
+        ok: bool,
+        cache_until: DateTime<Utc>,
+    ) -> Result<(), sqlx::Error> {
+        sqlx::query(
+            r#"
+            INSERT INTO antifraud_verdicts (user_email, ok, cache_until)
+            VALUES ($1, $2, $3)
+            ON CONFLICT (user_email)
+            DO UPDATE SET ok = EXCLUDED.ok, cache_until = EXCLUDED.cache_until
+            "#,
+        )
+        .bind(user_email)
+        .bind(ok)
+        .bind(cache_until)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,331 @@
+use super::{
+    ActivationRepository, CommentRepository, CompanyRepository, MemberRepository, PromoRepository,
+    UserRepository,
+};
+use crate::testing;
+use chrono::{Duration, Utc};
+use std::collections::HashSet;
+use tokio::task::JoinSet;
+
+#[cfg_attr(feature = "postgres", ignore = "needs TEST_DATABASE_URL")]
+#[tokio::test]
+async fn users_round_trip() {
+    let repository = testing::repository().await;
+    let mut user = testing::user(&repository, 25, "ru").await;
+
+    let found = repository
+        .retrieve_user_by_email(&user.email)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(found.id, user.id);
+    assert_eq!(found.other.country, "ru");
+
+    user.name = "Renamed".to_string();
+    user.other.age = 30;
+    repository.update_user(&user).await.unwrap();
+
+    let found = repository
+        .retrieve_user_by_id(&user.id)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(found.name, "Renamed");
+    assert_eq!(found.other.age, 30);
+
+    repository.delete_user(&user, Utc::now()).await.unwrap();
+    assert!(repository
+        .retrieve_user_by_email(&user.email)
+        .await
+        .unwrap()
+        .is_none());
+    assert!(repository
+        .retrieve_user_by_id(&user.id)
+        .await
+        .unwrap()
+        .is_none());
+}
+
+#[cfg_attr(feature = "postgres", ignore = "needs TEST_DATABASE_URL")]
+#[tokio::test]
+async fn companies_are_created_with_their_owner() {
+    let repository = testing::repository().await;
+    let (company, owner) = testing::company(&repository).await;
+
+    let found = repository
+        .retrieve_company_by_id(&company.id)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(found.name, company.name);
+
+    let member = repository
+        .retrieve_member_by_email(&owner.email)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(member.id, owner.id);
+    assert_eq!(member.company_id, company.id);
+    assert_eq!(member.role, "owner");
+}
+
+#[cfg_attr(feature = "postgres", ignore = "needs TEST_DATABASE_URL")]
+#[tokio::test]
+async fn promos_round_trip() {
+    let repository = testing::repository().await;
+    let now = Utc::now();
+    let today = now.date_naive();
+    let (company, _) = testing::company(&repository).await;
+    let mut promo = testing::promo(&repository, &company, 10, None, now).await;
+
+    let found = repository
+        .retrieve_promo(&promo.promo_id, today)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(found.company_name, company.name);
+    assert_eq!(found.used_count, 0);
+    assert!(found.active);
+    assert!(repository.promo_exists(&promo.promo_id).await.unwrap());
+
+    // A promo whose last day has passed is no longer active.
+    promo.description = "Changed by the test".to_string();
+    promo.active_until = Some(today - Duration::days(1));
+    repository.update_promo(&promo).await.unwrap();
+
+    let found = repository
+        .retrieve_promo(&promo.promo_id, today)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(found.description, "Changed by the test");
+    assert!(!found.active);
+
+    assert!(repository
+        .retrieve_promo(&uuid::Uuid::new_v4().to_string(), today)
+        .await
+        .unwrap()
+        .is_none());
+}
+
+#[cfg_attr(feature = "postgres", ignore = "needs TEST_DATABASE_URL")]
+#[tokio::test]
+async fn unpublished_promos_are_hidden_from_users() {
+    let repository = testing::repository().await;
+    let now = Utc::now();
+    let (company, _) = testing::company(&repository).await;
+    let user = testing::user(&repository, 25, "ru").await;
+    let mut promo = testing::promo(&repository, &company, 10, None, now).await;
+    promo.promo_id = uuid::Uuid::new_v4().to_string();
+    promo.published_at = None;
+    repository.create_promo(&promo, None).await.unwrap();
+
+    assert!(!repository.promo_exists(&promo.promo_id).await.unwrap());
+    assert!(repository
+        .retrieve_promo_for_user(&user.id, &promo.promo_id, now.date_naive())
+        .await
+        .unwrap()
+        .is_none());
+
+    assert!(repository
+        .publish_promo(&promo.promo_id, now)
+        .await
+        .unwrap());
+    assert!(!repository
+        .publish_promo(&promo.promo_id, now)
+        .await
+        .unwrap());
+    assert!(repository.promo_exists(&promo.promo_id).await.unwrap());
+}
+
+#[cfg_attr(feature = "postgres", ignore = "needs TEST_DATABASE_URL")]
+#[tokio::test]
+async fn likes_are_counted_once_per_user() {
+    let repository = testing::repository().await;
+    let now = Utc::now();
+    let (company, _) = testing::company(&repository).await;
+    let user = testing::user(&repository, 25, "ru").await;
+    let promo = testing::promo(&repository, &company, 10, None, now).await;
+
+    repository
+        .add_like(&promo.promo_id, &user.id)
+        .await
+        .unwrap();
+    repository
+        .add_like(&promo.promo_id, &user.id)
+        .await
+        .unwrap();
+
+    let found = repository
+        .retrieve_promo_for_user(&user.id, &promo.promo_id, now.date_naive())
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(found.like_count, 1);
+    assert!(found.is_liked_by_user);
+
+    repository
+        .remove_like(&promo.promo_id, &user.id)
+        .await
+        .unwrap();
+    repository
+        .remove_like(&promo.promo_id, &user.id)
+        .await
+        .unwrap();
+
+    let found = repository
+        .retrieve_promo_for_user(&user.id, &promo.promo_id, now.date_naive())
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(found.like_count, 0);
+    assert!(!found.is_liked_by_user);
+}
+
+#[cfg_attr(feature = "postgres", ignore = "needs TEST_DATABASE_URL")]
+#[tokio::test]
+async fn comments_round_trip() {
+    let repository = testing::repository().await;
+    let now = Utc::now();
+    let (company, _) = testing::company(&repository).await;
+    let user = testing::user(&repository, 25, "ru").await;
+    let promo = testing::promo(&repository, &company, 10, None, now).await;
+
+    for (id, text) in [
+        ("first", "The first comment"),
+        ("second", "The second comment"),
+    ] {
+        repository
+            .create_comment(id, &promo.promo_id, &user.id, text)
+            .await
+            .unwrap();
+    }
+    repository
+        .update_comment("first", "An edited comment")
+        .await
+        .unwrap();
+
+    let comment = repository
+        .retrieve_comment(&promo.promo_id, "first")
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(comment.text, "An edited comment");
+    assert_eq!(comment.author.name, user.name);
+    assert_eq!(
+        repository
+            .retrieve_comment_author_id(&promo.promo_id, "first")
+            .await
+            .unwrap(),
+        Some(user.id.clone())
+    );
+    // Comments are looked up within their promo only.
+    assert!(repository
+        .retrieve_comment(&uuid::Uuid::new_v4().to_string(), "first")
+        .await
+        .unwrap()
+        .is_none());
+
+    repository.delete_comment("second").await.unwrap();
+    let comments = repository.retrieve_comments(&promo.promo_id).await.unwrap();
+    assert_eq!(comments.len(), 1);
+    assert_eq!(comments[0].id, "first");
+
+    let found = repository
+        .retrieve_promo_for_user(&user.id, &promo.promo_id, now.date_naive())
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(found.comment_count, 1);
+}
+
+// Fires `attempts` activations of the promo at once, each by a user of its
+// own, and returns the codes that were issued.
+async fn activate_concurrently(
+    repository: &super::Repository,
+    promo_id: &str,
+    attempts: usize,
+) -> Vec<String> {
+    let mut users = vec![];
+    for _ in 0..attempts {
+        users.push(testing::user(repository, 25, "ru").await);
+    }
+
+    let mut activations = JoinSet::new();
+    for user in users {
+        let repository = repository.clone();
+        let promo_id = promo_id.to_string();
+        activations.spawn(async move {
+            repository
+                .activate_promo(&promo_id, &user.id, "ru", Utc::now())
+                .await
+                .unwrap()
+        });
+    }
+
+    let mut codes = vec![];
+    while let Some(code) = activations.join_next().await {
+        codes.extend(code.unwrap());
+    }
+    codes
+}
+
+#[cfg_attr(feature = "postgres", ignore = "needs TEST_DATABASE_URL")]
+#[tokio::test(flavor = "multi_thread", worker_threads = 8)]
+async fn concurrent_activations_never_issue_a_unique_code_twice() {
+    let repository = testing::repository().await;
+    let now = Utc::now();
+    let (company, _) = testing::company(&repository).await;
+    let unique_codes: Vec<String> = (0..20).map(|i| format!("CODE-{}", i)).collect();
+    let promo = testing::promo(
+        &repository,
+        &company,
+        unique_codes.len() as i32,
+        Some(&unique_codes),
+        now,
+    )
+    .await;
+
+    let codes = activate_concurrently(&repository, &promo.promo_id, 60).await;
+
+    let distinct: HashSet<&String> = codes.iter().collect();
+    assert_eq!(distinct.len(), codes.len(), "a code was issued twice");
+    assert_eq!(codes.len(), unique_codes.len());
+    assert!(codes.iter().all(|code| unique_codes.contains(code)));
+
+    let promo = repository
+        .retrieve_promo(&promo.promo_id, now.date_naive())
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(promo.used_count as usize, codes.len());
+    assert!(promo.used_count <= promo.max_count);
+    assert!(!promo.active);
+}
+
+#[cfg_attr(feature = "postgres", ignore = "needs TEST_DATABASE_URL")]
+#[tokio::test(flavor = "multi_thread", worker_threads = 8)]
+async fn concurrent_activations_stop_at_max_count() {
+    let repository = testing::repository().await;
+    let now = Utc::now();
+    let (company, _) = testing::company(&repository).await;
+    let promo = testing::promo(&repository, &company, 15, None, now).await;
+
+    let codes = activate_concurrently(&repository, &promo.promo_id, 50).await;
+    assert_eq!(codes.len(), 15);
+
+    let promo = repository
+        .retrieve_promo(&promo.promo_id, now.date_naive())
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(promo.used_count, 15);
+    assert!(promo.used_count <= promo.max_count);
+
+    let countries = repository
+        .retrieve_promo_countries(&promo.promo_id)
+        .await
+        .unwrap();
+    assert_eq!(countries.len(), 1);
+    assert_eq!(countries[0].activate_count, 15);
+}
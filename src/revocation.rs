@@ -0,0 +1,131 @@
+use crate::repository::{Repository, TokenRepository};
+use chrono::{DateTime, Utc};
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+};
+
+// Revoked token ids are kept in memory so the auth middleware can check them
+// without a query, and mirrored to the database so revocations survive a
+// restart. Entries are dropped once the token would have expired anyway.
+#[derive(Clone)]
+pub struct RevokedTokens {
+    repository: Repository,
+    tokens: Arc<Mutex<HashMap<String, DateTime<Utc>>>>,
+}
+
+impl RevokedTokens {
+    pub async fn load(repository: Repository) -> Result<Self, sqlx::Error> {
+        let tokens = repository.retrieve_revoked_tokens(Utc::now()).await?;
+
+        Ok(RevokedTokens {
+            repository,
+            tokens: Arc::new(Mutex::new(tokens.into_iter().collect())),
+        })
+    }
+
+    pub fn is_revoked(&self, jti: &str) -> bool {
+        self.tokens
+            .lock()
+            .unwrap()
+            .get(jti)
+            .is_some_and(|expires_at| *expires_at > Utc::now())
+    }
+
+    // Signing in again invalidates every token previously issued to the subject.
+    pub async fn start_session(
+        &self,
+        subject_id: &str,
+        jti: &str,
+        expires_at: DateTime<Utc>,
+    ) -> Result<(), sqlx::Error> {
+        let now = Utc::now();
+        let revoked = self
+            .repository
+            .replace_issued_tokens(subject_id, jti, expires_at, now)
+            .await?;
+
+        self.remember(revoked, now);
+        self.repository.delete_expired_tokens(now).await
+    }
+
+    // Revokes every token issued to the subject, e.g. after a password change.
+    pub async fn end_sessions(&self, subject_id: &str) -> Result<(), sqlx::Error> {
+        let now = Utc::now();
+        let revoked = self
+            .repository
+            .revoke_issued_tokens(subject_id, now)
+            .await?;
+
+        self.remember(revoked, now);
+        self.repository.delete_expired_tokens(now).await
+    }
+
+    pub async fn revoke(&self, jti: &str, expires_at: DateTime<Utc>) -> Result<(), sqlx::Error> {
+        let now = Utc::now();
+        self.repository.revoke_token(jti, expires_at).await?;
+
+        self.remember(vec![(jti.to_string(), expires_at)], now);
+        self.repository.delete_expired_tokens(now).await
+    }
+
+    fn remember(&self, revoked: Vec<(String, DateTime<Utc>)>, now: DateTime<Utc>) {
+        let mut tokens = self.tokens.lock().unwrap();
+        tokens.retain(|_, expires_at| *expires_at > now);
+        tokens.extend(revoked);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::RevokedTokens;
+    use crate::{repository::TokenRepository, testing};
+    use chrono::{Duration, Utc};
+
+    #[cfg_attr(feature = "postgres", ignore = "needs TEST_DATABASE_URL")]
+    #[tokio::test]
+    async fn revocations_survive_a_restart() {
+        let repository = testing::repository().await;
+        let expires_at = Utc::now() + Duration::hours(1);
+
+        let revoked_tokens = RevokedTokens::load(repository.clone()).await.unwrap();
+        revoked_tokens
+            .revoke("signed-out", expires_at)
+            .await
+            .unwrap();
+        // Signing in again revokes the token of the previous sign-in.
+        revoked_tokens
+            .start_session("subject", "first", expires_at)
+            .await
+            .unwrap();
+        revoked_tokens
+            .start_session("subject", "second", expires_at)
+            .await
+            .unwrap();
+        assert!(revoked_tokens.is_revoked("first"));
+
+        let restarted = RevokedTokens::load(repository).await.unwrap();
+        assert!(restarted.is_revoked("signed-out"));
+        assert!(restarted.is_revoked("first"));
+        assert!(!restarted.is_revoked("second"));
+    }
+
+    #[cfg_attr(feature = "postgres", ignore = "needs TEST_DATABASE_URL")]
+    #[tokio::test]
+    async fn expired_revocations_are_not_loaded() {
+        let repository = testing::repository().await;
+        repository
+            .revoke_token("expired", Utc::now() - Duration::minutes(1))
+            .await
+            .unwrap();
+        repository
+            .revoke_token("live", Utc::now() + Duration::minutes(1))
+            .await
+            .unwrap();
+
+        let revoked_tokens = RevokedTokens::load(repository).await.unwrap();
+        let tokens = revoked_tokens.tokens.lock().unwrap();
+        assert!(!tokens.contains_key("expired"));
+        assert!(tokens.contains_key("live"));
+    }
+}
@@ -1,4 +1,11 @@
-use crate::{business, user, AppState};
+use crate::{
+    auth::{
+        self,
+        middleware::{authorize, AuthConfig, BUSINESS_ROLES, USER_ROLES},
+        Audience, Role,
+    },
+    business, user, AppState,
+};
 use axum::{
     middleware,
     routing::{delete, get, patch, post, put},
@@ -6,8 +13,38 @@ use axum::{
 };
 
 pub async fn app(state: AppState) -> Router {
+    let business_auth = |roles| {
+        middleware::from_fn_with_state(
+            AuthConfig::new(state.clone(), Audience::Business, roles),
+            authorize,
+        )
+    };
+    let verified_business_auth = |roles| {
+        middleware::from_fn_with_state(
+            AuthConfig::new(state.clone(), Audience::Business, roles).require_verified_email(),
+            authorize,
+        )
+    };
+    let any_member = business_auth(BUSINESS_ROLES);
+    let analyst = business_auth(&[Role::Owner, Role::Editor, Role::Analyst]);
+    let editor = verified_business_auth(&[Role::Owner, Role::Editor]);
+    let owner = verified_business_auth(&[Role::Owner]);
+    let member_session = middleware::from_fn_with_state(
+        AuthConfig::new(state.clone(), Audience::Business, BUSINESS_ROLES).without_api_keys(),
+        authorize,
+    );
+    let user_auth = middleware::from_fn_with_state(
+        AuthConfig::new(state.clone(), Audience::User, USER_ROLES),
+        authorize,
+    );
+    let verified_user = middleware::from_fn_with_state(
+        AuthConfig::new(state.clone(), Audience::User, USER_ROLES).require_verified_email(),
+        authorize,
+    );
+
     Router::new()
         .route("/api/ping", get(ping))
+        .route("/.well-known/jwks.json", get(auth::jwks::jwks))
         .route(
             "/api/business/auth/sign-up",
             post(business::auth::sign_up::sign_up),
@@ -16,128 +53,197 @@ pub async fn app(state: AppState) -> Router {
             "/api/business/auth/sign-in",
             post(business::auth::sign_in::sign_in),
         )
+        .route(
+            "/api/business/auth/refresh",
+            post(business::auth::refresh::refresh),
+        )
+        .route(
+            "/api/business/auth/password-reset",
+            post(business::auth::password_reset::request_reset),
+        )
+        .route(
+            "/api/business/auth/password-reset/confirm",
+            post(business::auth::password_reset::confirm_reset),
+        )
+        .route(
+            "/api/business/auth/accept-invitation",
+            post(business::members::accept::accept_invitation),
+        )
+        .route(
+            "/api/business/auth/sign-out",
+            post(business::auth::sign_out::sign_out).layer(member_session.clone()),
+        )
+        .route(
+            "/api/business/auth/verify-email",
+            get(business::auth::verify_email::verify_email),
+        )
+        .route(
+            "/api/business/auth/verify-email/resend",
+            post(business::auth::verify_email::resend_verification).layer(member_session.clone()),
+        )
+        .route(
+            "/api/business/auth/sign-in/2fa",
+            post(business::auth::two_factor::verify_sign_in),
+        )
+        .route(
+            "/api/business/auth/2fa/enroll",
+            post(business::auth::two_factor::enroll).layer(member_session.clone()),
+        )
+        .route(
+            "/api/business/auth/2fa/confirm",
+            post(business::auth::two_factor::confirm).layer(member_session.clone()),
+        )
+        .route(
+            "/api/business/auth/2fa/disable",
+            post(business::auth::two_factor::disable).layer(member_session.clone()),
+        )
+        .route(
+            "/api/business/auth/2fa/recovery-codes",
+            post(business::auth::two_factor::regenerate_recovery_codes)
+                .layer(member_session.clone()),
+        )
         .route(
             "/api/business/promo",
-            post(business::promo::create::create_promo).layer(middleware::from_fn_with_state(
-                state.clone(),
-                business::middlewares::authorize::authorize_middleware,
-            )),
+            post(business::promo::create::create_promo).layer(editor.clone()),
         )
         .route(
             "/api/business/promo",
-            get(business::promo::list::list_promos).layer(middleware::from_fn_with_state(
-                state.clone(),
-                business::middlewares::authorize::authorize_middleware,
-            )),
+            get(business::promo::list::list_promos).layer(any_member.clone()),
         )
         .route(
             "/api/business/promo/{id}",
-            get(business::promo::promo_by_id::get_promo).layer(middleware::from_fn_with_state(
-                state.clone(),
-                business::middlewares::authorize::authorize_middleware,
-            )),
+            get(business::promo::promo_by_id::get_promo).layer(any_member.clone()),
         )
         .route(
             "/api/business/promo/{id}",
-            patch(business::promo::promo_by_id::edit_promo).layer(middleware::from_fn_with_state(
-                state.clone(),
-                business::middlewares::authorize::authorize_middleware,
-            )),
+            patch(business::promo::promo_by_id::edit_promo).layer(editor.clone()),
         )
         .route(
             "/api/business/promo/{id}/stat",
-            get(business::promo::promo_by_id::get_promo_stat).layer(
-                middleware::from_fn_with_state(
-                    state.clone(),
-                    business::middlewares::authorize::authorize_middleware,
-                ),
-            ),
+            get(business::promo::promo_by_id::get_promo_stat).layer(analyst.clone()),
+        )
+        .route(
+            "/api/business/promo/{id}/events",
+            get(business::promo::events::get_promo_events).layer(analyst.clone()),
+        )
+        .route(
+            "/api/business/members",
+            get(business::members::list::list_members).layer(owner.clone()),
+        )
+        .route(
+            "/api/business/members/invite",
+            post(business::members::invite::invite_member).layer(owner.clone()),
+        )
+        .route(
+            "/api/business/api-keys",
+            post(business::api_keys::create::create_api_key).layer(owner.clone()),
+        )
+        .route(
+            "/api/business/api-keys",
+            get(business::api_keys::list::list_api_keys).layer(owner.clone()),
+        )
+        .route(
+            "/api/business/api-keys/{id}",
+            delete(business::api_keys::revoke::revoke_api_key).layer(owner.clone()),
         )
         .route("/api/user/auth/sign-up", post(user::auth::sign_up::sign_up))
         .route("/api/user/auth/sign-in", post(user::auth::sign_in::sign_in))
+        .route("/api/user/auth/refresh", post(user::auth::refresh::refresh))
+        .route(
+            "/api/user/auth/password-reset",
+            post(user::auth::password_reset::request_reset),
+        )
+        .route(
+            "/api/user/auth/password-reset/confirm",
+            post(user::auth::password_reset::confirm_reset),
+        )
+        .route(
+            "/api/user/auth/sign-out",
+            post(user::auth::sign_out::sign_out).layer(user_auth.clone()),
+        )
+        .route(
+            "/api/user/auth/verify-email",
+            get(user::auth::verify_email::verify_email),
+        )
+        .route(
+            "/api/user/auth/verify-email/resend",
+            post(user::auth::verify_email::resend_verification).layer(user_auth.clone()),
+        )
         .route(
             "/api/user/profile",
-            get(user::profile::get_profile).layer(middleware::from_fn_with_state(
-                state.clone(),
-                user::middlewares::authorize::authorize_middleware,
-            )),
+            get(user::profile::get_profile).layer(user_auth.clone()),
         )
         .route(
             "/api/user/profile",
-            patch(user::profile::edit_profile).layer(middleware::from_fn_with_state(
-                state.clone(),
-                user::middlewares::authorize::authorize_middleware,
-            )),
+            patch(user::profile::edit_profile).layer(user_auth.clone()),
+        )
+        .route(
+            "/api/user/profile",
+            delete(user::account::delete_account).layer(user_auth.clone()),
+        )
+        .route(
+            "/api/user/profile/export",
+            get(user::account::export_data).layer(user_auth.clone()),
+        )
+        .route(
+            "/api/user/sessions",
+            get(user::sessions::list_sessions).layer(user_auth.clone()),
+        )
+        .route(
+            "/api/user/sessions",
+            delete(user::sessions::revoke_other_sessions).layer(user_auth.clone()),
+        )
+        .route(
+            "/api/user/sessions/{id}",
+            delete(user::sessions::revoke_session).layer(user_auth.clone()),
         )
         .route(
             "/api/user/feed",
-            get(user::feed::promo_feed).layer(middleware::from_fn_with_state(
-                state.clone(),
-                user::middlewares::authorize::authorize_middleware,
-            )),
+            get(user::feed::promo_feed).layer(user_auth.clone()),
+        )
+        .route(
+            "/api/user/promo/history",
+            get(user::promo::history::get_history).layer(user_auth.clone()),
+        )
+        .route(
+            "/api/user/promo/search",
+            get(user::promo::search::search_promos).layer(user_auth.clone()),
         )
         .route(
             "/api/user/promo/{id}",
-            get(user::promo::get_promo).layer(middleware::from_fn_with_state(
-                state.clone(),
-                user::middlewares::authorize::authorize_middleware,
-            )),
+            get(user::promo::get_promo).layer(user_auth.clone()),
         )
         .route(
             "/api/user/promo/{id}/like",
-            post(user::promo::like::add_like).layer(middleware::from_fn_with_state(
-                state.clone(),
-                user::middlewares::authorize::authorize_middleware,
-            )),
+            post(user::promo::like::add_like).layer(user_auth.clone()),
         )
         .route(
             "/api/user/promo/{id}/like",
-            delete(user::promo::like::remove_like).layer(middleware::from_fn_with_state(
-                state.clone(),
-                user::middlewares::authorize::authorize_middleware,
-            )),
+            delete(user::promo::like::remove_like).layer(user_auth.clone()),
         )
         .route(
             "/api/user/promo/{id}/comments",
-            post(user::promo::comments::add_comment).layer(middleware::from_fn_with_state(
-                state.clone(),
-                user::middlewares::authorize::authorize_middleware,
-            )),
+            post(user::promo::comments::add_comment).layer(verified_user.clone()),
         )
         .route(
             "/api/user/promo/{id}/comments",
-            get(user::promo::comments::get_comments).layer(middleware::from_fn_with_state(
-                state.clone(),
-                user::middlewares::authorize::authorize_middleware,
-            )),
+            get(user::promo::comments::get_comments).layer(user_auth.clone()),
         )
         .route(
             "/api/user/promo/{promo_id}/comments/{comment_id}",
-            get(user::promo::comments::get_comment_by_id).layer(middleware::from_fn_with_state(
-                state.clone(),
-                user::middlewares::authorize::authorize_middleware,
-            )),
+            get(user::promo::comments::get_comment_by_id).layer(user_auth.clone()),
         )
         .route(
             "/api/user/promo/{promo_id}/comments/{comment_id}",
-            put(user::promo::comments::edit_comment).layer(middleware::from_fn_with_state(
-                state.clone(),
-                user::middlewares::authorize::authorize_middleware,
-            )),
+            put(user::promo::comments::edit_comment).layer(user_auth.clone()),
         )
         .route(
             "/api/user/promo/{promo_id}/comments/{comment_id}",
-            delete(user::promo::comments::delete_comment).layer(middleware::from_fn_with_state(
-                state.clone(),
-                user::middlewares::authorize::authorize_middleware,
-            )),
+            delete(user::promo::comments::delete_comment).layer(user_auth.clone()),
         )
         .route(
             "/api/user/promo/{promo_id}/activate",
-            post(user::promo::activate_promo).layer(middleware::from_fn_with_state(
-                state.clone(),
-                user::middlewares::authorize::authorize_middleware,
-            )),
+            post(user::promo::activate_promo).layer(verified_user.clone()),
         )
         .with_state(state)
 }
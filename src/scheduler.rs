@@ -0,0 +1,328 @@
+use crate::{
+    business::promo::Promo,
+    calendar::Calendar,
+    repository::{JobRepository, PromoRepository, Repository},
+};
+use chrono::{DateTime, Duration, Utc};
+use sqlx::FromRow;
+use std::{env, time};
+use tokio::{task::JoinHandle, time::MissedTickBehavior};
+
+// A failing job is given up on after this many attempts. Retries back off
+// from `RETRY_BASE_SECS`, doubling with every attempt.
+const MAX_ATTEMPTS: i32 = 5;
+const RETRY_BASE_SECS: i64 = 30;
+// A claimed job is kept from other runs for this long, after which it is
+// picked up again in case the run that claimed it died.
+const LOCK_SECS: i64 = 5 * 60;
+const BATCH_SIZE: i64 = 50;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum JobKind {
+    // Makes a promo visible to users once its `publish_at` has come.
+    PublishPromo,
+    // Records that a promo stopped being active at the end of `active_until`.
+    ExpirePromo,
+}
+
+impl JobKind {
+    pub fn name(self) -> &'static str {
+        match self {
+            JobKind::PublishPromo => "publish_promo",
+            JobKind::ExpirePromo => "expire_promo",
+        }
+    }
+
+    fn parse(name: &str) -> Option<Self> {
+        match name {
+            "publish_promo" => Some(JobKind::PublishPromo),
+            "expire_promo" => Some(JobKind::ExpirePromo),
+            _ => None,
+        }
+    }
+}
+
+#[derive(FromRow)]
+pub struct ScheduledJob {
+    pub id: i64,
+    pub kind: String,
+    pub promo_id: String,
+    pub attempts: i32,
+}
+
+// Runs the jobs persisted in the database from within the server. Every
+// `JOBS_POLL_INTERVAL` seconds (5 by default) it claims the jobs that are due
+// by the calendar's clock, so jobs scheduled before a restart still run, and
+// several instances never run the same job at once.
+#[derive(Clone)]
+pub struct Scheduler {
+    repository: Repository,
+    calendar: Calendar,
+    poll_interval: time::Duration,
+}
+
+impl Scheduler {
+    pub fn from_env(repository: Repository, calendar: Calendar) -> Result<Self, String> {
+        let poll_interval = match env::var("JOBS_POLL_INTERVAL") {
+            Ok(value) => value
+                .parse()
+                .map_err(|err| format!("JOBS_POLL_INTERVAL: {}", err))?,
+            Err(_) => 5,
+        };
+
+        Ok(Scheduler {
+            repository,
+            calendar,
+            poll_interval: time::Duration::from_secs(poll_interval.max(1)),
+        })
+    }
+
+    pub fn spawn(self) -> JoinHandle<()> {
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(self.poll_interval);
+            interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+            loop {
+                interval.tick().await;
+                if let Err(err) = self.run_due_jobs().await {
+                    tracing::error!("Unable to run scheduled jobs: {}", err);
+                }
+            }
+        })
+    }
+
+    pub async fn run_due_jobs(&self) -> Result<(), sqlx::Error> {
+        let now = self.calendar.now();
+        let jobs = self
+            .repository
+            .claim_due_jobs(now, now + Duration::seconds(LOCK_SECS), BATCH_SIZE)
+            .await?;
+
+        for job in jobs {
+            let Some(kind) = JobKind::parse(&job.kind) else {
+                tracing::warn!("Dropping job {} of unknown kind {}", job.id, job.kind);
+                self.repository
+                    .complete_job(job.id, now, Some("unknown job kind"))
+                    .await?;
+                continue;
+            };
+
+            match self.run(kind, &job.promo_id, now).await {
+                Ok(()) => self.repository.complete_job(job.id, now, None).await?,
+                Err(err) if job.attempts >= MAX_ATTEMPTS => {
+                    tracing::error!("Giving up on job {} ({}): {}", job.id, job.kind, err);
+                    self.repository
+                        .complete_job(job.id, now, Some(&err.to_string()))
+                        .await?;
+                }
+                Err(err) => {
+                    let backoff = RETRY_BASE_SECS << (job.attempts - 1).clamp(0, 16);
+                    tracing::warn!(
+                        "Job {} ({}) failed, retrying in {}s: {}",
+                        job.id,
+                        job.kind,
+                        backoff,
+                        err
+                    );
+                    self.repository
+                        .retry_job(job.id, now + Duration::seconds(backoff), &err.to_string())
+                        .await?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    async fn run(
+        &self,
+        kind: JobKind,
+        promo_id: &str,
+        now: DateTime<Utc>,
+    ) -> Result<(), sqlx::Error> {
+        match kind {
+            // Publishing records the "published" event along with it.
+            JobKind::PublishPromo => {
+                if self.repository.publish_promo(promo_id, now).await? {
+                    tracing::info!("Promo {} published", promo_id);
+                }
+            }
+            JobKind::ExpirePromo => {
+                let Some(promo) = self
+                    .repository
+                    .retrieve_promo(promo_id, self.calendar.today())
+                    .await?
+                else {
+                    return Ok(());
+                };
+
+                // The promo may have been given more days since the job was
+                // scheduled.
+                if promo
+                    .active_until
+                    .is_some_and(|date| self.calendar.end_of(date) <= now)
+                {
+                    self.repository
+                        .record_promo_event(promo_id, "expired", now)
+                        .await?;
+                    tracing::info!("Promo {} expired", promo_id);
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+// Brings the jobs of a promo in line with its publish time and last day,
+// publishing it right away when its publish time has already come.
+pub async fn schedule_promo(
+    repository: &Repository,
+    calendar: &Calendar,
+    promo: &Promo,
+) -> Result<(), sqlx::Error> {
+    let now = calendar.now();
+
+    if promo.published_at.is_none() {
+        match promo.publish_at {
+            Some(publish_at) if publish_at > now => {
+                repository
+                    .schedule_job(JobKind::PublishPromo, &promo.promo_id, publish_at, now)
+                    .await?
+            }
+            _ => {
+                repository
+                    .cancel_job(JobKind::PublishPromo, &promo.promo_id)
+                    .await?;
+                repository.publish_promo(&promo.promo_id, now).await?;
+            }
+        }
+    }
+
+    match promo.active_until.map(|date| calendar.end_of(date)) {
+        Some(expires_at) if expires_at > now => {
+            repository
+                .schedule_job(JobKind::ExpirePromo, &promo.promo_id, expires_at, now)
+                .await
+        }
+        _ => {
+            repository
+                .cancel_job(JobKind::ExpirePromo, &promo.promo_id)
+                .await
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{schedule_promo, Scheduler};
+    use crate::{
+        calendar::Calendar,
+        clock::Clock,
+        repository::{PromoRepository, Repository},
+        testing,
+    };
+    use chrono::{DateTime, Duration, NaiveDate, Utc};
+
+    async fn event_kinds(repository: &Repository, promo_id: &str) -> Vec<String> {
+        repository
+            .retrieve_promo_events(promo_id)
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|event| event.kind)
+            .collect()
+    }
+
+    #[cfg_attr(feature = "postgres", ignore = "needs TEST_DATABASE_URL")]
+    #[tokio::test]
+    async fn promos_are_published_and_expired_as_the_clock_moves() {
+        let repository = testing::repository().await;
+        let start: DateTime<Utc> = "2025-03-01T09:00:00Z".parse().unwrap();
+        let clock = Clock::manual(start);
+        let calendar = Calendar::from_env(clock.clone()).unwrap();
+
+        let (company, _) = testing::company(&repository).await;
+        let mut promo = testing::promo(&repository, &company, 10, None, start).await;
+        promo.promo_id = uuid::Uuid::new_v4().to_string();
+        promo.published_at = None;
+        promo.publish_at = Some(start + Duration::hours(1));
+        promo.active_until = NaiveDate::from_ymd_opt(2025, 3, 2);
+        repository.create_promo(&promo, None).await.unwrap();
+        schedule_promo(&repository, &calendar, &promo)
+            .await
+            .unwrap();
+
+        let scheduler = Scheduler::from_env(repository.clone(), calendar).unwrap();
+        scheduler.run_due_jobs().await.unwrap();
+        assert!(!repository.promo_exists(&promo.promo_id).await.unwrap());
+
+        clock.advance(Duration::hours(1));
+        scheduler.run_due_jobs().await.unwrap();
+        assert!(repository.promo_exists(&promo.promo_id).await.unwrap());
+        assert_eq!(
+            event_kinds(&repository, &promo.promo_id).await,
+            ["published"]
+        );
+
+        // The expiry job was stored, so a restarted server still runs it. The
+        // promo's last day ends at midnight UTC+3.
+        drop(scheduler);
+        let scheduler = Scheduler::from_env(
+            repository.clone(),
+            Calendar::from_env(clock.clone()).unwrap(),
+        )
+        .unwrap();
+        clock.advance(Duration::hours(34) + Duration::minutes(59));
+        scheduler.run_due_jobs().await.unwrap();
+        assert_eq!(
+            event_kinds(&repository, &promo.promo_id).await,
+            ["published"]
+        );
+
+        clock.advance(Duration::minutes(1));
+        scheduler.run_due_jobs().await.unwrap();
+        scheduler.run_due_jobs().await.unwrap();
+        assert_eq!(
+            event_kinds(&repository, &promo.promo_id).await,
+            ["published", "expired"]
+        );
+    }
+
+    #[cfg_attr(feature = "postgres", ignore = "needs TEST_DATABASE_URL")]
+    #[tokio::test]
+    async fn extended_promos_do_not_expire_on_the_old_date() {
+        let repository = testing::repository().await;
+        let start: DateTime<Utc> = "2025-03-01T09:00:00Z".parse().unwrap();
+        let clock = Clock::manual(start);
+        let calendar = Calendar::from_env(clock.clone()).unwrap();
+
+        let (company, _) = testing::company(&repository).await;
+        let mut promo = testing::promo(&repository, &company, 10, None, start).await;
+        promo.active_until = NaiveDate::from_ymd_opt(2025, 3, 1);
+        repository.update_promo(&promo).await.unwrap();
+        schedule_promo(&repository, &calendar, &promo)
+            .await
+            .unwrap();
+
+        promo.active_until = NaiveDate::from_ymd_opt(2025, 3, 5);
+        repository.update_promo(&promo).await.unwrap();
+        schedule_promo(&repository, &calendar, &promo)
+            .await
+            .unwrap();
+
+        let scheduler = Scheduler::from_env(repository.clone(), calendar).unwrap();
+        clock.advance(Duration::days(1));
+        scheduler.run_due_jobs().await.unwrap();
+        assert_eq!(
+            event_kinds(&repository, &promo.promo_id).await,
+            ["published"]
+        );
+
+        clock.advance(Duration::days(4));
+        scheduler.run_due_jobs().await.unwrap();
+        assert_eq!(
+            event_kinds(&repository, &promo.promo_id).await,
+            ["published", "expired"]
+        );
+    }
+}
@@ -0,0 +1,312 @@
+use crate::{
+    auth::{encode_jwt, Audience, Claims, Role},
+    error::{AppError, SESSION_NOT_FOUND},
+    extract::ClientInfo,
+    refresh,
+    repository::{RefreshTokenRepository, UserRepository, UserSessionRepository},
+    AppState,
+};
+use chrono::{DateTime, Duration, Utc};
+use sqlx::FromRow;
+use std::env;
+use uuid::Uuid;
+
+// `last_seen_at` is only written when it is at least this much behind, so
+// that authenticated requests don't each cost an update.
+const LAST_SEEN_RESOLUTION_SECONDS: i64 = 60;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum SessionPolicy {
+    // Signing in ends the user's other sessions. This is the default because
+    // api.yml requires sign-in to invalidate every token issued before.
+    Single,
+    // Users may stay signed in on several devices at once.
+    Multiple,
+}
+
+// Read once at startup. `USER_SESSIONS` is `single` (the default) or
+// `multiple`, which departs from the API spec.
+#[derive(Clone)]
+pub struct SessionConfig {
+    pub policy: SessionPolicy,
+}
+
+impl SessionConfig {
+    pub fn from_env() -> Self {
+        let policy = match env::var("USER_SESSIONS").as_deref() {
+            Ok("multiple") => SessionPolicy::Multiple,
+            _ => SessionPolicy::Single,
+        };
+
+        SessionConfig { policy }
+    }
+}
+
+// A signed-in device of a user. The id is also the id of the session's
+// refresh token family, and the session only accepts the access token issued
+// last, so a refresh retires the previous one.
+#[derive(FromRow, Clone, Debug)]
+pub struct UserSession {
+    pub id: String,
+    pub user_id: String,
+    pub access_token_id: String,
+    pub ip_address: String,
+    pub user_agent: Option<String>,
+    pub device: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub last_seen_at: DateTime<Utc>,
+    pub expires_at: DateTime<Utc>,
+    pub revoked_at: Option<DateTime<Utc>>,
+}
+
+impl UserSession {
+    fn new(
+        id: &str,
+        user_id: &str,
+        claims: &Claims,
+        client: &ClientInfo,
+        expires_at: DateTime<Utc>,
+    ) -> Self {
+        let now = Utc::now();
+        UserSession {
+            id: id.to_string(),
+            user_id: user_id.to_string(),
+            access_token_id: claims.jti.clone(),
+            ip_address: client.ip_address.to_string(),
+            user_agent: client.user_agent.clone(),
+            device: client.user_agent.as_deref().and_then(describe_device),
+            created_at: now,
+            last_seen_at: now,
+            expires_at,
+            revoked_at: None,
+        }
+    }
+}
+
+// Opens a session for a user who just proved their identity. Returns the
+// access and refresh tokens.
+pub async fn start(
+    app_state: &AppState,
+    user_id: &str,
+    client: &ClientInfo,
+) -> Result<(String, String), AppError> {
+    if app_state.sessions.policy == SessionPolicy::Single {
+        end_all(app_state, user_id).await?;
+    }
+    app_state
+        .repository
+        .delete_stale_user_sessions(user_id, Utc::now())
+        .await?;
+
+    let id = Uuid::new_v4().to_string();
+    let (token, claims) = encode_jwt(
+        &app_state.jwt_keys,
+        Audience::User,
+        user_id,
+        Role::User,
+        Some(&id),
+    )?;
+    let (refresh_token, record) =
+        refresh::start_family(&app_state.repository, Audience::User, user_id, &id).await?;
+    app_state
+        .repository
+        .create_user_session(&UserSession::new(
+            &id,
+            user_id,
+            &claims,
+            client,
+            record.expires_at,
+        ))
+        .await?;
+
+    Ok((token, refresh_token))
+}
+
+// Exchanges a refresh token for new tokens of the same session. A family
+// issued before sessions were tracked gets its session here.
+pub async fn refresh(
+    app_state: &AppState,
+    token: &str,
+    client: &ClientInfo,
+) -> Result<(String, String), AppError> {
+    let (successor, refresh_token) = refresh::rotate(app_state, Audience::User, token).await?;
+    let user_id = &successor.subject_id;
+    if app_state
+        .repository
+        .retrieve_user_by_id(user_id)
+        .await?
+        .is_none()
+    {
+        return Err(AppError::Unauthorized);
+    }
+
+    let (token, claims) = encode_jwt(
+        &app_state.jwt_keys,
+        Audience::User,
+        user_id,
+        Role::User,
+        Some(&successor.family_id),
+    )?;
+    let mut session = UserSession::new(
+        &successor.family_id,
+        user_id,
+        &claims,
+        client,
+        successor.expires_at,
+    );
+    match app_state
+        .repository
+        .retrieve_user_session(&session.id)
+        .await?
+    {
+        Some(existing) if existing.user_id == session.user_id => {
+            session.created_at = existing.created_at;
+            if !app_state.repository.refresh_user_session(&session).await? {
+                return Err(AppError::Unauthorized);
+            }
+        }
+        Some(_) => return Err(AppError::Unauthorized),
+        None => app_state.repository.create_user_session(&session).await?,
+    }
+
+    Ok((token, refresh_token))
+}
+
+// Returns the session a user access token belongs to, provided the session
+// is live and the token is its current one.
+pub async fn authenticate(app_state: &AppState, claims: &Claims) -> Result<UserSession, AppError> {
+    let session_id = claims.sid.as_deref().ok_or(AppError::Unauthorized)?;
+    let now = Utc::now();
+    let session = match app_state
+        .repository
+        .retrieve_user_session(session_id)
+        .await?
+    {
+        Some(session)
+            if session.user_id == claims.sub
+                && session.access_token_id == claims.jti
+                && session.revoked_at.is_none()
+                && session.expires_at > now =>
+        {
+            session
+        }
+        _ => return Err(AppError::Unauthorized),
+    };
+
+    if now - session.last_seen_at >= Duration::seconds(LAST_SEEN_RESOLUTION_SECONDS) {
+        app_state
+            .repository
+            .touch_user_session(&session.id, now)
+            .await?;
+    }
+    Ok(session)
+}
+
+pub async fn revoke(app_state: &AppState, user_id: &str, id: &str) -> Result<(), AppError> {
+    let now = Utc::now();
+    if !app_state
+        .repository
+        .revoke_user_session(user_id, id, now)
+        .await?
+    {
+        return Err(AppError::NotFound(SESSION_NOT_FOUND));
+    }
+    Ok(app_state
+        .repository
+        .revoke_refresh_token_family(id, now)
+        .await?)
+}
+
+pub async fn revoke_others(
+    app_state: &AppState,
+    user_id: &str,
+    keep_id: &str,
+) -> Result<(), AppError> {
+    let now = Utc::now();
+    for id in app_state
+        .repository
+        .revoke_user_sessions(user_id, Some(keep_id), now)
+        .await?
+    {
+        app_state
+            .repository
+            .revoke_refresh_token_family(&id, now)
+            .await?;
+    }
+    Ok(())
+}
+
+pub async fn end_all(app_state: &AppState, user_id: &str) -> Result<(), AppError> {
+    app_state
+        .repository
+        .revoke_user_sessions(user_id, None, Utc::now())
+        .await?;
+    refresh::revoke(&app_state.repository, Audience::User, user_id).await
+}
+
+// A short label such as "Firefox on Windows", guessed from the user agent.
+fn describe_device(user_agent: &str) -> Option<String> {
+    const BROWSERS: [(&str, &str); 7] = [
+        ("Edg/", "Edge"),
+        ("OPR/", "Opera"),
+        ("YaBrowser/", "Yandex Browser"),
+        ("Firefox/", "Firefox"),
+        ("Chrome/", "Chrome"),
+        ("Safari/", "Safari"),
+        ("curl/", "curl"),
+    ];
+    const PLATFORMS: [(&str, &str); 7] = [
+        ("iPhone", "iPhone"),
+        ("iPad", "iPad"),
+        ("Android", "Android"),
+        ("Windows", "Windows"),
+        ("Mac OS X", "macOS"),
+        ("CrOS", "ChromeOS"),
+        ("Linux", "Linux"),
+    ];
+    let find = |names: &[(&'static str, &'static str)]| {
+        names
+            .iter()
+            .find(|(marker, _)| user_agent.contains(marker))
+            .map(|(_, name)| *name)
+    };
+
+    match (find(&BROWSERS), find(&PLATFORMS)) {
+        (Some(browser), Some(platform)) => Some(format!("{} on {}", browser, platform)),
+        (Some(name), None) | (None, Some(name)) => Some(name.to_string()),
+        (None, None) => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::testing;
+    use axum::http::StatusCode;
+    use serde_json::Value;
+
+    #[cfg_attr(feature = "postgres", ignore = "needs TEST_DATABASE_URL")]
+    #[tokio::test]
+    async fn signing_in_ends_other_sessions_by_default() {
+        let repository = testing::repository().await;
+        let user = testing::user(&repository, 25, "ru").await;
+        let state = testing::app_state(repository, "127.0.0.1:9").await;
+        let first = testing::user_token(&state, &user).await;
+        let second = testing::user_token(&state, &user).await;
+        let base_url = testing::serve(state).await;
+        let client = reqwest::Client::new();
+        let list_sessions = |token: &str| {
+            client
+                .get(format!("{}/api/user/sessions", base_url))
+                .bearer_auth(token)
+                .send()
+        };
+
+        let response = list_sessions(&first).await.unwrap();
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+        let response = list_sessions(&second).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let sessions: Value = response.json().await.unwrap();
+        assert_eq!(sessions.as_array().unwrap().len(), 1);
+    }
+}
@@ -0,0 +1,60 @@
+use super::{FailureStore, SignInFailures, StoreError};
+use chrono::{DateTime, Utc};
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+};
+
+#[derive(Clone, Default)]
+pub struct MemoryFailureStore {
+    failures: Arc<Mutex<HashMap<String, SignInFailures>>>,
+}
+
+impl FailureStore for MemoryFailureStore {
+    async fn reserve(
+        &self,
+        attempt_key: &str,
+        now: DateTime<Utc>,
+        reset_before: DateTime<Utc>,
+        locked_until: impl Fn(&SignInFailures) -> Option<DateTime<Utc>> + Send,
+    ) -> Result<Option<DateTime<Utc>>, StoreError> {
+        let mut failures = self.failures.lock().unwrap();
+        let entry = failures
+            .entry(attempt_key.to_string())
+            .or_insert(SignInFailures {
+                failures: 0,
+                last_failed_at: now,
+            });
+        if entry.last_failed_at < reset_before {
+            entry.failures = 0;
+        }
+
+        if let Some(locked_until) = locked_until(entry).filter(|until| *until > now) {
+            return Ok(Some(locked_until));
+        }
+        entry.failures += 1;
+        entry.last_failed_at = now;
+
+        Ok(None)
+    }
+
+    async fn release(&self, attempt_key: &str) -> Result<(), StoreError> {
+        if let Some(entry) = self.failures.lock().unwrap().get_mut(attempt_key) {
+            entry.failures = (entry.failures - 1).max(0);
+        }
+        Ok(())
+    }
+
+    async fn clear(&self, attempt_key: &str) -> Result<(), StoreError> {
+        self.failures.lock().unwrap().remove(attempt_key);
+        Ok(())
+    }
+
+    async fn delete_stale(&self, reset_before: DateTime<Utc>) -> Result<(), StoreError> {
+        self.failures
+            .lock()
+            .unwrap()
+            .retain(|_, failures| failures.last_failed_at >= reset_before);
+        Ok(())
+    }
+}
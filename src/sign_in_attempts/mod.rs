@@ -0,0 +1,302 @@
+use crate::{
+    auth::Audience,
+    error::{AppError, SIGN_IN_LOCKED},
+    repository::{Repository, SignInFailureRepository},
+};
+use chrono::{DateTime, Duration, Utc};
+use memory::MemoryFailureStore;
+use sqlx::FromRow;
+use std::{
+    env, fmt,
+    net::IpAddr,
+    sync::{Arc, Mutex},
+};
+
+pub mod memory;
+#[cfg(test)]
+mod tests;
+
+// Failures are forgotten once none happened for this long.
+const FAILURES_RESET_MINUTES: i64 = 60;
+const PRUNE_INTERVAL_SECS: i64 = 60;
+const MAX_LOCKOUT_SECS: i64 = 15 * 60;
+
+struct Limit {
+    free_failures: i64,
+    base_lockout_secs: i64,
+}
+
+// An address may be shared by many people, so it gets more room than a
+// single account before it is locked out.
+const ACCOUNT_LIMIT: Limit = Limit {
+    free_failures: 5,
+    base_lockout_secs: 30,
+};
+const ADDRESS_LIMIT: Limit = Limit {
+    free_failures: 20,
+    base_lockout_secs: 30,
+};
+
+impl Limit {
+    // Every failure past the free ones doubles the lockout, up to the cap.
+    fn locked_until(&self, failures: &SignInFailures) -> Option<DateTime<Utc>> {
+        let excess = failures.failures - self.free_failures;
+        if excess <= 0 {
+            return None;
+        }
+
+        let lockout_secs = self
+            .base_lockout_secs
+            .saturating_mul(1 << (excess - 1).min(16))
+            .min(MAX_LOCKOUT_SECS);
+        Some(failures.last_failed_at + Duration::seconds(lockout_secs))
+    }
+}
+
+#[derive(FromRow, Clone, Debug)]
+pub struct SignInFailures {
+    pub failures: i64,
+    pub last_failed_at: DateTime<Utc>,
+}
+
+#[derive(Debug)]
+pub enum StoreError {
+    Database(sqlx::Error),
+}
+
+impl fmt::Display for StoreError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StoreError::Database(err) => write!(f, "database error: {}", err),
+        }
+    }
+}
+
+impl From<sqlx::Error> for StoreError {
+    fn from(err: sqlx::Error) -> Self {
+        StoreError::Database(err)
+    }
+}
+
+pub trait FailureStore {
+    // Counts an attempt as failed before it is checked, so that concurrent
+    // attempts never get past the limit together. Nothing is counted while
+    // `locked_until` of the failures so far is after `now`, that time is
+    // returned instead. Failures before `reset_before` are forgotten first.
+    async fn reserve(
+        &self,
+        attempt_key: &str,
+        now: DateTime<Utc>,
+        reset_before: DateTime<Utc>,
+        locked_until: impl Fn(&SignInFailures) -> Option<DateTime<Utc>> + Send,
+    ) -> Result<Option<DateTime<Utc>>, StoreError>;
+    // Takes back one reserved failure of an attempt that succeeded.
+    async fn release(&self, attempt_key: &str) -> Result<(), StoreError>;
+    async fn clear(&self, attempt_key: &str) -> Result<(), StoreError>;
+    async fn delete_stale(&self, reset_before: DateTime<Utc>) -> Result<(), StoreError>;
+}
+
+impl FailureStore for Repository {
+    async fn reserve(
+        &self,
+        attempt_key: &str,
+        now: DateTime<Utc>,
+        reset_before: DateTime<Utc>,
+        locked_until: impl Fn(&SignInFailures) -> Option<DateTime<Utc>> + Send,
+    ) -> Result<Option<DateTime<Utc>>, StoreError> {
+        Ok(self
+            .reserve_sign_in_attempt(attempt_key, now, reset_before, locked_until)
+            .await?)
+    }
+
+    async fn release(&self, attempt_key: &str) -> Result<(), StoreError> {
+        Ok(self.release_sign_in_attempt(attempt_key).await?)
+    }
+
+    async fn clear(&self, attempt_key: &str) -> Result<(), StoreError> {
+        Ok(self.clear_sign_in_failures(attempt_key).await?)
+    }
+
+    async fn delete_stale(&self, reset_before: DateTime<Utc>) -> Result<(), StoreError> {
+        Ok(self.delete_stale_sign_in_failures(reset_before).await?)
+    }
+}
+
+// Where failures are counted is picked at startup with
+// `SIGN_IN_ATTEMPTS_STORE`: `memory` (the default) keeps them per process,
+// `database` shares them between instances and across restarts.
+#[derive(Clone)]
+pub enum AppFailureStore {
+    Memory(MemoryFailureStore),
+    Database(Repository),
+}
+
+impl FailureStore for AppFailureStore {
+    async fn reserve(
+        &self,
+        attempt_key: &str,
+        now: DateTime<Utc>,
+        reset_before: DateTime<Utc>,
+        locked_until: impl Fn(&SignInFailures) -> Option<DateTime<Utc>> + Send,
+    ) -> Result<Option<DateTime<Utc>>, StoreError> {
+        match self {
+            AppFailureStore::Memory(store) => {
+                store
+                    .reserve(attempt_key, now, reset_before, locked_until)
+                    .await
+            }
+            AppFailureStore::Database(store) => {
+                store
+                    .reserve(attempt_key, now, reset_before, locked_until)
+                    .await
+            }
+        }
+    }
+
+    async fn release(&self, attempt_key: &str) -> Result<(), StoreError> {
+        match self {
+            AppFailureStore::Memory(store) => store.release(attempt_key).await,
+            AppFailureStore::Database(store) => store.release(attempt_key).await,
+        }
+    }
+
+    async fn clear(&self, attempt_key: &str) -> Result<(), StoreError> {
+        match self {
+            AppFailureStore::Memory(store) => store.clear(attempt_key).await,
+            AppFailureStore::Database(store) => store.clear(attempt_key).await,
+        }
+    }
+
+    async fn delete_stale(&self, reset_before: DateTime<Utc>) -> Result<(), StoreError> {
+        match self {
+            AppFailureStore::Memory(store) => store.delete_stale(reset_before).await,
+            AppFailureStore::Database(store) => store.delete_stale(reset_before).await,
+        }
+    }
+}
+
+// Failed sign-ins are counted per account and per client address. Once either
+// runs out of free failures, sign-in is refused with a growing lockout before
+// the password is even hashed.
+#[derive(Clone)]
+pub struct SignInAttempts {
+    store: AppFailureStore,
+    last_pruned_at: Arc<Mutex<DateTime<Utc>>>,
+}
+
+impl SignInAttempts {
+    pub fn from_env(repository: Repository) -> Self {
+        let store = match env::var("SIGN_IN_ATTEMPTS_STORE").as_deref() {
+            Ok("database") => AppFailureStore::Database(repository),
+            _ => AppFailureStore::Memory(MemoryFailureStore::default()),
+        };
+
+        SignInAttempts {
+            store,
+            last_pruned_at: Arc::new(Mutex::new(Utc::now())),
+        }
+    }
+
+    // Fails with 429 while the account or the address is locked out.
+    // Otherwise the attempt is counted as failed for both until it succeeds.
+    pub async fn start(
+        &self,
+        audience: Audience,
+        email: &str,
+        address: IpAddr,
+    ) -> Result<SignInAttempt, AppError> {
+        self.reserve(account_key(audience, email), address).await
+    }
+
+    // Second-factor codes are counted apart from passwords: signing in with the
+    // right password again must not buy more guesses at the code.
+    pub async fn start_two_factor(
+        &self,
+        member_id: &str,
+        address: IpAddr,
+    ) -> Result<SignInAttempt, AppError> {
+        self.reserve(format!("2fa:{}", member_id), address).await
+    }
+
+    async fn reserve(
+        &self,
+        account_key: String,
+        address: IpAddr,
+    ) -> Result<SignInAttempt, AppError> {
+        let attempt = SignInAttempt {
+            attempts: self.clone(),
+            account_key,
+            address_key: format!("ip:{}", address),
+        };
+
+        let now = Utc::now();
+        let reset_before = now - Duration::minutes(FAILURES_RESET_MINUTES);
+        let mut locked_until = self
+            .store
+            .reserve(&attempt.account_key, now, reset_before, |failures| {
+                ACCOUNT_LIMIT.locked_until(failures)
+            })
+            .await?;
+        if locked_until.is_none() {
+            locked_until = self
+                .store
+                .reserve(&attempt.address_key, now, reset_before, |failures| {
+                    ADDRESS_LIMIT.locked_until(failures)
+                })
+                .await?;
+            if locked_until.is_some() {
+                self.store.release(&attempt.account_key).await?;
+            }
+        }
+
+        if let Some(locked_until) = locked_until {
+            // Rounded up so that retrying right on time is not refused.
+            let retry_after = (locked_until - now + Duration::milliseconds(999)).num_seconds();
+            return Err(AppError::TooManyRequests(
+                SIGN_IN_LOCKED,
+                retry_after as u64,
+            ));
+        }
+        Ok(attempt)
+    }
+
+    async fn prune(&self, now: DateTime<Utc>) -> Result<(), StoreError> {
+        {
+            let mut last_pruned_at = self.last_pruned_at.lock().unwrap();
+            if now - *last_pruned_at < Duration::seconds(PRUNE_INTERVAL_SECS) {
+                return Ok(());
+            }
+            *last_pruned_at = now;
+        }
+
+        self.store
+            .delete_stale(now - Duration::minutes(FAILURES_RESET_MINUTES))
+            .await
+    }
+}
+
+fn account_key(audience: Audience, email: &str) -> String {
+    format!("{}:{}", audience.as_str(), email.to_lowercase())
+}
+
+// An attempt that is dropped without being settled, e.g. because the
+// password could not be checked, stays counted as failed.
+pub struct SignInAttempt {
+    attempts: SignInAttempts,
+    account_key: String,
+    address_key: String,
+}
+
+impl SignInAttempt {
+    // The failure was counted when the attempt started.
+    pub async fn failed(self) -> Result<(), AppError> {
+        Ok(self.attempts.prune(Utc::now()).await?)
+    }
+
+    // Only the account starts over, a shared address keeps its count.
+    pub async fn succeeded(self) -> Result<(), AppError> {
+        let store = &self.attempts.store;
+        store.clear(&self.account_key).await?;
+        Ok(store.release(&self.address_key).await?)
+    }
+}
@@ -0,0 +1,129 @@
+use super::{
+    memory::MemoryFailureStore, AppFailureStore, SignInAttempts, ACCOUNT_LIMIT, ADDRESS_LIMIT,
+};
+use crate::{auth::Audience, error::AppError, testing};
+use chrono::Utc;
+use std::{
+    net::{IpAddr, Ipv4Addr},
+    sync::{Arc, Mutex},
+};
+use tokio::task::JoinSet;
+
+fn attempts(store: AppFailureStore) -> SignInAttempts {
+    SignInAttempts {
+        store,
+        last_pruned_at: Arc::new(Mutex::new(Utc::now())),
+    }
+}
+
+fn memory() -> AppFailureStore {
+    AppFailureStore::Memory(MemoryFailureStore::default())
+}
+
+async fn database() -> AppFailureStore {
+    AppFailureStore::Database(testing::repository().await)
+}
+
+// Starts `count` attempts at once, each from an address of its own unless
+// `address` is given, and returns how many were let through. None of them is
+// settled, as if all were still checking the password.
+async fn start_concurrently(
+    attempts: &SignInAttempts,
+    email: &str,
+    address: Option<IpAddr>,
+    count: u8,
+) -> i64 {
+    let mut started = JoinSet::new();
+    for i in 0..count {
+        let attempts = attempts.clone();
+        let email = email.to_string();
+        let address = address.unwrap_or(Ipv4Addr::new(10, 0, 0, i).into());
+        started.spawn(async move { attempts.start(Audience::User, &email, address).await });
+    }
+
+    let mut let_through = 0;
+    while let Some(result) = started.join_next().await {
+        match result.unwrap() {
+            Ok(_) => let_through += 1,
+            Err(AppError::TooManyRequests(..)) => {}
+            Err(err) => panic!("unexpected error: {}", err),
+        }
+    }
+    let_through
+}
+
+async fn account_limit_holds_under_concurrency(store: AppFailureStore) {
+    let attempts = attempts(store);
+    let let_through = start_concurrently(&attempts, "race@example.com", None, 40).await;
+    // The attempt after the last free failure is still let through, the
+    // lockout starts once it fails too.
+    assert_eq!(let_through, ACCOUNT_LIMIT.free_failures + 1);
+}
+
+async fn address_limit_holds_under_concurrency(store: AppFailureStore) {
+    let attempts = attempts(store);
+    let address = Some(Ipv4Addr::new(10, 0, 1, 1).into());
+    let mut let_through = 0;
+    for i in 0..8 {
+        let email = format!("user-{}@example.com", i);
+        let_through += start_concurrently(&attempts, &email, address, 5).await;
+    }
+    assert_eq!(let_through, ADDRESS_LIMIT.free_failures + 1);
+}
+
+async fn successes_are_not_counted(store: AppFailureStore) {
+    let attempts = attempts(store);
+    let address: IpAddr = Ipv4Addr::new(10, 0, 2, 1).into();
+
+    for _ in 0..ACCOUNT_LIMIT.free_failures {
+        let attempt = attempts
+            .start(Audience::User, "user@example.com", address)
+            .await
+            .unwrap();
+        attempt.failed().await.unwrap();
+    }
+    // A success clears the account, and does not count for the address.
+    for _ in 0..ADDRESS_LIMIT.free_failures * 2 {
+        let attempt = attempts
+            .start(Audience::User, "user@example.com", address)
+            .await
+            .unwrap();
+        attempt.succeeded().await.unwrap();
+    }
+
+    let let_through = start_concurrently(&attempts, "user@example.com", Some(address), 20).await;
+    assert_eq!(let_through, ACCOUNT_LIMIT.free_failures + 1);
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 8)]
+async fn concurrent_attempts_do_not_outrun_the_account_limit() {
+    account_limit_holds_under_concurrency(memory()).await;
+}
+
+#[cfg_attr(feature = "postgres", ignore = "needs TEST_DATABASE_URL")]
+#[tokio::test(flavor = "multi_thread", worker_threads = 8)]
+async fn concurrent_attempts_do_not_outrun_the_account_limit_in_the_database() {
+    account_limit_holds_under_concurrency(database().await).await;
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 8)]
+async fn concurrent_attempts_do_not_outrun_the_address_limit() {
+    address_limit_holds_under_concurrency(memory()).await;
+}
+
+#[cfg_attr(feature = "postgres", ignore = "needs TEST_DATABASE_URL")]
+#[tokio::test(flavor = "multi_thread", worker_threads = 8)]
+async fn concurrent_attempts_do_not_outrun_the_address_limit_in_the_database() {
+    address_limit_holds_under_concurrency(database().await).await;
+}
+
+#[tokio::test]
+async fn successful_attempts_are_not_counted() {
+    successes_are_not_counted(memory()).await;
+}
+
+#[cfg_attr(feature = "postgres", ignore = "needs TEST_DATABASE_URL")]
+#[tokio::test]
+async fn successful_attempts_are_not_counted_in_the_database() {
+    successes_are_not_counted(database().await).await;
+}
@@ -0,0 +1,205 @@
+// Fixtures shared by the tests. Every test gets a database of its own: a new
+// file with the `sqlite` backend, or a new schema in the database named by
+// `TEST_DATABASE_URL` with the `postgres` one.
+use crate::{
+    antifraud::AntifraudClient,
+    auth::{keys::JwtKeys, password::PasswordHasher, Role},
+    business::{
+        auth::{sign_in::issue_tokens, Company, CompanyMember},
+        promo::{Promo, Target},
+    },
+    calendar::Calendar,
+    clock::Clock,
+    email_verification::EmailVerificationConfig,
+    extract::ClientInfo,
+    mailer::{memory::MemoryMailer, AppMailer},
+    repository::{CompanyRepository, PromoRepository, Repository, UserRepository},
+    revocation::RevokedTokens,
+    routes,
+    sessions::{self, SessionConfig},
+    sign_in_attempts::SignInAttempts,
+    user::{User, UserTargetSettings},
+    validation::ValidationRules,
+    AppState,
+};
+use chrono::{DateTime, Utc};
+use sqlx::types::Json;
+use std::net::{Ipv4Addr, SocketAddr};
+use tokio::net::TcpListener;
+use uuid::Uuid;
+
+// Tests that need a database are marked
+// `#[cfg_attr(feature = "postgres", ignore = "needs TEST_DATABASE_URL")]`, so
+// with the Postgres backend they show up as ignored unless run with
+// `--include-ignored`, which then requires the variable.
+pub async fn repository() -> Repository {
+    #[cfg(feature = "postgres")]
+    {
+        let database_url = std::env::var("TEST_DATABASE_URL")
+            .expect("TEST_DATABASE_URL must name a database to run the database tests in");
+        Repository::connect_isolated(&database_url)
+            .await
+            .expect("Unable to set up the test schema")
+    }
+
+    #[cfg(not(feature = "postgres"))]
+    {
+        let path = std::env::temp_dir().join(format!("prod_project-test-{}.db", Uuid::new_v4()));
+        Repository::connect(&format!("sqlite://{}", path.display()))
+            .await
+            .expect("Unable to set up the test database")
+    }
+}
+
+pub async fn user(repository: &Repository, age: i8, country: &str) -> User {
+    let user = User {
+        id: Uuid::new_v4().to_string(),
+        name: "Test".to_string(),
+        surname: "User".to_string(),
+        email: format!("{}@example.com", Uuid::new_v4().simple()),
+        avatar_url: None,
+        other: Json(UserTargetSettings {
+            age,
+            country: country.to_string(),
+        }),
+        password_hash: String::new(),
+        email_verified_at: None,
+    };
+    repository.create_user(&user).await.unwrap();
+    user
+}
+
+pub async fn company(repository: &Repository) -> (Company, CompanyMember) {
+    let company = Company {
+        id: Uuid::new_v4().to_string(),
+        name: "Test Company".to_string(),
+    };
+    let owner = CompanyMember {
+        id: Uuid::new_v4().to_string(),
+        company_id: company.id.clone(),
+        name: company.name.clone(),
+        email: format!("{}@example.com", Uuid::new_v4().simple()),
+        password_hash: String::new(),
+        role: Role::Owner.as_str().to_string(),
+        email_verified_at: None,
+    };
+    repository.create_company(&company, &owner).await.unwrap();
+    (company, owner)
+}
+
+// A published promo without dates or targeting. UNIQUE promos get `codes`,
+// COMMON ones are given the code "COMMON".
+pub async fn promo(
+    repository: &Repository,
+    company: &Company,
+    max_count: i32,
+    codes: Option<&[String]>,
+    now: DateTime<Utc>,
+) -> Promo {
+    let promo = Promo {
+        promo_id: Uuid::new_v4().to_string(),
+        company_id: company.id.clone(),
+        company_name: company.name.clone(),
+        description: "A promo made by a test".to_string(),
+        image_url: None,
+        target: Json(any_target()),
+        max_count,
+        active_from: None,
+        active_until: None,
+        mode: if codes.is_some() { "UNIQUE" } else { "COMMON" }.to_string(),
+        promo_common: if codes.is_some() {
+            None
+        } else {
+            Some("COMMON".to_string())
+        },
+        create_date: now,
+        used_count: 0,
+        publish_at: None,
+        published_at: Some(now),
+        active: false,
+    };
+    repository.create_promo(&promo, codes).await.unwrap();
+    promo
+}
+
+// A published COMMON promo of a company of its own, for tests of what users
+// get to see.
+pub async fn targeted_promo(
+    repository: &Repository,
+    description: &str,
+    target: Target,
+    create_date: DateTime<Utc>,
+) -> Promo {
+    let (company, _) = company(repository).await;
+    let mut promo = promo(repository, &company, 10, None, create_date).await;
+    promo.description = description.to_string();
+    promo.target = Json(target);
+    repository.update_promo(&promo).await.unwrap();
+    promo
+}
+
+pub fn any_target() -> Target {
+    Target {
+        age_from: None,
+        age_until: None,
+        country: None,
+        categories: None,
+    }
+}
+
+// The state `main` would build, with the defaults of an empty environment,
+// except that mail is kept in memory for `mailbox`.
+pub async fn app_state(repository: Repository, antifraud_address: &str) -> AppState {
+    AppState {
+        revoked_tokens: RevokedTokens::load(repository.clone()).await.unwrap(),
+        antifraud: AntifraudClient::new(antifraud_address, repository.clone()),
+        jwt_keys: JwtKeys::from_secret("test secret").unwrap(),
+        password_hasher: PasswordHasher::from_env().unwrap(),
+        validation_rules: ValidationRules::from_env().unwrap(),
+        mailer: AppMailer::Memory(MemoryMailer::default()),
+        email_verification: EmailVerificationConfig::from_env(),
+        sign_in_attempts: SignInAttempts::from_env(repository.clone()),
+        sessions: SessionConfig::from_env(),
+        calendar: Calendar::from_env(Clock::System).unwrap(),
+        repository,
+    }
+}
+
+// The mail sent through a state made by `app_state`.
+pub fn mailbox(state: &AppState) -> MemoryMailer {
+    match &state.mailer {
+        AppMailer::Memory(mailer) => mailer.clone(),
+        _ => panic!("the state keeps no mail in memory"),
+    }
+}
+
+// Serves the app on a free local port and returns its base URL.
+pub async fn serve(state: AppState) -> String {
+    let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0)).await.unwrap();
+    let address = listener.local_addr().unwrap();
+    let app = routes::app(state).await;
+    tokio::spawn(async move {
+        axum::serve(
+            listener,
+            app.into_make_service_with_connect_info::<SocketAddr>(),
+        )
+        .await
+        .unwrap();
+    });
+    format!("http://{}", address)
+}
+
+// An access token of a fresh session, as sign-in would hand out.
+pub async fn user_token(state: &AppState, user: &User) -> String {
+    let client = ClientInfo {
+        ip_address: Ipv4Addr::LOCALHOST.into(),
+        user_agent: None,
+    };
+    sessions::start(state, &user.id, &client).await.unwrap().0
+}
+
+// An access token of the member, as sign-in would hand out.
+pub async fn member_token(state: &AppState, member: &CompanyMember) -> String {
+    let tokens = issue_tokens(state, member).await.unwrap();
+    tokens["token"].as_str().unwrap().to_string()
+}
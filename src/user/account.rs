@@ -0,0 +1,279 @@
+use crate::{
+    auth::Audience,
+    error::AppError,
+    extract::{AppJson, ClientInfo},
+    repository::{UserRepository, UserSessionRepository},
+    user::{User, UserProfile},
+    validation::{Validate, Validator},
+    AppState,
+};
+use axum::{
+    extract::State,
+    http::{header, HeaderValue},
+    response::{IntoResponse, Response},
+    Extension, Json,
+};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use sqlx::FromRow;
+
+#[derive(Serialize, FromRow)]
+pub struct ExportedLike {
+    pub promo_id: String,
+    pub company_name: String,
+    pub description: String,
+    pub liked_at: DateTime<Utc>,
+}
+
+#[derive(Serialize, FromRow)]
+pub struct ExportedComment {
+    pub id: String,
+    pub promo_id: String,
+    pub text: String,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Serialize, FromRow)]
+pub struct ExportedActivation {
+    pub promo_id: String,
+    pub company_name: String,
+    pub description: String,
+    pub activated_at: DateTime<Utc>,
+}
+
+#[derive(Deserialize)]
+pub struct DeleteAccount {
+    pub password: String,
+}
+
+impl Validate for DeleteAccount {
+    fn validate(&self, v: &mut Validator) {
+        v.current_password("password", &self.password);
+    }
+}
+
+// Everything stored about the user, served as a downloadable JSON file.
+pub async fn export_data(
+    State(app_state): State<AppState>,
+    Extension(user): Extension<User>,
+) -> Result<Response, AppError> {
+    let repository = &app_state.repository;
+    let now = Utc::now();
+    let sessions: Vec<Value> = repository
+        .retrieve_user_sessions(&user.id, now)
+        .await?
+        .into_iter()
+        .map(|session| {
+            json!({
+                "device": session.device,
+                "ip_address": session.ip_address,
+                "user_agent": session.user_agent,
+                "created_at": session.created_at,
+                "last_seen_at": session.last_seen_at
+            })
+        })
+        .collect();
+
+    let archive = json!({
+        "exported_at": now,
+        "id": user.id,
+        "email_verified_at": user.email_verified_at,
+        "likes": repository.retrieve_user_likes(&user.id).await?,
+        "comments": repository.retrieve_user_comments(&user.id).await?,
+        "activations": repository.retrieve_user_activations(&user.id).await?,
+        "sessions": sessions,
+        "profile": UserProfile::from(user),
+    });
+
+    let mut response = Json(archive).into_response();
+    response.headers_mut().insert(
+        header::CONTENT_DISPOSITION,
+        HeaderValue::from_static("attachment; filename=\"personal-data.json\""),
+    );
+    Ok(response)
+}
+
+// Anonymizes the account instead of dropping it, see `delete_user`. The
+// password is asked for again because the deletion can't be undone, and
+// guesses count against the same limits as at sign-in.
+pub async fn delete_account(
+    State(app_state): State<AppState>,
+    Extension(user): Extension<User>,
+    client: ClientInfo,
+    AppJson(delete_account): AppJson<DeleteAccount>,
+) -> Result<Json<Value>, AppError> {
+    app_state.validation_rules.check(&delete_account)?;
+    let attempt = app_state
+        .sign_in_attempts
+        .start(Audience::User, &user.email, client.ip_address)
+        .await?;
+    if !app_state
+        .password_hasher
+        .verify(&delete_account.password, &user.password_hash)
+        .await?
+    {
+        attempt.failed().await?;
+        return Err(AppError::InvalidCredentials);
+    }
+
+    app_state.repository.delete_user(&user, Utc::now()).await?;
+    // The freed email starts with a clean count.
+    attempt.succeeded().await?;
+
+    Ok(Json(json!({
+        "status": "ok"
+    })))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        repository::{ActivationRepository, CommentRepository, PromoRepository, UserRepository},
+        testing,
+        user::User,
+        AppState,
+    };
+    use axum::http::StatusCode;
+    use chrono::Utc;
+    use serde_json::{json, Value};
+
+    const PASSWORD: &str = "Account password 1!";
+
+    // A user who has liked, commented on and activated one promo.
+    async fn active_user(state: &AppState) -> (User, String) {
+        let repository = &state.repository;
+        let mut user = testing::user(repository, 30, "ru").await;
+        user.password_hash = state.password_hasher.hash(PASSWORD).await.unwrap();
+        repository.update_user(&user).await.unwrap();
+        let (company, _) = testing::company(repository).await;
+        let promo = testing::promo(repository, &company, 10, None, Utc::now()).await;
+        repository
+            .add_like(&promo.promo_id, &user.id)
+            .await
+            .unwrap();
+        repository
+            .create_comment("comment-id", &promo.promo_id, &user.id, "Works as promised")
+            .await
+            .unwrap();
+        repository
+            .activate_promo(&promo.promo_id, &user.id, "ru", Utc::now())
+            .await
+            .unwrap()
+            .unwrap();
+        (user, promo.promo_id)
+    }
+
+    #[cfg_attr(feature = "postgres", ignore = "needs TEST_DATABASE_URL")]
+    #[tokio::test]
+    async fn export_holds_the_users_data() {
+        let repository = testing::repository().await;
+        let state = testing::app_state(repository, "127.0.0.1:9").await;
+        let (user, promo_id) = active_user(&state).await;
+        let access_token = testing::user_token(&state, &user).await;
+        let base_url = testing::serve(state).await;
+
+        let response = reqwest::Client::new()
+            .get(format!("{}/api/user/profile/export", base_url))
+            .bearer_auth(&access_token)
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert!(response.headers()["Content-Disposition"]
+            .to_str()
+            .unwrap()
+            .starts_with("attachment"));
+        let archive: Value = response.json().await.unwrap();
+        assert_eq!(archive["id"], user.id.as_str());
+        assert_eq!(archive["profile"]["email"], user.email.as_str());
+        assert_eq!(archive["likes"][0]["promo_id"], promo_id.as_str());
+        assert_eq!(archive["comments"][0]["text"], "Works as promised");
+        assert_eq!(archive["activations"][0]["promo_id"], promo_id.as_str());
+        assert_eq!(archive["sessions"].as_array().unwrap().len(), 1);
+        assert!(archive.to_string().find("password").is_none());
+    }
+
+    #[cfg_attr(feature = "postgres", ignore = "needs TEST_DATABASE_URL")]
+    #[tokio::test]
+    async fn deletion_anonymizes_the_user_but_keeps_promo_stats() {
+        let repository = testing::repository().await;
+        let state = testing::app_state(repository.clone(), "127.0.0.1:9").await;
+        let (user, promo_id) = active_user(&state).await;
+        let access_token = testing::user_token(&state, &user).await;
+        let base_url = testing::serve(state).await;
+
+        let response = reqwest::Client::new()
+            .delete(format!("{}/api/user/profile", base_url))
+            .bearer_auth(&access_token)
+            .json(&json!({ "password": PASSWORD }))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let response = reqwest::Client::new()
+            .get(format!("{}/api/user/profile", base_url))
+            .bearer_auth(&access_token)
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+        assert!(repository
+            .retrieve_user_by_id(&user.id)
+            .await
+            .unwrap()
+            .is_none());
+        assert!(repository
+            .retrieve_user_by_email(&user.email)
+            .await
+            .unwrap()
+            .is_none());
+
+        // The anonymous row keeps what the user did.
+        assert_eq!(repository.count_activations(&user.id).await.unwrap(), 1);
+        let today = Utc::now().date_naive();
+        let promo = repository
+            .retrieve_promo(&promo_id, today)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(promo.used_count, 1);
+        let viewer = testing::user(&repository, 30, "ru").await;
+        let seen = repository
+            .retrieve_promo_for_user(&viewer.id, &promo_id, today)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(seen.like_count, 1);
+        assert_eq!(seen.comment_count, 0);
+    }
+
+    #[cfg_attr(feature = "postgres", ignore = "needs TEST_DATABASE_URL")]
+    #[tokio::test]
+    async fn password_guesses_are_limited() {
+        let repository = testing::repository().await;
+        let state = testing::app_state(repository, "127.0.0.1:9").await;
+        let (user, _) = active_user(&state).await;
+        let access_token = testing::user_token(&state, &user).await;
+        let base_url = testing::serve(state).await;
+        let delete = |password: &'static str| {
+            reqwest::Client::new()
+                .delete(format!("{}/api/user/profile", base_url))
+                .bearer_auth(&access_token)
+                .json(&json!({ "password": password }))
+                .send()
+        };
+
+        let mut statuses = vec![];
+        for _ in 0..8 {
+            statuses.push(delete("Wrong password 1!").await.unwrap().status());
+        }
+        assert_eq!(statuses[..5], [StatusCode::UNAUTHORIZED; 5]);
+        assert_eq!(statuses[7], StatusCode::TOO_MANY_REQUESTS);
+
+        // Locked out, the right password is not even checked.
+        let response = delete(PASSWORD).await.unwrap();
+        assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+    }
+}
@@ -1,2 +1,6 @@
+pub mod password_reset;
+pub mod refresh;
 pub mod sign_in;
+pub mod sign_out;
 pub mod sign_up;
+pub mod verify_email;
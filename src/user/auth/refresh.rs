@@ -0,0 +1,27 @@
+use crate::{
+    error::AppError,
+    extract::{AppJson, ClientInfo},
+    sessions, AppState,
+};
+use axum::{extract::State, Json};
+use serde::Deserialize;
+use serde_json::{json, Value};
+
+#[derive(Deserialize)]
+pub struct RefreshData {
+    pub refresh_token: String,
+}
+
+pub async fn refresh(
+    State(app_state): State<AppState>,
+    client: ClientInfo,
+    AppJson(refresh_data): AppJson<RefreshData>,
+) -> Result<Json<Value>, AppError> {
+    let (token, refresh_token) =
+        sessions::refresh(&app_state, &refresh_data.refresh_token, &client).await?;
+
+    Ok(Json(json!({
+        "token": token,
+        "refresh_token": refresh_token
+    })))
+}
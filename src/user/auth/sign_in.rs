@@ -1,9 +1,14 @@
-use axum::{extract::State, http::StatusCode, Json};
+use axum::{extract::State, Json};
 use serde::Deserialize;
 use serde_json::{json, Value};
 
 use crate::{
-    user::middlewares::authorize::{encode_jwt, retrieve_user_by_email, verify_password},
+    auth::Audience,
+    error::AppError,
+    extract::{AppJson, ClientInfo},
+    repository::UserRepository,
+    sessions,
+    validation::{Validate, Validator},
     AppState,
 };
 
@@ -13,24 +18,96 @@ pub struct SignInData {
     pub password: String,
 }
 
+// Only bounds are checked: the account may predate the current password
+// policy.
+impl Validate for SignInData {
+    fn validate(&self, v: &mut Validator) {
+        v.length("email", &self.email, 8..=120);
+        v.current_password("password", &self.password);
+    }
+}
+
 pub async fn sign_in(
     State(app_state): State<AppState>,
-    Json(sign_in_data): Json<SignInData>,
-) -> Result<Json<Value>, StatusCode> {
-    let user = match retrieve_user_by_email(&app_state.pool, &sign_in_data.email).await {
-        Some(user) => user,
-        None => return Err(StatusCode::UNAUTHORIZED),
+    client: ClientInfo,
+    AppJson(sign_in_data): AppJson<SignInData>,
+) -> Result<Json<Value>, AppError> {
+    app_state.validation_rules.check(&sign_in_data)?;
+
+    let attempt = app_state
+        .sign_in_attempts
+        .start(Audience::User, &sign_in_data.email, client.ip_address)
+        .await?;
+
+    let mut user = match app_state
+        .repository
+        .retrieve_user_by_email(&sign_in_data.email)
+        .await?
+    {
+        Some(user)
+            if app_state
+                .password_hasher
+                .verify(&sign_in_data.password, &user.password_hash)
+                .await? =>
+        {
+            user
+        }
+        _ => {
+            attempt.failed().await?;
+            return Err(AppError::InvalidCredentials);
+        }
     };
+    attempt.succeeded().await?;
 
-    if !verify_password(&sign_in_data.password, &user.password_hash)
-        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+    if let Some(password_hash) = app_state
+        .password_hasher
+        .rehash(&sign_in_data.password, &user.password_hash)
+        .await?
     {
-        return Err(StatusCode::UNAUTHORIZED);
+        user.password_hash = password_hash;
+        app_state.repository.update_user(&user).await?;
     }
 
-    let token = encode_jwt(user.email).map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    let (token, refresh_token) = sessions::start(&app_state, &user.id, &client).await?;
 
     Ok(Json(json!({
-        "token": token
+        "token": token,
+        "refresh_token": refresh_token
     })))
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::{repository::UserRepository, testing};
+    use axum::http::StatusCode;
+    use serde_json::json;
+
+    #[cfg_attr(feature = "postgres", ignore = "needs TEST_DATABASE_URL")]
+    #[tokio::test]
+    async fn bcrypt_hashes_are_upgraded_on_sign_in() {
+        let repository = testing::repository().await;
+        let mut user = testing::user(&repository, 30, "ru").await;
+        user.password_hash = bcrypt::hash("Legacy password 1!", 4).unwrap();
+        repository.update_user(&user).await.unwrap();
+        let state = testing::app_state(repository.clone(), "127.0.0.1:9").await;
+        let base_url = testing::serve(state).await;
+        let sign_in = || {
+            reqwest::Client::new()
+                .post(format!("{}/api/user/auth/sign-in", base_url))
+                .json(&json!({ "email": user.email, "password": "Legacy password 1!" }))
+                .send()
+        };
+
+        assert_eq!(sign_in().await.unwrap().status(), StatusCode::OK);
+        let password_hash = repository
+            .retrieve_user_by_id(&user.id)
+            .await
+            .unwrap()
+            .unwrap()
+            .password_hash;
+        assert!(password_hash.starts_with("$argon2id$"), "{}", password_hash);
+
+        // The new hash takes the same password.
+        assert_eq!(sign_in().await.unwrap().status(), StatusCode::OK);
+    }
+}
@@ -0,0 +1,18 @@
+use crate::{
+    error::AppError,
+    sessions::{self, UserSession},
+    AppState,
+};
+use axum::{extract::State, Extension, Json};
+use serde_json::{json, Value};
+
+pub async fn sign_out(
+    State(app_state): State<AppState>,
+    Extension(session): Extension<UserSession>,
+) -> Result<Json<Value>, AppError> {
+    sessions::revoke(&app_state, &session.user_id, &session.id).await?;
+
+    Ok(Json(json!({
+        "status": "ok"
+    })))
+}
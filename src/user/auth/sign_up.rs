@@ -1,54 +1,52 @@
 use crate::{
-    user::{middlewares::authorize::hash_password, CreateUser, User},
+    auth::Audience,
+    email_verification,
+    error::{AppError, EMAIL_ALREADY_REGISTERED},
+    extract::AppJson,
+    repository::{Repository, UserRepository},
+    user::{CreateUser, User},
     AppState,
 };
-use axum::{extract::State, http::StatusCode, Json};
-use sqlx::PgPool;
+use axum::{extract::State, Json};
 use uuid::Uuid;
 
 pub async fn sign_up(
     State(app_state): State<AppState>,
-    Json(create_user): Json<CreateUser>,
-) -> Result<Json<String>, StatusCode> {
-    if !create_user.is_valid() {
-        return Err(StatusCode::BAD_REQUEST);
-    }
+    AppJson(create_user): AppJson<CreateUser>,
+) -> Result<Json<String>, AppError> {
+    app_state.validation_rules.check(&create_user)?;
 
-    if !is_unique_user(&app_state.pool, &create_user).await {
-        return Err(StatusCode::CONFLICT);
+    if !is_unique_user(&app_state.repository, &create_user).await? {
+        return Err(AppError::Conflict(EMAIL_ALREADY_REGISTERED));
     }
 
-    let id = Uuid::new_v4().to_string();
+    let user = User {
+        id: Uuid::new_v4().to_string(),
+        name: create_user.name,
+        surname: create_user.surname,
+        email: create_user.email,
+        avatar_url: create_user.avatar_url,
+        other: create_user.other,
+        password_hash: app_state
+            .password_hasher
+            .hash(&create_user.password)
+            .await?,
+        email_verified_at: None,
+    };
 
-    sqlx::query(
-        r#"
-        INSERT INTO users VALUES (?, ?, ?, ?, ?, ?, ?)
-        "#,
-    )
-    .bind(&id)
-    .bind(create_user.name)
-    .bind(create_user.surname)
-    .bind(create_user.email)
-    .bind(create_user.avatar_url)
-    .bind(create_user.other)
-    .bind(hash_password(&create_user.password).unwrap())
-    .execute(&app_state.pool)
-    .await
-    .unwrap();
+    app_state.repository.create_user(&user).await?;
+    email_verification::send_verification(&app_state, Audience::User, &user.id, &user.email)
+        .await?;
 
-    Ok(Json(id))
+    Ok(Json(user.id))
 }
 
-async fn is_unique_user(pool: &PgPool, sign_up_data: &CreateUser) -> bool {
-    let row: Option<User> = sqlx::query_as(
-        r#"
-        SELECT * FROM users WHERE email = ?
-        "#,
-    )
-    .bind(&sign_up_data.email)
-    .fetch_optional(pool)
-    .await
-    .unwrap();
-
-    row.is_none()
+async fn is_unique_user(
+    repository: &Repository,
+    sign_up_data: &CreateUser,
+) -> Result<bool, AppError> {
+    Ok(repository
+        .retrieve_user_by_email(&sign_up_data.email)
+        .await?
+        .is_none())
 }
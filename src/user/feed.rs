@@ -1,42 +1,151 @@
-use super::{promo, User};
+use super::User;
 use crate::{
-    business::promo::{Promo, PromoForUser},
+    error::AppError,
+    extract::AppQuery,
+    pagination::Pagination,
+    repository::PromoRepository,
+    validation::{Validate, Validator},
     AppState,
 };
 use axum::{
-    extract::{Query, State},
-    http::StatusCode,
+    extract::State,
+    http::{HeaderMap, HeaderValue, StatusCode},
+    response::{IntoResponse, Response},
     Extension, Json,
 };
+use serde::Deserialize;
+
+// Narrows the feed beyond target matching. Absent fields filter nothing.
+#[derive(Deserialize)]
+pub struct FeedFilter {
+    pub category: Option<String>,
+    pub active: Option<bool>,
+}
+
+impl Validate for FeedFilter {
+    fn validate(&self, v: &mut Validator) {
+        if let Some(ref category) = self.category {
+            v.length("category", category, 2..=20);
+        }
+    }
+}
 
 pub async fn promo_feed(
     State(app_state): State<AppState>,
     Extension(user): Extension<User>,
-    Query(params): Query<Vec<(String, String)>>,
-) -> Result<Json<Vec<PromoForUser>>, StatusCode> {
-    let promos: Vec<Promo> = sqlx::query_as(
-        r#"
-            SELECT * FROM promos
-        "#,
-    )
-    .fetch_all(&app_state.pool)
-    .await
-    .unwrap();
-    let promos_for_user = promos
-        .into_iter()
-        .map(|promo| PromoForUser {
-            promo_id: promo.promo_id,
-            company_id: promo.company_id,
-            company_name: promo.company_name,
-            description: promo.description,
-            image_url: promo.image_url,
-            active: promo.active,
-            is_activated_by_user: promo.activated_users.contains(&user.email),
-            like_count: promo.likes.0.len() as i32,
-            is_liked_by_user: promo.likes.0.contains(&user.email),
-            comment_count: promo.comments.0.len() as i32,
-        })
-        .collect();
-
-    Ok(Json(promos_for_user))
+    AppQuery(pagination): AppQuery<Pagination>,
+    AppQuery(filter): AppQuery<FeedFilter>,
+) -> Result<Response, AppError> {
+    app_state.validation_rules.check(&filter)?;
+
+    let today = app_state.calendar.today();
+    let total = app_state
+        .repository
+        .count_feed(&user.id, &filter, today)
+        .await?;
+
+    let promos_for_user = app_state
+        .repository
+        .retrieve_feed(
+            &user.id,
+            &filter,
+            today,
+            pagination.limit as i64,
+            pagination.offset as i64,
+        )
+        .await?;
+
+    let mut headers = HeaderMap::new();
+    headers.insert("X-Total-Count", HeaderValue::from(total));
+    Ok((StatusCode::OK, headers, Json(promos_for_user)).into_response())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        business::promo::Target,
+        testing::{self, any_target, targeted_promo},
+    };
+    use axum::http::StatusCode;
+    use chrono::{Duration, Utc};
+    use serde_json::Value;
+
+    #[cfg_attr(feature = "postgres", ignore = "needs TEST_DATABASE_URL")]
+    #[tokio::test]
+    async fn feed_shows_the_promos_targeted_at_the_user() {
+        let repository = testing::repository().await;
+        let user = testing::user(&repository, 25, "ru").await;
+        let now = Utc::now();
+        let mut expected = vec![];
+        for (minutes_ago, target) in [
+            (3, any_target()),
+            (
+                2,
+                Target {
+                    age_from: Some(25),
+                    age_until: Some(25),
+                    ..any_target()
+                },
+            ),
+            (
+                1,
+                Target {
+                    age_from: Some(18),
+                    country: Some("RU".to_string()),
+                    ..any_target()
+                },
+            ),
+        ] {
+            let promo = targeted_promo(
+                &repository,
+                "Meant for this user",
+                target,
+                now - Duration::minutes(minutes_ago),
+            )
+            .await;
+            expected.push(promo.promo_id);
+        }
+        // Newest first.
+        expected.reverse();
+        for target in [
+            Target {
+                age_from: Some(26),
+                ..any_target()
+            },
+            Target {
+                age_until: Some(24),
+                ..any_target()
+            },
+            Target {
+                country: Some("us".to_string()),
+                ..any_target()
+            },
+        ] {
+            targeted_promo(&repository, "Meant for someone else", target, now).await;
+        }
+        let state = testing::app_state(repository, "127.0.0.1:9").await;
+        let access_token = testing::user_token(&state, &user).await;
+        let base_url = testing::serve(state).await;
+        let feed = |limit: &'static str, offset: &'static str| {
+            reqwest::Client::new()
+                .get(format!("{}/api/user/feed", base_url))
+                .bearer_auth(&access_token)
+                .query(&[("limit", limit), ("offset", offset)])
+                .send()
+        };
+
+        let mut listed = vec![];
+        for offset in ["0", "2"] {
+            let response = feed("2", offset).await.unwrap();
+            assert_eq!(response.status(), StatusCode::OK);
+            // The total of the whole feed, not of the page.
+            assert_eq!(response.headers()["X-Total-Count"], "3");
+            let page: Vec<Value> = response.json().await.unwrap();
+            listed.extend(
+                page.iter()
+                    .map(|promo| promo["promo_id"].as_str().unwrap().to_string()),
+            );
+        }
+        assert_eq!(listed, expected);
+    }
 }
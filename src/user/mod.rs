@@ -1,12 +1,14 @@
-use regex::Regex;
+use crate::validation::{Validate, Validator};
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use sqlx::prelude::FromRow;
 
+pub mod account;
 pub mod auth;
 pub mod feed;
-pub mod middlewares;
 pub mod profile;
 pub mod promo;
+pub mod sessions;
 
 #[derive(Serialize, Deserialize, FromRow)]
 pub struct UserProfile {
@@ -19,13 +21,26 @@ pub struct UserProfile {
 
 #[derive(Serialize, Deserialize, FromRow, Clone)]
 pub struct User {
-    id: String,
-    name: String,
-    surname: String,
-    email: String,
-    avatar_url: Option<String>,
-    other: sqlx::types::Json<UserTargetSettings>,
-    password_hash: String,
+    pub id: String,
+    pub name: String,
+    pub surname: String,
+    pub email: String,
+    pub avatar_url: Option<String>,
+    pub other: sqlx::types::Json<UserTargetSettings>,
+    pub password_hash: String,
+    pub email_verified_at: Option<DateTime<Utc>>,
+}
+
+impl From<User> for UserProfile {
+    fn from(user: User) -> Self {
+        UserProfile {
+            name: user.name,
+            surname: user.surname,
+            email: user.email,
+            avatar_url: user.avatar_url,
+            other: user.other,
+        }
+    }
 }
 
 #[derive(Serialize, Deserialize)]
@@ -36,42 +51,20 @@ pub struct PatchUser {
     password: Option<String>,
 }
 
-impl PatchUser {
-    pub fn is_valid(&self) -> bool {
+impl Validate for PatchUser {
+    fn validate(&self, v: &mut Validator) {
         if let Some(ref name) = self.name {
-            if name.chars().count() < 1 || name.chars().count() > 100 {
-                return false;
-            }
+            v.length("name", name, 1..=100);
         }
         if let Some(ref surname) = self.surname {
-            if surname.chars().count() < 1 || surname.chars().count() > 120 {
-                return false;
-            }
+            v.length("surname", surname, 1..=120);
         }
-
-        if self.avatar_url.is_some() && self.avatar_url.as_ref().unwrap().len() > 350 {
-            return false;
+        if let Some(ref avatar_url) = self.avatar_url {
+            v.url("avatar_url", avatar_url);
         }
         if let Some(ref password) = self.password {
-            let mut has_whitespace = false;
-            let mut has_upper = false;
-            let mut has_lower = false;
-            let mut has_digit = false;
-            for c in password.chars() {
-                has_whitespace |= c.is_whitespace();
-                has_lower |= c.is_lowercase();
-                has_upper |= c.is_uppercase();
-                has_digit |= c.is_digit(10);
-            }
-
-            return !has_whitespace
-                && has_upper
-                && has_lower
-                && has_digit
-                && password.chars().count() >= 8
-                && password.chars().count() <= 60;
+            v.password("password", password);
         }
-        true
     }
 }
 
@@ -85,62 +78,28 @@ pub struct CreateUser {
     password: String,
 }
 
-impl CreateUser {
-    pub fn is_valid(&self) -> bool {
-        if self.name.chars().count() < 1 || self.name.chars().count() > 100 {
-            return false;
-        }
-        if self.surname.chars().count() < 1 || self.surname.chars().count() > 120 {
-            return false;
-        }
-        let email_regex = Regex::new(
-            r"^([a-z0-9_+]([a-z0-9_+.]*[a-z0-9_+])?)@([a-z0-9]+([\-\.]{1}[a-z0-9]+)*\.[a-z]{2,6})",
-        )
-        .unwrap();
-        if !email_regex.is_match(&self.email) {
-            return false;
-        }
-        if self.avatar_url.is_some() && self.avatar_url.as_ref().unwrap().len() > 350 {
-            return false;
-        }
-        if !self.other.is_valid() {
-            return false;
-        }
-        let mut has_whitespace = false;
-        let mut has_upper = false;
-        let mut has_lower = false;
-        let mut has_digit = false;
-
-        for c in self.password.chars() {
-            has_whitespace |= c.is_whitespace();
-            has_lower |= c.is_lowercase();
-            has_upper |= c.is_uppercase();
-            has_digit |= c.is_digit(10);
+impl Validate for CreateUser {
+    fn validate(&self, v: &mut Validator) {
+        v.length("name", &self.name, 1..=100);
+        v.length("surname", &self.surname, 1..=120);
+        v.email("email", &self.email);
+        if let Some(ref avatar_url) = self.avatar_url {
+            v.url("avatar_url", avatar_url);
         }
-
-        !has_whitespace
-            && has_upper
-            && has_lower
-            && has_digit
-            && self.password.chars().count() >= 8
-            && self.password.chars().count() <= 60
+        v.nested("other", &self.other.0);
+        v.password("password", &self.password);
     }
 }
 
 #[derive(Serialize, Deserialize, FromRow, Clone)]
-struct UserTargetSettings {
-    age: i8,
-    country: String,
+pub struct UserTargetSettings {
+    pub age: i8,
+    pub country: String,
 }
 
-impl UserTargetSettings {
-    pub fn is_valid(&self) -> bool {
-        if self.age < 0 || self.age > 100 {
-            return false;
-        }
-        if self.country.chars().count() != 2 {
-            return false;
-        }
-        true
+impl Validate for UserTargetSettings {
+    fn validate(&self, v: &mut Validator) {
+        v.range("age", self.age, 0..=100);
+        v.country("country", &self.country);
     }
 }
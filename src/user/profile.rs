@@ -1,62 +1,30 @@
 use super::{PatchUser, User, UserProfile};
-use crate::{business::auth::hash_password, AppState};
-use axum::{extract::State, http::StatusCode, Extension, Json};
+use crate::{error::AppError, extract::AppJson, repository::UserRepository, AppState};
+use axum::{extract::State, Extension, Json};
 
-pub async fn get_profile(
-    State(app_state): State<AppState>,
-    Extension(user): Extension<User>,
-) -> Result<Json<UserProfile>, StatusCode> {
-    let user_profile: UserProfile = sqlx::query_as(
-        r#"
-        SELECT * FROM users WHERE email = ?
-        "#,
-    )
-    .bind(user.email)
-    .fetch_one(&app_state.pool)
-    .await
-    .unwrap();
-
-    Ok(Json(user_profile))
+pub async fn get_profile(Extension(user): Extension<User>) -> Json<UserProfile> {
+    Json(user.into())
 }
 
 pub async fn edit_profile(
     State(app_state): State<AppState>,
-    Extension(user): Extension<User>,
-    Json(patch_user): Json<PatchUser>,
-) -> Result<Json<UserProfile>, StatusCode> {
-    if !patch_user.is_valid() {
-        return Err(StatusCode::BAD_REQUEST);
-    }
+    Extension(mut user): Extension<User>,
+    AppJson(patch_user): AppJson<PatchUser>,
+) -> Result<Json<UserProfile>, AppError> {
+    app_state.validation_rules.check(&patch_user)?;
 
-    sqlx::query(
-        r#"
-        UPDATE users
-        SET name = ?, surname = ?, avatar_url = ?, password_hash = ?
-        WHERE email = ?
-        "#,
-    )
-    .bind(patch_user.name.unwrap_or(user.name))
-    .bind(patch_user.surname.unwrap_or(user.surname))
-    .bind(patch_user.avatar_url)
-    .bind(if let Some(ref password) = patch_user.password {
-        hash_password(password).unwrap()
-    } else {
-        user.password_hash
-    })
-    .bind(&user.email)
-    .execute(&app_state.pool)
-    .await
-    .unwrap();
+    if let Some(name) = patch_user.name {
+        user.name = name;
+    }
+    if let Some(surname) = patch_user.surname {
+        user.surname = surname;
+    }
+    user.avatar_url = patch_user.avatar_url;
+    if let Some(ref password) = patch_user.password {
+        user.password_hash = app_state.password_hasher.hash(password).await?;
+    }
 
-    let user_profile: UserProfile = sqlx::query_as(
-        r#"
-        SELECT * FROM users WHERE email = ?
-        "#,
-    )
-    .bind(user.email)
-    .fetch_one(&app_state.pool)
-    .await
-    .unwrap();
+    app_state.repository.update_user(&user).await?;
 
-    Ok(Json(user_profile))
+    Ok(Json(user.into()))
 }
@@ -1,232 +1,148 @@
-use std::collections::HashSet;
-
 use axum::{
-    extract::{
-        connect_info::Connected,
-        rejection::{JsonDataError, LengthLimitError},
-        Path, State,
-    },
+    extract::{Path, State},
     http::StatusCode,
     Extension, Json,
 };
+use serde::Deserialize;
 use uuid::Uuid;
 
 use crate::{
-    business::promo::{Comment, CommentAuthor, Promo},
+    business::promo::Comment,
+    error::{AppError, NO_ACCESS_TO_COMMENT, PROMO_NOT_FOUND, PROMO_OR_COMMENT_NOT_FOUND},
+    extract::AppJson,
+    repository::{CommentRepository, PromoRepository},
     user::User,
+    validation::{Validate, Validator},
     AppState,
 };
 
+#[derive(Deserialize)]
+pub struct CommentText {
+    text: String,
+}
+
+impl Validate for CommentText {
+    fn validate(&self, v: &mut Validator) {
+        v.length("text", &self.text, 10..=1000);
+    }
+}
+
 pub async fn add_comment(
     State(app_state): State<AppState>,
     Extension(user): Extension<User>,
     Path(promo_id): Path<String>,
-    Json(text): Json<String>,
-) -> Result<(StatusCode, Json<Comment>), StatusCode> {
-    let id = Uuid::new_v4().to_string();
-    let comment = Comment {
-        id,
-        text,
-        date: chrono::Utc::now().to_string(),
-        author: CommentAuthor {
-            name: user.name,
-            surname: user.surname,
-            email: user.email,
-            avatar_url: user.avatar_url,
-        },
-    };
-
-    let promo: Option<Promo> = sqlx::query_as(
-        r#"
-        SELECT comments FROM promos WHERE promo_id = ?
-        "#,
-    )
-    .bind(&promo_id)
-    .fetch_optional(&app_state.pool)
-    .await
-    .unwrap();
-
-    if let Some(promo) = promo {
-        let mut comments = promo.comments;
-        comments.insert(comment.clone());
-        sqlx::query(
-            r#"
-                UPDATE promos
-                SET comments = ?
-                WHERE promo_id = ?
-            "#,
-        )
-        .bind(sqlx::types::Json(&comment))
-        .bind(promo_id)
-        .execute(&app_state.pool)
-        .await
-        .unwrap();
-    } else {
-        return Err(StatusCode::BAD_REQUEST);
+    AppJson(comment_text): AppJson<CommentText>,
+) -> Result<(StatusCode, Json<Comment>), AppError> {
+    app_state.validation_rules.check(&comment_text)?;
+    if !app_state.repository.promo_exists(&promo_id).await? {
+        return Err(AppError::NotFound(PROMO_NOT_FOUND));
     }
 
-    Ok((StatusCode::CREATED, Json(comment)))
+    let id = Uuid::new_v4().to_string();
+    app_state
+        .repository
+        .create_comment(&id, &promo_id, &user.id, &comment_text.text)
+        .await?;
+
+    match app_state
+        .repository
+        .retrieve_comment(&promo_id, &id)
+        .await?
+    {
+        Some(comment) => Ok((StatusCode::CREATED, Json(comment))),
+        None => Err(AppError::NotFound(PROMO_OR_COMMENT_NOT_FOUND)),
+    }
 }
 
 pub async fn get_comments(
     State(app_state): State<AppState>,
     Extension(_user): Extension<User>,
     Path(promo_id): Path<String>,
-) -> Result<Json<HashSet<Comment>>, StatusCode> {
-    let promo: Option<Promo> = sqlx::query_as(
-        r#"
-            SELECT * FROM promos WHERE promo_id = ?
-        "#,
-    )
-    .bind(promo_id)
-    .fetch_optional(&app_state.pool)
-    .await
-    .unwrap();
-
-    if let Some(promo) = promo {
-        return Ok(Json(promo.comments.0));
-    } else {
-        return Err(StatusCode::BAD_REQUEST);
+) -> Result<Json<Vec<Comment>>, AppError> {
+    if !app_state.repository.promo_exists(&promo_id).await? {
+        return Err(AppError::NotFound(PROMO_NOT_FOUND));
     }
+
+    let comments = app_state.repository.retrieve_comments(&promo_id).await?;
+
+    Ok(Json(comments))
 }
 
 pub async fn get_comment_by_id(
     State(app_state): State<AppState>,
-    Path(promo_id): Path<String>,
-    Path(comment_id): Path<String>,
-) -> Result<Json<Comment>, StatusCode> {
-    let promo: Option<Promo> = sqlx::query_as(
-        r#"
-            SELECT * FROM promos WHERE promo_id = ?
-        "#,
-    )
-    .bind(promo_id)
-    .fetch_optional(&app_state.pool)
-    .await
-    .unwrap();
-
-    if let Some(promo) = promo {
-        let comments = Vec::from_iter(promo.comments.0.into_iter());
-        if let Some(comment) = comments
-            .into_iter()
-            .find(|comment| comment.id == comment_id)
-        {
-            return Ok(Json(comment));
-        } else {
-            return Err(StatusCode::BAD_REQUEST);
-        }
-    } else {
-        return Err(StatusCode::BAD_REQUEST);
+    Path((promo_id, comment_id)): Path<(String, String)>,
+) -> Result<Json<Comment>, AppError> {
+    match app_state
+        .repository
+        .retrieve_comment(&promo_id, &comment_id)
+        .await?
+    {
+        Some(comment) => Ok(Json(comment)),
+        None => Err(AppError::NotFound(PROMO_OR_COMMENT_NOT_FOUND)),
     }
 }
 
 pub async fn edit_comment(
     State(app_state): State<AppState>,
     Extension(user): Extension<User>,
-    Path(promo_id): Path<String>,
-    Path(comment_id): Path<String>,
-    Json(text): Json<String>,
-) -> Result<Json<Comment>, StatusCode> {
-    let promo: Option<Promo> = sqlx::query_as(
-        r#"
-            SELECT * FROM promos WHERE promo_id = ?
-        "#,
-    )
-    .bind(&promo_id)
-    .fetch_optional(&app_state.pool)
-    .await
-    .unwrap();
-
-    let promo = match promo {
-        Some(promo) => promo,
-        None => return Err(StatusCode::NOT_FOUND),
-    };
+    Path((promo_id, comment_id)): Path<(String, String)>,
+    AppJson(comment_text): AppJson<CommentText>,
+) -> Result<Json<Comment>, AppError> {
+    app_state.validation_rules.check(&comment_text)?;
+
+    match app_state
+        .repository
+        .retrieve_comment_author_id(&promo_id, &comment_id)
+        .await?
+    {
+        Some(author_id) if author_id != user.id => {
+            return Err(AppError::Forbidden(NO_ACCESS_TO_COMMENT))
+        }
+        Some(_) => (),
+        None => return Err(AppError::NotFound(PROMO_OR_COMMENT_NOT_FOUND)),
+    }
 
-    let comments = Vec::from_iter(promo.comments.0.into_iter());
+    app_state
+        .repository
+        .update_comment(&comment_id, &comment_text.text)
+        .await?;
 
-    match comments
-        .clone()
-        .into_iter()
-        .find(|comment| comment.id == comment_id)
+    match app_state
+        .repository
+        .retrieve_comment(&promo_id, &comment_id)
+        .await?
     {
-        Some(mut comment) => {
-            if comment.author.email != user.email {
-                return Err(StatusCode::FORBIDDEN);
-            }
-            comment.text = text;
-            sqlx::query(
-                r#"
-                    UPDATE promos
-                    SET comments = ?
-                    WHERE promo_id = ?
-                "#,
-            )
-            .bind(sqlx::types::Json(comments))
-            .bind(promo_id)
-            .execute(&app_state.pool)
-            .await
-            .unwrap();
-
-            Ok(Json(comment.clone()))
-        }
-        None => return Err(StatusCode::NOT_FOUND),
+        Some(comment) => Ok(Json(comment)),
+        None => Err(AppError::NotFound(PROMO_OR_COMMENT_NOT_FOUND)),
     }
 }
 
 pub async fn delete_comment(
     State(app_state): State<AppState>,
     Extension(user): Extension<User>,
-    Path(promo_id): Path<String>,
-    Path(comment_id): Path<String>,
-    Json(text): Json<String>,
-) -> Result<Json<Comment>, StatusCode> {
-    let promo: Option<Promo> = sqlx::query_as(
-        r#"
-            SELECT * FROM promos WHERE promo_id = ?
-        "#,
-    )
-    .bind(&promo_id)
-    .fetch_optional(&app_state.pool)
-    .await
-    .unwrap();
-
-    let promo = match promo {
-        Some(promo) => promo,
-        None => return Err(StatusCode::NOT_FOUND),
-    };
-
-    let mut comments = Vec::from_iter(promo.comments.0.into_iter());
-
-    match comments
-        .clone()
-        .into_iter()
-        .find(|comment| comment.id == comment_id)
+    Path((promo_id, comment_id)): Path<(String, String)>,
+) -> Result<Json<Comment>, AppError> {
+    match app_state
+        .repository
+        .retrieve_comment_author_id(&promo_id, &comment_id)
+        .await?
     {
-        Some(comment) => {
-            if comment.author.email != user.email {
-                return Err(StatusCode::FORBIDDEN);
-            }
-
-            let idx = comments
-                .iter()
-                .rposition(|comment| comment.id == comment_id);
-            comments.remove(idx.unwrap());
-
-            sqlx::query(
-                r#"
-                    UPDATE promos
-                    SET comments = ?
-                    WHERE promo_id = ?
-                "#,
-            )
-            .bind(sqlx::types::Json(comments))
-            .bind(promo_id)
-            .execute(&app_state.pool)
-            .await
-            .unwrap();
-
-            Ok(Json(comment.clone()))
+        Some(author_id) if author_id != user.id => {
+            return Err(AppError::Forbidden(NO_ACCESS_TO_COMMENT))
         }
-        None => return Err(StatusCode::NOT_FOUND),
+        Some(_) => (),
+        None => return Err(AppError::NotFound(PROMO_OR_COMMENT_NOT_FOUND)),
     }
+
+    let Some(comment) = app_state
+        .repository
+        .retrieve_comment(&promo_id, &comment_id)
+        .await?
+    else {
+        return Err(AppError::NotFound(PROMO_OR_COMMENT_NOT_FOUND));
+    };
+
+    app_state.repository.delete_comment(&comment_id).await?;
+
+    Ok(Json(comment))
 }
@@ -0,0 +1,32 @@
+use crate::{
+    error::AppError, extract::AppQuery, pagination::Pagination, repository::ActivationRepository,
+    user::User, AppState,
+};
+use axum::{
+    extract::State,
+    http::{HeaderMap, HeaderValue, StatusCode},
+    response::{IntoResponse, Response},
+    Extension, Json,
+};
+
+pub async fn get_history(
+    State(app_state): State<AppState>,
+    Extension(user): Extension<User>,
+    AppQuery(pagination): AppQuery<Pagination>,
+) -> Result<Response, AppError> {
+    let total = app_state.repository.count_activations(&user.id).await?;
+
+    let history = app_state
+        .repository
+        .retrieve_activation_history(
+            &user.id,
+            app_state.calendar.today(),
+            pagination.limit as i64,
+            pagination.offset as i64,
+        )
+        .await?;
+
+    let mut headers = HeaderMap::new();
+    headers.insert("X-Total-Count", HeaderValue::from(total));
+    Ok((StatusCode::OK, headers, Json(history)).into_response())
+}
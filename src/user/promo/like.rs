@@ -1,7 +1,11 @@
-use crate::{business::promo::Promo, user::User, AppState};
+use crate::{
+    error::{AppError, PROMO_NOT_FOUND},
+    repository::PromoRepository,
+    user::User,
+    AppState,
+};
 use axum::{
     extract::{Path, State},
-    http::StatusCode,
     Extension,
 };
 
@@ -9,35 +13,13 @@ pub async fn add_like(
     State(app_state): State<AppState>,
     Extension(user): Extension<User>,
     Path(promo_id): Path<String>,
-) -> Result<(), StatusCode> {
-    let promo: Option<Promo> = sqlx::query_as(
-        r#"
-            SELECT * FROM promos WHERE promo_id = ?
-        "#,
-    )
-    .bind(&promo_id)
-    .fetch_optional(&app_state.pool)
-    .await
-    .unwrap();
-
-    if let Some(mut promo) = promo {
-        promo.likes.0.insert(user.email);
-        sqlx::query(
-            r#"
-                UPDATE promos
-                SET likes = ?
-                WHERE promo_id = ?
-            "#,
-        )
-        .bind(promo.likes)
-        .bind(promo_id)
-        .execute(&app_state.pool)
-        .await
-        .unwrap();
-    } else {
-        return Err(StatusCode::BAD_REQUEST);
+) -> Result<(), AppError> {
+    if !app_state.repository.promo_exists(&promo_id).await? {
+        return Err(AppError::NotFound(PROMO_NOT_FOUND));
     }
 
+    app_state.repository.add_like(&promo_id, &user.id).await?;
+
     Ok(())
 }
 
@@ -45,34 +27,15 @@ pub async fn remove_like(
     State(app_state): State<AppState>,
     Extension(user): Extension<User>,
     Path(promo_id): Path<String>,
-) -> Result<(), StatusCode> {
-    let promo: Option<Promo> = sqlx::query_as(
-        r#"
-            SELECT * FROM promos WHERE promo_id = ?
-        "#,
-    )
-    .bind(&promo_id)
-    .fetch_optional(&app_state.pool)
-    .await
-    .unwrap();
-
-    if let Some(mut promo) = promo {
-        promo.likes.0.remove(&user.email);
-        sqlx::query(
-            r#"
-                UPDATE promos
-                SET likes = ?
-                WHERE promo_id = ?
-            "#,
-        )
-        .bind(promo.likes)
-        .bind(promo_id)
-        .execute(&app_state.pool)
-        .await
-        .unwrap();
-    } else {
-        return Err(StatusCode::BAD_REQUEST);
+) -> Result<(), AppError> {
+    if !app_state.repository.promo_exists(&promo_id).await? {
+        return Err(AppError::NotFound(PROMO_NOT_FOUND));
     }
 
+    app_state
+        .repository
+        .remove_like(&promo_id, &user.id)
+        .await?;
+
     Ok(())
 }
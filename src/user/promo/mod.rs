@@ -1,136 +1,105 @@
 use super::User;
 use crate::{
-    business::promo::{Promo, PromoForUser},
+    business::promo::PromoForUser,
+    error::{AppError, ACTIVATION_FORBIDDEN, PROMO_NOT_FOUND},
+    extract::AppJson,
+    repository::{ActivationRepository, PromoRepository},
+    validation::FieldError,
     AppState,
 };
 use axum::{
     extract::{Path, State},
-    http::StatusCode,
     Extension, Json,
 };
 use serde_json::{json, Value};
 
 pub mod comments;
+pub mod history;
 pub mod like;
+pub mod search;
 
 pub async fn get_promo(
     State(app_state): State<AppState>,
     Extension(user): Extension<User>,
     Path(id): Path<String>,
-) -> Result<Json<PromoForUser>, StatusCode> {
-    let promo: Option<Promo> = sqlx::query_as(
-        r#"
-        SELECT * FROM promos WHERE promo_id = ? 
-        "#,
-    )
-    .bind(id)
-    .fetch_optional(&app_state.pool)
-    .await
-    .unwrap();
-
-    if promo.is_none() {
-        return Err(StatusCode::BAD_REQUEST);
+) -> Result<Json<PromoForUser>, AppError> {
+    match app_state
+        .repository
+        .retrieve_promo_for_user(&user.id, &id, app_state.calendar.today())
+        .await?
+    {
+        Some(promo) => Ok(Json(promo)),
+        None => Err(AppError::NotFound(PROMO_NOT_FOUND)),
     }
-    let promo = promo.unwrap();
-
-    Ok(Json(PromoForUser {
-        promo_id: promo.promo_id,
-        company_id: promo.company_id,
-        company_name: promo.company_name,
-        description: promo.description,
-        image_url: promo.image_url,
-        active: promo.active,
-        is_activated_by_user: promo.activated_users.0.contains(&user.email),
-        like_count: promo.likes.0.len() as i32,
-        is_liked_by_user: promo.likes.0.contains(&user.email),
-        comment_count: promo.comments.0.len() as i32,
-    }))
 }
 
 pub async fn activate_promo(
     State(app_state): State<AppState>,
     Extension(user): Extension<User>,
     Path(id): Path<String>,
-    Json(promo_name): Json<Option<String>>,
-) -> Result<Json<Value>, StatusCode> {
-    let promo: Option<Promo> = sqlx::query_as(
-        r#"
-            SELECT * FROM promos WHERE promo_id = ?
-        "#,
-    )
-    .bind(id)
-    .fetch_optional(&app_state.pool)
-    .await
-    .unwrap();
-
-    let mut promo = match promo {
-        Some(promo) => promo,
-        None => return Err(StatusCode::NOT_FOUND),
+    AppJson(promo_name): AppJson<Option<String>>,
+) -> Result<Json<Value>, AppError> {
+    let promo = match app_state
+        .repository
+        .retrieve_promo(&id, app_state.calendar.today())
+        .await?
+    {
+        Some(promo) if promo.published_at.is_some() => promo,
+        _ => return Err(AppError::NotFound(PROMO_NOT_FOUND)),
     };
 
     if !promo.active
-        || promo.max_count == promo.activated_users.0.len() as i32
         || promo.target.0.age_from.unwrap_or(user.other.age) > user.other.age
         || promo.target.0.age_until.unwrap_or(user.other.age) < user.other.age
     {
-        return Err(StatusCode::FORBIDDEN);
+        return Err(AppError::Forbidden(ACTIVATION_FORBIDDEN));
     }
 
     if promo.mode == "UNIQUE" {
-        if promo_name.is_none() {
-            return Err(StatusCode::BAD_REQUEST);
-        }
-        if !promo
-            .promo_unique
-            .as_ref()
-            .unwrap()
-            .contains(&promo_name.unwrap())
+        let Some(promo_name) = promo_name else {
+            return Err(AppError::Validation(vec![FieldError {
+                field: "promo".to_string(),
+                code: "required",
+            }]));
+        };
+        if !app_state
+            .repository
+            .unique_code_exists(&promo.promo_id, &promo_name)
+            .await?
         {
-            return Err(StatusCode::NOT_FOUND);
-        }
-
-        if promo.promo_unique.as_ref().unwrap().len() < 1 {
-            return Err(StatusCode::FORBIDDEN);
+            return Err(AppError::NotFound(PROMO_NOT_FOUND));
         }
+    }
 
-        promo.activated_users.0.insert(user.email);
-        let mut promos = Vec::from_iter(promo.promo_unique.as_ref().unwrap().iter());
-
-        let activated_promo = promos.pop().unwrap();
-
-        sqlx::query(
-            r#"
-                UPDATE promos
-                SET activated_users = ?, promo_unique = ?
-                WHERE promo_id = ?
-            "#,
-        )
-        .bind(sqlx::types::Json(promo.activated_users))
-        .bind(sqlx::types::Json(Some(promo.promo_unique.clone())))
-        .execute(&app_state.pool)
+    match app_state
+        .antifraud
+        .validate(&user.email, &promo.promo_id)
         .await
-        .unwrap();
-
-        return Ok(Json(json!({
-            "text": activated_promo
-        })));
-    } else {
-        promo.activated_users.0.insert(user.email);
+    {
+        Ok(verdict) if verdict.ok => (),
+        result => {
+            if let Err(err) = result {
+                tracing::warn!("{}", err);
+            }
+            return Err(AppError::Forbidden(ACTIVATION_FORBIDDEN));
+        }
+    }
 
-        sqlx::query(
-            r#"
-                UPDATE promos
-                SET activated_users = ?
-                WHERE promo_id = ?
-            "#,
+    let activated_promo = match app_state
+        .repository
+        .activate_promo(
+            &promo.promo_id,
+            &user.id,
+            &user.other.country,
+            app_state.calendar.now(),
         )
-        .bind(sqlx::types::Json(promo.activated_users))
-        .execute(&app_state.pool)
-        .await
-        .unwrap();
+        .await?
+    {
+        Some(code) => code,
+        None => return Err(AppError::Forbidden(ACTIVATION_FORBIDDEN)),
+    };
 
-        Ok(Json(json!({
-            "text": promo.promo_common.unwrap()
-        })))
-    }
+    Ok(Json(json!({
+        "text": activated_promo
+    })))
 }
@@ -0,0 +1,241 @@
+use crate::{
+    business::promo::PromoForUser,
+    error::AppError,
+    extract::AppQuery,
+    pagination::Pagination,
+    repository::PromoRepository,
+    user::{feed::FeedFilter, User},
+    validation::{FieldError, Validate, Validator},
+    AppState,
+};
+use axum::{
+    extract::State,
+    http::{HeaderMap, HeaderValue, StatusCode},
+    response::{IntoResponse, Response},
+    Extension, Json,
+};
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+
+// The repository wraps matched words of a snippet in these private-use
+// characters, which can't clash with promo text once it has been escaped.
+pub const MATCH_START: &str = "\u{E000}";
+pub const MATCH_END: &str = "\u{E001}";
+
+#[derive(Deserialize)]
+pub struct SearchText {
+    q: Option<String>,
+}
+
+impl SearchText {
+    // Hands back the query it has checked, so the handler need not unwrap it.
+    fn validate(&self, v: &mut Validator) -> Option<&str> {
+        let q = v.required("q", &self.q)?;
+        v.length("q", q, 1..=100);
+        v.check("q", q.chars().any(char::is_alphanumeric));
+        Some(q)
+    }
+}
+
+#[derive(Serialize, FromRow)]
+pub struct PromoSearchHit {
+    #[serde(flatten)]
+    #[sqlx(flatten)]
+    pub promo: PromoForUser,
+    // A fragment of the description as HTML, matches wrapped in `<mark>`.
+    pub snippet: String,
+}
+
+// Ranks promos the user could see in the feed by how well their description
+// and company name match `q`, nudged up by their like count.
+pub async fn search_promos(
+    State(app_state): State<AppState>,
+    Extension(user): Extension<User>,
+    AppQuery(text): AppQuery<SearchText>,
+    AppQuery(pagination): AppQuery<Pagination>,
+    AppQuery(filter): AppQuery<FeedFilter>,
+) -> Result<Response, AppError> {
+    let mut validator = app_state.validation_rules.validator();
+    let q = text.validate(&mut validator);
+    filter.validate(&mut validator);
+    validator.finish()?;
+
+    let Some(q) = q else {
+        return Err(AppError::Validation(vec![FieldError {
+            field: "q".to_string(),
+            code: "required",
+        }]));
+    };
+    let today = app_state.calendar.today();
+    let total = app_state
+        .repository
+        .count_search(&user.id, q, &filter, today)
+        .await?;
+
+    let mut hits = app_state
+        .repository
+        .search_promos(
+            &user.id,
+            q,
+            &filter,
+            today,
+            pagination.limit as i64,
+            pagination.offset as i64,
+        )
+        .await?;
+    for hit in hits.iter_mut() {
+        hit.snippet = highlight(&hit.snippet);
+    }
+
+    let mut headers = HeaderMap::new();
+    headers.insert("X-Total-Count", HeaderValue::from(total));
+    Ok((StatusCode::OK, headers, Json(hits)).into_response())
+}
+
+fn highlight(snippet: &str) -> String {
+    let mut html = String::with_capacity(snippet.len());
+    for c in snippet.chars() {
+        match c {
+            '&' => html.push_str("&amp;"),
+            '<' => html.push_str("&lt;"),
+            '>' => html.push_str("&gt;"),
+            '"' => html.push_str("&quot;"),
+            '\'' => html.push_str("&#39;"),
+            _ => html.push(c),
+        }
+    }
+    html.replace(MATCH_START, "<mark>")
+        .replace(MATCH_END, "</mark>")
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        business::promo::Target,
+        repository::PromoRepository,
+        testing::{self, any_target, targeted_promo},
+    };
+    use axum::http::StatusCode;
+    use chrono::{Duration, Utc};
+    use serde_json::Value;
+
+    #[cfg_attr(feature = "postgres", ignore = "needs TEST_DATABASE_URL")]
+    #[tokio::test]
+    async fn hits_are_ranked_by_relevance_then_likes() {
+        let repository = testing::repository().await;
+        let user = testing::user(&repository, 30, "ru").await;
+        let now = Utc::now();
+        let once = targeted_promo(
+            &repository,
+            "Seasonal offer on many things in the store, ask for a discount at the counter",
+            any_target(),
+            now,
+        )
+        .await;
+        let thrice = targeted_promo(
+            &repository,
+            "Discount on shoes, discount on coats, discount on everything",
+            any_target(),
+            now - Duration::hours(1),
+        )
+        .await;
+        // Equal matches, the older one liked: without the like it would come
+        // last, as ties go to the newest.
+        let plain =
+            targeted_promo(&repository, "Free delivery this weekend", any_target(), now).await;
+        let liked = targeted_promo(
+            &repository,
+            "Free delivery this weekend",
+            any_target(),
+            now - Duration::hours(1),
+        )
+        .await;
+        let other = testing::user(&repository, 30, "ru").await;
+        repository
+            .add_like(&liked.promo_id, &other.id)
+            .await
+            .unwrap();
+        let state = testing::app_state(repository, "127.0.0.1:9").await;
+        let access_token = testing::user_token(&state, &user).await;
+        let base_url = testing::serve(state).await;
+        let search = |q: &'static str| {
+            let request = reqwest::Client::new()
+                .get(format!("{}/api/user/promo/search", base_url))
+                .bearer_auth(&access_token)
+                .query(&[("q", q)]);
+            async move {
+                let response = request.send().await.unwrap();
+                assert_eq!(response.status(), StatusCode::OK);
+                let hits: Vec<Value> = response.json().await.unwrap();
+                hits.iter()
+                    .map(|hit| hit["promo_id"].as_str().unwrap().to_string())
+                    .collect::<Vec<_>>()
+            }
+        };
+
+        assert_eq!(
+            search("discount").await,
+            [thrice.promo_id.clone(), once.promo_id.clone()]
+        );
+        assert_eq!(
+            search("delivery").await,
+            [liked.promo_id.clone(), plain.promo_id.clone()]
+        );
+    }
+
+    #[cfg_attr(feature = "postgres", ignore = "needs TEST_DATABASE_URL")]
+    #[tokio::test]
+    async fn hits_are_limited_to_the_users_target() {
+        let repository = testing::repository().await;
+        let user = testing::user(&repository, 30, "ru").await;
+        let now = Utc::now();
+        let matching = targeted_promo(
+            &repository,
+            "Discount for adults in Russia",
+            Target {
+                age_from: Some(18),
+                age_until: Some(30),
+                country: Some("RU".to_string()),
+                ..any_target()
+            },
+            now,
+        )
+        .await;
+        for target in [
+            Target {
+                age_from: Some(31),
+                ..any_target()
+            },
+            Target {
+                age_until: Some(29),
+                ..any_target()
+            },
+            Target {
+                country: Some("fr".to_string()),
+                ..any_target()
+            },
+        ] {
+            targeted_promo(&repository, "Discount for someone else", target, now).await;
+        }
+        let state = testing::app_state(repository, "127.0.0.1:9").await;
+        let access_token = testing::user_token(&state, &user).await;
+        let base_url = testing::serve(state).await;
+
+        let response = reqwest::Client::new()
+            .get(format!("{}/api/user/promo/search", base_url))
+            .bearer_auth(&access_token)
+            .query(&[("q", "discount")])
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()["X-Total-Count"], "1");
+        let hits: Vec<Value> = response.json().await.unwrap();
+        assert_eq!(hits.len(), 1);
+        assert_eq!(hits[0]["promo_id"], matching.promo_id.as_str());
+        assert!(hits[0]["snippet"]
+            .as_str()
+            .unwrap()
+            .contains("<mark>Discount</mark>"));
+    }
+}
@@ -0,0 +1,64 @@
+use crate::{
+    error::AppError,
+    repository::UserSessionRepository,
+    sessions::{self, UserSession},
+    AppState,
+};
+use axum::{
+    extract::{Path, State},
+    Extension, Json,
+};
+use chrono::Utc;
+use serde_json::{json, Value};
+
+pub async fn list_sessions(
+    State(app_state): State<AppState>,
+    Extension(current): Extension<UserSession>,
+) -> Result<Json<Vec<Value>>, AppError> {
+    let sessions = app_state
+        .repository
+        .retrieve_user_sessions(&current.user_id, Utc::now())
+        .await?;
+
+    Ok(Json(
+        sessions
+            .into_iter()
+            .map(|session| {
+                json!({
+                    "id": session.id,
+                    "device": session.device,
+                    "ip_address": session.ip_address,
+                    "user_agent": session.user_agent,
+                    "created_at": session.created_at,
+                    "last_seen_at": session.last_seen_at,
+                    "current": session.id == current.id
+                })
+            })
+            .collect(),
+    ))
+}
+
+// Revoking the current session signs the caller out.
+pub async fn revoke_session(
+    State(app_state): State<AppState>,
+    Extension(current): Extension<UserSession>,
+    Path(id): Path<String>,
+) -> Result<Json<Value>, AppError> {
+    sessions::revoke(&app_state, &current.user_id, &id).await?;
+
+    Ok(Json(json!({
+        "status": "ok"
+    })))
+}
+
+// Signs the user out everywhere except on the calling device.
+pub async fn revoke_other_sessions(
+    State(app_state): State<AppState>,
+    Extension(current): Extension<UserSession>,
+) -> Result<Json<Value>, AppError> {
+    sessions::revoke_others(&app_state, &current.user_id, &current.id).await?;
+
+    Ok(Json(json!({
+        "status": "ok"
+    })))
+}
@@ -0,0 +1,151 @@
+use crate::error::AppError;
+use countries::is_country_code;
+use password::PasswordPolicy;
+use regex::Regex;
+use reqwest::Url;
+use serde::Serialize;
+use std::{ops::RangeInclusive, sync::LazyLock};
+
+pub mod countries;
+pub mod password;
+
+const MAX_URL_LENGTH: usize = 350;
+
+static EMAIL_REGEX: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(
+        r"^([a-z0-9_+]([a-z0-9_+.]*[a-z0-9_+])?)@([a-z0-9]+([\-\.]{1}[a-z0-9]+)*\.[a-z]{2,6})",
+    )
+    .unwrap()
+});
+
+// One broken rule of one request field. `field` is a dotted path such as
+// `target.age_from`, `code` names the rule.
+#[derive(Serialize, Clone, Debug)]
+pub struct FieldError {
+    pub field: String,
+    pub code: &'static str,
+}
+
+pub trait Validate {
+    fn validate(&self, v: &mut Validator);
+}
+
+// The configurable part of validation, loaded once at startup.
+#[derive(Clone)]
+pub struct ValidationRules {
+    pub password_policy: PasswordPolicy,
+}
+
+impl ValidationRules {
+    pub fn from_env() -> Result<Self, String> {
+        Ok(ValidationRules {
+            password_policy: PasswordPolicy::from_env()?,
+        })
+    }
+
+    pub fn validator(&self) -> Validator<'_> {
+        Validator {
+            rules: self,
+            prefix: String::new(),
+            errors: vec![],
+        }
+    }
+
+    pub fn check<T: Validate>(&self, value: &T) -> Result<(), AppError> {
+        let mut v = self.validator();
+        value.validate(&mut v);
+        v.finish()
+    }
+}
+
+// Collects every broken rule instead of stopping at the first one, so the
+// client learns about all of its mistakes at once.
+pub struct Validator<'a> {
+    rules: &'a ValidationRules,
+    prefix: String,
+    errors: Vec<FieldError>,
+}
+
+impl Validator<'_> {
+    pub fn finish(self) -> Result<(), AppError> {
+        if self.errors.is_empty() {
+            Ok(())
+        } else {
+            Err(AppError::Validation(self.errors))
+        }
+    }
+
+    pub fn fail(&mut self, field: &str, code: &'static str) {
+        self.errors.push(FieldError {
+            field: format!("{}{}", self.prefix, field),
+            code,
+        });
+    }
+
+    pub fn check(&mut self, field: &str, valid: bool) {
+        if !valid {
+            self.fail(field, "invalid");
+        }
+    }
+
+    pub fn required<'v, T>(&mut self, field: &str, value: &'v Option<T>) -> Option<&'v T> {
+        if value.is_none() {
+            self.fail(field, "required");
+        }
+        value.as_ref()
+    }
+
+    // Counts characters, not bytes.
+    pub fn length(&mut self, field: &str, value: &str, range: RangeInclusive<usize>) {
+        if !range.contains(&value.chars().count()) {
+            self.fail(field, "length");
+        }
+    }
+
+    pub fn range<T: PartialOrd>(&mut self, field: &str, value: T, range: RangeInclusive<T>) {
+        if !range.contains(&value) {
+            self.fail(field, "range");
+        }
+    }
+
+    pub fn email(&mut self, field: &str, value: &str) {
+        self.length(field, value, 8..=120);
+        if !EMAIL_REGEX.is_match(value) {
+            self.fail(field, "email");
+        }
+    }
+
+    pub fn url(&mut self, field: &str, value: &str) {
+        let valid = value.len() <= MAX_URL_LENGTH
+            && Url::parse(value).is_ok_and(|url| matches!(url.scheme(), "http" | "https"));
+        if !valid {
+            self.fail(field, "url");
+        }
+    }
+
+    pub fn country(&mut self, field: &str, value: &str) {
+        if !is_country_code(value) {
+            self.fail(field, "country");
+        }
+    }
+
+    // A new password has to follow the current policy.
+    pub fn password(&mut self, field: &str, value: &str) {
+        for code in self.rules.password_policy.violations(value) {
+            self.fail(field, code);
+        }
+    }
+
+    // A password typed to prove who the client is is only bounded in length,
+    // so that tightening the policy does not lock out existing accounts.
+    pub fn current_password(&mut self, field: &str, value: &str) {
+        self.length(field, value, 1..=self.rules.password_policy.max_length);
+    }
+
+    pub fn nested<T: Validate>(&mut self, field: &str, value: &T) {
+        let prefix = self.prefix.clone();
+        self.prefix = format!("{}{}.", prefix, field);
+        value.validate(self);
+        self.prefix = prefix;
+    }
+}
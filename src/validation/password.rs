@@ -0,0 +1,90 @@
+use std::{collections::HashSet, env, fs, sync::Arc};
+
+// Composition rules for new passwords, read from the `PASSWORD_*` variables.
+// The defaults are the rules the API has always enforced. When
+// `PASSWORD_BREACHED_LIST` names a file, every password listed in it (one per
+// line, compared case-insensitively) is refused as well.
+#[derive(Clone)]
+pub struct PasswordPolicy {
+    pub min_length: usize,
+    pub max_length: usize,
+    require_uppercase: bool,
+    require_lowercase: bool,
+    require_digit: bool,
+    require_special: bool,
+    breached: Arc<HashSet<String>>,
+}
+
+impl PasswordPolicy {
+    pub fn from_env() -> Result<Self, String> {
+        let breached = match env::var("PASSWORD_BREACHED_LIST") {
+            Ok(path) => fs::read_to_string(&path)
+                .map_err(|err| format!("{}: {}", path, err))?
+                .lines()
+                .map(|line| line.trim().to_lowercase())
+                .filter(|line| !line.is_empty())
+                .collect(),
+            Err(_) => HashSet::new(),
+        };
+
+        Ok(PasswordPolicy {
+            min_length: number("PASSWORD_MIN_LENGTH", 8)?,
+            max_length: number("PASSWORD_MAX_LENGTH", 60)?,
+            require_uppercase: flag("PASSWORD_REQUIRE_UPPERCASE", true)?,
+            require_lowercase: flag("PASSWORD_REQUIRE_LOWERCASE", true)?,
+            require_digit: flag("PASSWORD_REQUIRE_DIGIT", true)?,
+            require_special: flag("PASSWORD_REQUIRE_SPECIAL", false)?,
+            breached: Arc::new(breached),
+        })
+    }
+
+    // Returns the codes of the rules the password breaks.
+    pub fn violations(&self, password: &str) -> Vec<&'static str> {
+        let mut has_whitespace = false;
+        let mut has_upper = false;
+        let mut has_lower = false;
+        let mut has_digit = false;
+        let mut has_special = false;
+        for c in password.chars() {
+            has_whitespace |= c.is_whitespace();
+            has_lower |= c.is_lowercase();
+            has_upper |= c.is_uppercase();
+            has_digit |= c.is_ascii_digit();
+            has_special |= !c.is_alphanumeric() && !c.is_whitespace();
+        }
+
+        let length = password.chars().count();
+        [
+            (length < self.min_length, "password_too_short"),
+            (length > self.max_length, "password_too_long"),
+            (has_whitespace, "password_whitespace"),
+            (self.require_uppercase && !has_upper, "password_uppercase"),
+            (self.require_lowercase && !has_lower, "password_lowercase"),
+            (self.require_digit && !has_digit, "password_digit"),
+            (self.require_special && !has_special, "password_special"),
+            (
+                self.breached.contains(&password.to_lowercase()),
+                "password_breached",
+            ),
+        ]
+        .into_iter()
+        .filter_map(|(broken, code)| broken.then_some(code))
+        .collect()
+    }
+}
+
+fn number(name: &str, default: usize) -> Result<usize, String> {
+    match env::var(name) {
+        Ok(value) => value.parse().map_err(|err| format!("{}: {}", name, err)),
+        Err(_) => Ok(default),
+    }
+}
+
+fn flag(name: &str, default: bool) -> Result<bool, String> {
+    match env::var(name).as_deref() {
+        Ok("true" | "1") => Ok(true),
+        Ok("false" | "0") => Ok(false),
+        Ok(value) => Err(format!("{}: expected true or false, got {}", name, value)),
+        Err(_) => Ok(default),
+    }
+}